@@ -0,0 +1,109 @@
+//! Tab completion at the prompt: command names in the first word, and
+//! function names after commands that take a location or expression.
+
+use rustyline::completion::Completer;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Helper};
+
+use crate::{COMMANDS, resolve_command};
+
+/// Commands whose arguments are completed from the function names.
+const SYMBOL_COMMANDS: &[&str] = &["break", "tbreak", "print"];
+
+/// Completion state of the line editor.
+#[derive(Debug, Default)]
+pub struct Completion {
+    /// Function names of the current program, sorted; empty while there
+    /// is none to read them from.
+    pub symbols: Vec<String>,
+}
+
+impl Completer for Completion {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let line = &line[..pos];
+        let start = line.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        let word = &line[start..];
+        let candidates = match line[..start].split_whitespace().next() {
+            None => {
+                let mut names: Vec<String> = COMMANDS
+                    .iter()
+                    .map(|spec| spec.name)
+                    .filter(|name| name.starts_with(word))
+                    .map(str::to_string)
+                    .collect();
+                names.sort_unstable();
+                names
+            }
+            Some(command)
+                if resolve_command(command)
+                    .is_ok_and(|spec| SYMBOL_COMMANDS.contains(&spec.name)) =>
+            {
+                let first = self.symbols.partition_point(|name| name.as_str() < word);
+                self.symbols[first..]
+                    .iter()
+                    .take_while(|name| name.starts_with(word))
+                    .cloned()
+                    .collect()
+            }
+            Some(_) => Vec::new(),
+        };
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for Completion {
+    type Hint = String;
+}
+
+impl Highlighter for Completion {}
+
+impl Validator for Completion {}
+
+impl Helper for Completion {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustyline::history::DefaultHistory;
+
+    fn complete(completion: &Completion, line: &str) -> (usize, Vec<String>) {
+        let history = DefaultHistory::new();
+        completion
+            .complete(line, line.len(), &Context::new(&history))
+            .unwrap()
+    }
+
+    #[test]
+    fn completes_commands_then_symbols_by_position() {
+        let completion = Completion {
+            symbols: vec!["main".into(), "malloc".into(), "tick".into()],
+        };
+        assert_eq!(
+            complete(&completion, "disa"),
+            (0, vec!["disable".to_string(), "disassemble".to_string()])
+        );
+        assert_eq!(
+            complete(&completion, "b ma"),
+            (2, vec!["main".to_string(), "malloc".to_string()])
+        );
+        assert_eq!(
+            complete(&completion, "tbreak  ti"),
+            (8, vec!["tick".to_string()])
+        );
+        // Other commands' arguments are not function names.
+        assert_eq!(complete(&completion, "frame ma"), (6, Vec::new()));
+        assert_eq!(
+            complete(&Completion::default(), "print ma"),
+            (6, Vec::new())
+        );
+    }
+}
@@ -0,0 +1,84 @@
+//! Where fdb keeps its own files, following the XDG base directory
+//! specification.
+
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+/// History file in the current directory, used by `--local-history` and
+/// when no state directory can be found.
+pub const LOCAL_HISTORY: &str = ".fdb_history";
+
+/// The file the prompt's history is loaded from and saved to: `explicit`
+/// if given, else `$XDG_STATE_HOME/fdb/history`, falling back to
+/// `~/.local/state/fdb/history`.
+pub fn history_file(explicit: Option<PathBuf>, local: bool) -> PathBuf {
+    resolve_history(
+        explicit,
+        local,
+        std::env::var_os("XDG_STATE_HOME"),
+        std::env::var_os("HOME"),
+    )
+}
+
+fn resolve_history(
+    explicit: Option<PathBuf>,
+    local: bool,
+    state_home: Option<OsString>,
+    home: Option<OsString>,
+) -> PathBuf {
+    if let Some(path) = explicit {
+        return path;
+    }
+    if local {
+        return LOCAL_HISTORY.into();
+    }
+    // The specification says relative values are invalid and to be ignored.
+    let absolute = |var: Option<OsString>| var.map(PathBuf::from).filter(|p| p.is_absolute());
+    absolute(state_home)
+        .or_else(|| absolute(home).map(|home| home.join(".local/state")))
+        .map(|state| state.join("fdb/history"))
+        .unwrap_or_else(|| LOCAL_HISTORY.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(
+        explicit: Option<&str>,
+        local: bool,
+        state: Option<&str>,
+        home: Option<&str>,
+    ) -> PathBuf {
+        resolve_history(
+            explicit.map(PathBuf::from),
+            local,
+            state.map(OsString::from),
+            home.map(OsString::from),
+        )
+    }
+
+    #[test]
+    fn history_prefers_the_flag_then_the_state_directory() {
+        assert_eq!(
+            resolve(Some("h"), true, Some("/state"), Some("/home/u")),
+            PathBuf::from("h")
+        );
+        assert_eq!(
+            resolve(None, true, Some("/state"), None),
+            PathBuf::from(LOCAL_HISTORY)
+        );
+        assert_eq!(
+            resolve(None, false, Some("/state"), Some("/home/u")),
+            PathBuf::from("/state/fdb/history")
+        );
+        assert_eq!(
+            resolve(None, false, Some("state"), Some("/home/u")),
+            PathBuf::from("/home/u/.local/state/fdb/history")
+        );
+        assert_eq!(
+            resolve(None, false, None, None),
+            PathBuf::from(LOCAL_HISTORY)
+        );
+    }
+}
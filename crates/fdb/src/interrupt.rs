@@ -0,0 +1,125 @@
+//! Ctrl-C while the program runs stops the program, not the debugger.
+
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use libfdb::ProcessState;
+use libfdb::process::ProcessHandle;
+use libfdb::signals::request_interrupt;
+use nix::libc;
+use nix::sys::signal::{SaFlags, SigAction, SigHandler, SigSet, Signal, sigaction};
+use nix::unistd::{Pid, getpgid, getpgrp};
+
+/// How long each wait lasts before checking for Ctrl-C.
+const POLL: Duration = Duration::from_millis(50);
+
+/// Set by the SIGINT handler, cleared once acted on.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Process the SIGINT handler stops, or 0 when nothing is running that
+/// the terminal's Ctrl-C would not reach by itself.
+static TARGET: AtomicI32 = AtomicI32::new(0);
+
+extern "C" fn on_sigint(_: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+    let pid = TARGET.load(Ordering::SeqCst);
+    if pid != 0 {
+        request_interrupt(Pid::from_raw(pid));
+    }
+}
+
+/// Catch SIGINT for the rest of the session, so that Ctrl-C never ends
+/// the debugger. Programs it launches get the default action back before
+/// they exec.
+pub fn install() -> Result<()> {
+    let action = SigAction::new(
+        SigHandler::Handler(on_sigint),
+        SaFlags::SA_RESTART,
+        SigSet::empty(),
+    );
+    // SAFETY: the handler only touches atomics and sends a signal.
+    unsafe { sigaction(Signal::SIGINT, &action)? };
+    Ok(())
+}
+
+/// Has Ctrl-C stop a running program for as long as it lives.
+pub struct Armed {
+    previous: i32,
+}
+
+/// Have Ctrl-C stop `process` until the guard is dropped. A program in our
+/// process group gets the terminal's SIGINT as well and stops with that,
+/// so it is left alone.
+pub fn arm(process: &ProcessHandle) -> Armed {
+    let pid = if shares_terminal_signals(process) {
+        0
+    } else {
+        process.pid().as_raw()
+    };
+    Armed {
+        previous: TARGET.swap(pid, Ordering::SeqCst),
+    }
+}
+
+impl Drop for Armed {
+    fn drop(&mut self) {
+        TARGET.store(self.previous, Ordering::SeqCst);
+    }
+}
+
+/// Wait for the running process to stop, as GDB does: Ctrl-C interrupts
+/// it, and so does running for longer than `timeout`.
+pub fn wait_for_stop(
+    process: &mut ProcessHandle,
+    timeout: Option<Duration>,
+) -> Result<ProcessState> {
+    INTERRUPTED.store(false, Ordering::SeqCst);
+    let _armed = arm(process);
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let mut ctrl_c_seen = false;
+    let mut interrupted = false;
+    loop {
+        if let Some(state) = process.wait_on_signal_timeout(POLL)? {
+            return Ok(state);
+        }
+        if interrupted {
+            continue;
+        }
+        if ctrl_c_seen {
+            // The terminal's SIGINT did not stop the program within a poll,
+            // so it was sent to us alone.
+            process.interrupt()?;
+            interrupted = true;
+        } else if INTERRUPTED.swap(false, Ordering::SeqCst) {
+            // A program in our process group got the terminal's SIGINT too
+            // and stops with it; the handler stopped anything else.
+            if shares_terminal_signals(process) {
+                ctrl_c_seen = true;
+            } else {
+                interrupted = true;
+            }
+        } else if let (Some(deadline), Some(timeout)) = (deadline, timeout)
+            && Instant::now() >= deadline
+        {
+            println!(
+                "Program still running after {}s; interrupting it.",
+                timeout.as_secs_f64()
+            );
+            process.interrupt()?;
+            interrupted = true;
+        }
+    }
+}
+
+/// Whether Ctrl-C was pressed since the last call, for waits that have
+/// no program to stop.
+pub fn take_ctrl_c() -> bool {
+    INTERRUPTED.swap(false, Ordering::SeqCst)
+}
+
+/// Whether the program is in our process group, so Ctrl-C at the terminal
+/// reaches it directly.
+fn shares_terminal_signals(process: &ProcessHandle) -> bool {
+    getpgid(Some(process.pid())).is_ok_and(|pgid| pgid == getpgrp())
+}
@@ -1,11 +1,37 @@
 //! Command-line interface for the fdb debugger.
 
-use anyhow::{Context, Result};
+mod completion;
+mod config;
+mod interrupt;
+mod mi;
+mod output;
+mod settings;
+
+use anyhow::{Context, Result, bail};
 use clap::{Parser, Subcommand};
-use libfdb::{ProcessState, process::ProcessHandle};
+use completion::Completion;
+use libfdb::breakpoint::BreakpointSpec;
+use libfdb::catchpoint::CatchKind;
+use libfdb::disasm::Instruction;
+use libfdb::expr::Expr;
+use libfdb::fork::FollowForkMode;
+use libfdb::fpregs;
+use libfdb::memory::{DEFAULT_STRING_LIMIT, format_hexdump};
+use libfdb::process::{LaunchOptions, command_line, find_pid_by_name};
+use libfdb::registers::format_rflags;
+use libfdb::syscalls::{SyscallCatch, SyscallEvent, display_name, syscall_number};
+use libfdb::threads::ThreadState;
+use libfdb::watchpoint::{WatchKind, WatchSize};
+use libfdb::{FdbError, ProcessState, StopCause, StopReason, process::ProcessHandle};
 use log::info;
+use nix::sys::signal::Signal;
+use output::{Format, Pager, Reporter, Style, format_frame};
 use rustyline::{Editor, error::ReadlineError, history::DefaultHistory};
+use settings::{Settings, SourceOnError};
 use std::ffi::CString;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 /// Top-level argument parser describing the debugger interface.
 #[derive(Parser, Debug)]
@@ -13,6 +39,28 @@ use std::ffi::CString;
 struct Cli {
     #[command(subcommand)]
     cmd: Command,
+    /// How stops, registers and backtraces are printed.
+    #[arg(long, global = true, value_enum, default_value_t)]
+    format: Format,
+    /// Command language read from stdin.
+    #[arg(long, global = true, value_enum, default_value_t)]
+    interpreter: Interpreter,
+    /// File the prompt's command history is kept in.
+    #[arg(long, global = true, value_name = "FILE")]
+    history_file: Option<PathBuf>,
+    /// Keep history in .fdb_history in the current directory.
+    #[arg(long, global = true, conflicts_with = "history_file")]
+    local_history: bool,
+}
+
+/// Front ends selectable with `--interpreter`.
+#[derive(Debug, Clone, Copy, Default, clap::ValueEnum)]
+enum Interpreter {
+    /// The interactive prompt
+    #[default]
+    Console,
+    /// A subset of the GDB/MI machine interface
+    Mi,
 }
 
 /// Subcommands exposed by the debugger frontend.
@@ -21,11 +69,82 @@ enum Command {
     /// Run a program under fdb control.
     Run {
         prog: String,
+        /// Set an environment variable for the program (repeatable).
+        #[arg(long = "env", value_name = "KEY=VAL", value_parser = parse_env_var)]
+        env: Vec<(String, String)>,
+        /// Start the program with an empty environment.
+        #[arg(long)]
+        clear_env: bool,
+        /// Working directory for the program.
+        #[arg(long)]
+        cwd: Option<PathBuf>,
+        /// Redirect the program's stdout to a file.
+        #[arg(long)]
+        stdout: Option<PathBuf>,
+        /// Redirect the program's stderr to a file.
+        #[arg(long)]
+        stderr: Option<PathBuf>,
+        /// Run the commands in this file before prompting.
+        #[arg(long, value_name = "FILE")]
+        source: Option<PathBuf>,
+        /// Exit after the --source script with the program's exit status.
+        #[arg(long, requires = "source")]
+        batch: bool,
+        /// Set the breakpoints saved in this file by `save breakpoints`.
+        #[arg(long, value_name = "FILE")]
+        breakpoints: Option<PathBuf>,
+        /// Run to a temporary breakpoint on `main` before the first prompt.
+        #[arg(long)]
+        stop_at_main: bool,
+        #[arg(last = true)]
+        args: Vec<String>,
+    },
+    /// Run a program and log its system calls, like strace.
+    Trace {
+        prog: String,
+        /// Only log these syscalls (comma-separated names or numbers).
+        #[arg(long, value_delimiter = ',')]
+        syscall: Vec<String>,
         #[arg(last = true)]
         args: Vec<String>,
     },
-    /// Attach to an existing process ID.
-    Attach { pid: i32 },
+    /// Attach to an existing process ID, or to the process called --name.
+    Attach {
+        #[arg(required_unless_present_any = ["name", "wait"])]
+        pid: Option<i32>,
+        /// Attach to the one running process whose name contains this.
+        #[arg(
+            long,
+            value_name = "SUBSTR",
+            conflicts_with = "pid",
+            value_parser = clap::builder::NonEmptyStringValueParser::new()
+        )]
+        name: Option<String>,
+        /// Wait for a new process whose name contains this to start, then
+        /// attach to it.
+        #[arg(
+            long,
+            value_name = "SUBSTR",
+            conflicts_with_all = ["pid", "name"],
+            value_parser = clap::builder::NonEmptyStringValueParser::new()
+        )]
+        wait: Option<String>,
+        /// Give up waiting after this many seconds.
+        #[arg(
+            long,
+            value_name = "SECS",
+            requires = "wait",
+            conflicts_with_all = ["pid", "name"],
+            value_parser = parse_seconds
+        )]
+        timeout: Option<Duration>,
+        /// Run the commands in this file before prompting.
+        #[arg(long, value_name = "FILE")]
+        source: Option<PathBuf>,
+        /// Exit after the --source script with the program's exit status.
+        #[arg(long, requires = "source")]
+        batch: bool,
+    },
     /// Display version information for diagnostics.
     Version,
 }
@@ -33,14 +152,85 @@ enum Command {
 fn main() -> Result<()> {
     env_logger::init();
     let cli = Cli::parse();
+    let history = config::history_file(cli.history_file, cli.local_history);
     match cli.cmd {
-        Command::Run { prog, args } => {
-            let mut process = run_program(&prog, &args)?;
-            run_interactive_session(&mut process)?;
+        Command::Run {
+            prog,
+            env,
+            clear_env,
+            cwd,
+            stdout,
+            stderr,
+            source,
+            batch,
+            breakpoints,
+            stop_at_main,
+            args,
+        } => {
+            let options = LaunchOptions {
+                env: launch_env(&env, clear_env)?,
+                cwd,
+                stdout,
+                stderr,
+            };
+            let mut process = run_program(&prog, &args, &options)?;
+            if let Some(path) = breakpoints {
+                load_breakpoints(&mut process, &path)?;
+            }
+            if stop_at_main {
+                let main = process
+                    .function_breakpoint_addr("main")
+                    .context("no main function to stop at")?;
+                let id = process.set_temp_breakpoint(main)?;
+                println!("Temporary breakpoint {id} at {main:#x}");
+                process.resume()?;
+                let reporter = output::reporter(cli.format);
+                wait_and_report(reporter, &mut process, None)?;
+            }
+            start_session(
+                process,
+                cli.format,
+                cli.interpreter,
+                source.as_deref(),
+                batch,
+                &history,
+            )?;
+        }
+        Command::Trace {
+            prog,
+            syscall,
+            args,
+        } => {
+            let numbers = parse_syscalls(&syscall)?;
+            let mut process = run_program(&prog, &args, &LaunchOptions::default())?;
+            if !numbers.is_empty() {
+                process.set_syscall_catch(Some(SyscallCatch::Only(numbers)));
+            }
+            process.trace_syscalls(&mut std::io::stdout().lock())?;
         }
-        Command::Attach { pid } => {
-            let mut process = attach_to_process(pid)?;
-            run_interactive_session(&mut process)?;
+        Command::Attach {
+            pid,
+            name,
+            wait,
+            timeout,
+            source,
+            batch,
+        } => {
+            let pid = match (pid, name, wait) {
+                (Some(pid), ..) => pid,
+                (None, Some(name), _) => pid_named(&name)?,
+                (None, None, Some(name)) => wait_for_process(&name, timeout)?,
+                (None, None, None) => unreachable!("clap requires a pid, --name or --wait"),
+            };
+            let process = attach_to_process(pid)?;
+            start_session(
+                process,
+                cli.format,
+                cli.interpreter,
+                source.as_deref(),
+                batch,
+                &history,
+            )?;
         }
         Command::Version => {
             println!("fdb {}", libfdb::version());
@@ -49,7 +239,7 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn run_program(prog: &str, args: &[String]) -> Result<ProcessHandle> {
+fn run_program(prog: &str, args: &[String], options: &LaunchOptions) -> Result<ProcessHandle> {
     info!("Launching {prog} with args {args:?}");
     let c_prog = CString::new(prog)?;
     let mut tmp = Vec::with_capacity(args.len() + 1);
@@ -59,7 +249,125 @@ fn run_program(prog: &str, args: &[String]) -> Result<ProcessHandle> {
     }
     let argv: Vec<&std::ffi::CStr> = tmp.iter().map(|s| s.as_c_str()).collect();
 
-    Ok(ProcessHandle::launch(c_prog.as_c_str(), &argv)?)
+    Ok(ProcessHandle::launch_with_options(
+        c_prog.as_c_str(),
+        &argv,
+        options,
+    )?)
+}
+
+/// Environment for the program, or `None` to inherit ours unchanged.
+///
+/// Starts from our own environment unless `clear` is set; `overrides` win.
+fn launch_env(
+    overrides: &[(String, String)],
+    clear: bool,
+) -> Result<Option<Vec<(CString, CString)>>> {
+    if overrides.is_empty() && !clear {
+        return Ok(None);
+    }
+    let mut vars: Vec<(CString, CString)> = Vec::new();
+    if !clear {
+        for (key, val) in std::env::vars_os() {
+            vars.push((
+                CString::new(key.into_encoded_bytes())?,
+                CString::new(val.into_encoded_bytes())?,
+            ));
+        }
+    }
+    for (key, val) in overrides {
+        let key = CString::new(key.as_str())?;
+        let val = CString::new(val.as_str())?;
+        match vars.iter_mut().find(|(k, _)| *k == key) {
+            Some(slot) => slot.1 = val,
+            None => vars.push((key, val)),
+        }
+    }
+    Ok(Some(vars))
+}
+
+/// Resolve syscall names or numbers.
+fn parse_syscalls<S: AsRef<str>>(names: &[S]) -> Result<Vec<u64>> {
+    names
+        .iter()
+        .map(|name| {
+            let name = name.as_ref();
+            parse_u64(name)
+                .or_else(|| syscall_number(name))
+                .with_context(|| format!("unknown syscall: {name}"))
+        })
+        .collect()
+}
+
+/// Parse a positive number of seconds for `--timeout`.
+fn parse_seconds(s: &str) -> Result<Duration, String> {
+    s.parse()
+        .ok()
+        .filter(|secs: &f64| secs.is_finite() && *secs > 0.0)
+        .map(Duration::from_secs_f64)
+        .ok_or_else(|| format!("invalid number of seconds: {s}"))
+}
+
+/// Parse a `KEY=VAL` pair for `--env`.
+fn parse_env_var(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((key, val)) if !key.is_empty() => Ok((key.to_string(), val.to_string())),
+        _ => Err(format!("expected KEY=VAL, got '{s}'")),
+    }
+}
+
+/// The pid of the one process whose name contains `name`. When several
+/// do, they are listed with their command lines so one can be picked.
+fn pid_named(name: &str) -> Result<i32> {
+    match find_pid_by_name(name)?[..] {
+        [pid] => Ok(pid),
+        [] => bail!("no running process matches '{name}'"),
+        ref pids => {
+            eprintln!("Processes matching '{name}':");
+            for &pid in pids {
+                let cmdline = command_line(pid).unwrap_or_default();
+                eprintln!("  {pid:>7}  {cmdline}");
+            }
+            bail!(
+                "{} processes match '{name}'; attach to one by pid",
+                pids.len()
+            )
+        }
+    }
+}
+
+/// How often `attach --wait` looks for the process; short, so that it is
+/// attached to early in its life.
+const WAIT_POLL: Duration = Duration::from_millis(10);
+
+/// The pid of the first process whose name contains `name` and that was
+/// not already running when we started looking. Ctrl-C gives up, and so
+/// does `timeout` passing.
+fn wait_for_process(name: &str, timeout: Option<Duration>) -> Result<i32> {
+    interrupt::install()?;
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let running = find_pid_by_name(name)?;
+    println!("Waiting for a process matching '{name}' to start...");
+    loop {
+        if let Some(&pid) = find_pid_by_name(name)?
+            .iter()
+            .find(|pid| !running.contains(pid))
+        {
+            return Ok(pid);
+        }
+        if interrupt::take_ctrl_c() {
+            bail!("stopped waiting for '{name}'");
+        }
+        if let (Some(deadline), Some(timeout)) = (deadline, timeout)
+            && Instant::now() >= deadline
+        {
+            bail!(
+                "no process matching '{name}' started within {}s",
+                timeout.as_secs_f64()
+            );
+        }
+        std::thread::sleep(WAIT_POLL);
+    }
 }
 
 fn attach_to_process(pid: i32) -> Result<ProcessHandle> {
@@ -69,23 +377,111 @@ fn attach_to_process(pid: i32) -> Result<ProcessHandle> {
     Ok(handle)
 }
 
-fn run_interactive_session(process: &mut ProcessHandle) -> Result<()> {
+/// Set the breakpoints listed in `path`, one spec per line as written by
+/// `save breakpoints`. Ones that no longer resolve are reported and skipped.
+fn load_breakpoints(process: &mut ProcessHandle, path: &Path) -> Result<()> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let mut specs = Vec::new();
+    for (n, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let spec = line
+            .parse::<BreakpointSpec>()
+            .with_context(|| format!("{}:{}", path.display(), n + 1))?;
+        specs.push(spec);
+    }
+    for (spec, result) in specs.iter().zip(process.import_breakpoints(&specs)) {
+        match result {
+            Ok(id) => println!("Breakpoint {id}: {}", spec.location),
+            Err(e) => eprintln!("warning: could not set '{spec}': {e}"),
+        }
+    }
+    Ok(())
+}
+
+/// Run `source`, then read commands with the chosen interpreter; in batch
+/// mode run only `source` and exit with the program's status. The prompt
+/// keeps its history in `history`.
+fn start_session(
+    process: ProcessHandle,
+    format: Format,
+    interpreter: Interpreter,
+    source: Option<&Path>,
+    batch: bool,
+    history: &Path,
+) -> Result<()> {
+    interrupt::install()?;
+    let mut session = Session::new(process, output::reporter(format));
+    if let Interpreter::Console = interpreter {
+        output::set_color(session.settings.color);
+    }
+    match (source, interpreter) {
+        (Some(source), _) if batch => std::process::exit(run_batch(session, source)?),
+        (_, Interpreter::Mi) => {
+            if let Some(source) = source {
+                source_file(&mut session, source)?;
+            }
+            mi::run(&mut session)
+        }
+        (_, Interpreter::Console) => run_interactive_session(session, source, history),
+    }
+}
+
+/// Run the script at `source` without a prompt or history and return the
+/// status fdb should exit with: the program's exit code, 128 plus the
+/// signal number if a signal killed it, or 0 if it is still around.
+fn run_batch(mut session: Session, source: &Path) -> Result<i32> {
+    source_file(&mut session, source)?;
+    Ok(
+        match session
+            .inferiors
+            .get(session.current)
+            .map(ProcessHandle::state)
+        {
+            Some(ProcessState::Exited(code)) => code,
+            Some(ProcessState::Terminated(sig)) => 128 + sig as i32,
+            _ => 0,
+        },
+    )
+}
+
+fn run_interactive_session(
+    mut session: Session,
+    source: Option<&Path>,
+    history: &Path,
+) -> Result<()> {
+    session.interactive = true;
     println!(
         "Attached to process {} - entering interactive mode",
-        process.pid()
+        session.process().pid()
     );
 
-    let mut rl = Editor::<(), DefaultHistory>::new()?;
+    if let Some(path) = source
+        && let Err(e) = source_file(&mut session, path)
+    {
+        eprintln!("Error: {e}");
+    }
+
+    let mut rl = Editor::<Completion, DefaultHistory>::new()?;
+    rl.set_helper(Some(Completion::default()));
 
-    // Optional: persistent history (similar spirit to libedit’s history)
-    // don't want a new dep to locate $HOME, just use a local file.
-    let hist_file = ".fdb_history";
-    let _ = rl.load_history(hist_file);
+    // Optional: persistent history (similar spirit to libedit’s history).
+    // The state directory may not exist yet on a first run.
+    if let Some(dir) = history.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    let _ = rl.load_history(history);
 
     // Track the last non-empty command, to re-run on empty input
     let mut last_cmd: Option<String> = None;
 
-    loop {
+    while !session.quit {
+        if let Some(completion) = rl.helper_mut() {
+            completion.symbols = session.function_names();
+        }
         match rl.readline("fdb> ") {
             Ok(line) => {
                 let trimmed = line.trim();
@@ -106,15 +502,9 @@ fn run_interactive_session(process: &mut ProcessHandle) -> Result<()> {
                     trimmed
                 };
 
-                // Handle command; exit handled in loop after call
-                if let Err(e) = handle_command(process, to_run) {
+                if let Err(e) = execute(&mut session, to_run) {
                     eprintln!("Error: {e}");
                 }
-
-                // Quit commands
-                if matches!(to_run, "quit" | "exit") {
-                    break;
-                }
             }
 
             Err(ReadlineError::Interrupted) => {
@@ -135,67 +525,2220 @@ fn run_interactive_session(process: &mut ProcessHandle) -> Result<()> {
     }
 
     // Try to persist history (ignore errors)
-    let _ = rl.save_history(hist_file);
+    let _ = rl.save_history(history);
 
     println!("Exiting debugger");
     Ok(())
 }
 
-fn handle_command(process: &mut ProcessHandle, line: &str) -> Result<()> {
-    let args = split_whitespace(line);
-    if args.is_empty() {
-        return Ok(());
+/// Run one command line, then take in forked children and drop a process
+/// that was detached.
+fn execute(session: &mut Session, line: &str) -> Result<()> {
+    let result = handle_command(session, line);
+    let Session {
+        inferiors, current, ..
+    } = session;
+    if let Err(e) = adopt_fork_children(inferiors, current) {
+        eprintln!("Error: {e}");
     }
 
-    let cmd = args[0];
+    // A detached process can no longer be driven
+    if matches!(session.process().state(), ProcessState::Detached) {
+        session.inferiors.remove(session.current);
+        if session.inferiors.is_empty() {
+            session.quit = true;
+        } else {
+            session.current = 0;
+            session.selected_frame = 0;
+            println!("Switching to process {}", session.process().pid());
+        }
+    }
+    result
+}
 
-    // Accept "c", "cont", "continue" (prefix match on "continue")
-    // Accept "c", "cont", "continue"
-    if is_prefix(cmd, "continue") {
-        process.resume()?; // lib method (PTRACE_CONT)
-        match process.wait_on_signal()? {
-            // lib method (single waitpid) + state update
-            ProcessState::Stopped(reason) => {
-                println!("stopped by signal: {:?}", reason.signal);
-            }
-            ProcessState::Exited(code) => {
-                println!("process exited with code {code}");
+/// Run the commands in `path` one line at a time. Blank lines and lines
+/// starting with `#` are skipped.
+///
+/// A failing command ends the script unless `source-on-error` is `continue`.
+fn source_file(session: &mut Session, path: &Path) -> Result<()> {
+    let script =
+        std::fs::read_to_string(path).with_context(|| format!("cannot read {}", path.display()))?;
+    for (i, line) in script.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Err(e) = execute(session, line) {
+            let e = anyhow::anyhow!("{}:{}: {e}", path.display(), i + 1);
+            match session.settings.source_on_error {
+                SourceOnError::Stop => return Err(e),
+                SourceOnError::Continue => eprintln!("Error: {e}"),
             }
-            ProcessState::Terminated(sig) => {
-                println!("process terminated by signal: {:?}", sig);
+        }
+        if session.quit {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Take in children of a followed fork, switching to the child and releasing
+/// the parent when following children only.
+fn adopt_fork_children(inferiors: &mut Vec<ProcessHandle>, current: &mut usize) -> Result<()> {
+    while let Some(child) = inferiors[*current].take_fork_child() {
+        let parent = &mut inferiors[*current];
+        match parent.follow_fork() {
+            FollowForkMode::Child => {
+                println!(
+                    "Following child process {}, detaching from {}",
+                    child.pid(),
+                    parent.pid()
+                );
+                parent.detach()?;
+                inferiors[*current] = child;
             }
-            other => {
-                println!("status: {:?}", other);
+            _ => {
+                println!(
+                    "New inferior {} (process {})",
+                    inferiors.len() + 1,
+                    child.pid()
+                );
+                inferiors.push(child);
             }
         }
-        return Ok(());
+    }
+    Ok(())
+}
+
+/// Everything commands act on during an interactive session.
+struct Session {
+    /// Processes under the debugger; more appear when following forks.
+    inferiors: Vec<ProcessHandle>,
+    current: usize,
+    settings: Settings,
+    /// Prints stops, registers and backtraces in the chosen format.
+    reporter: &'static dyn Reporter,
+    /// Expressions shown after every stop, in the order they were added.
+    displays: Vec<AutoDisplay>,
+    next_display_id: u32,
+    /// Frame that `print`, `info locals` and register reads look at; 0 is
+    /// where the program stopped.
+    selected_frame: usize,
+    /// Whether commands come from the console prompt; batch runs and MI
+    /// never page their output.
+    interactive: bool,
+    /// Set by `quit` to end the session.
+    quit: bool,
+}
+
+/// An expression registered with `display`.
+struct AutoDisplay {
+    id: u32,
+    expr: String,
+}
+
+impl Session {
+    fn new(process: ProcessHandle, reporter: &'static dyn Reporter) -> Self {
+        Session {
+            inferiors: vec![process],
+            current: 0,
+            settings: Settings::default(),
+            reporter,
+            displays: Vec::new(),
+            next_display_id: 1,
+            selected_frame: 0,
+            interactive: false,
+            quit: false,
+        }
     }
 
-    match cmd {
-        "help" => {
-            println!("Available commands:");
-            println!("  help              - show this help");
-            println!("  continue|cont|c   - resume the program");
-            println!("  info              - show process info");
-            println!("  quit|exit         - exit debugger");
+    /// Print every `display` expression if the program is stopped.
+    fn show_displays(&mut self) {
+        let process = &self.inferiors[self.current];
+        if !matches!(process.state(), ProcessState::Stopped(_)) {
+            return;
         }
-        "info" => {
-            println!("Process PID: {}", process.pid());
-            println!("State: {:?}", process.state());
+        for display in &self.displays {
+            show_display(process, display, self.selected_frame);
+        }
+    }
+
+    /// With `set disassemble-next-line on`, list what runs next: the
+    /// instructions from the pc to the end of its source line, or only the
+    /// next one where there is no line information.
+    fn show_next_line(&self) {
+        let process = &self.inferiors[self.current];
+        if !matches!(process.state(), ProcessState::Stopped(_)) {
+            return;
+        }
+        let listed = process.pc().and_then(|pc| {
+            let insns = process.disassemble(pc, NEXT_LINE_MAX)?;
+            let line = process.addr_to_line(pc);
+            let count = match line {
+                Some(_) => insns
+                    .iter()
+                    .take_while(|insn| process.addr_to_line(insn.address) == line)
+                    .count(),
+                None => 1,
+            };
+            // Nothing decodes at a pc capstone does not understand.
+            let insns = &insns[..count.max(1).min(insns.len())];
+            Ok(print_disassembly(
+                &mut self.pager(),
+                process,
+                insns,
+                pc,
+                Listing {
+                    source: false,
+                    bytes: self.settings.disassembly_bytes,
+                },
+            )?)
+        });
+        if let Err(e) = listed {
+            eprintln!("warning: cannot disassemble the next line: {e}");
+        }
+    }
+
+    /// After the program ran: select the innermost frame again and show
+    /// the next line's instructions and the displays.
+    fn stopped(&mut self) {
+        self.selected_frame = 0;
+        if self.settings.disassemble_next_line {
+            self.show_next_line();
         }
-        "quit" | "exit" => {
-            // handled by the outer loop
+        self.show_displays();
+    }
+
+    /// Where listings that can run past a screen are written.
+    fn pager(&self) -> Pager {
+        let rows = (self.interactive && self.settings.pagination)
+            .then(output::terminal_height)
+            .flatten();
+        Pager::new(rows.map(|rows| self.settings.height.unwrap_or(rows)))
+    }
+
+    /// Function names of the current program for completion; none once
+    /// it has exited or before there is one.
+    fn function_names(&self) -> Vec<String> {
+        match self.inferiors.get(self.current) {
+            Some(process)
+                if matches!(
+                    process.state(),
+                    ProcessState::Initialized | ProcessState::Stopped(_)
+                ) =>
+            {
+                process.function_names()
+            }
+            _ => Vec::new(),
         }
+    }
+
+    /// The inferior commands apply to.
+    fn process(&mut self) -> &mut ProcessHandle {
+        &mut self.inferiors[self.current]
+    }
+}
+
+/// One command line, split into words, as handed to a command handler.
+struct Invocation<'a> {
+    /// Canonical name of the command
+    name: &'static str,
+    /// Words of the line; `args[0]` is the command as typed
+    args: Vec<&'a str>,
+    /// The whole line, for commands taking free-form text
+    line: &'a str,
+}
+
+type Handler = fn(&mut Session, &Invocation) -> Result<()>;
+
+/// A command of the interactive prompt.
+struct CommandSpec {
+    name: &'static str,
+    aliases: &'static [&'static str],
+    /// Argument synopsis and description of each form, for `help`.
+    forms: &'static [(&'static str, &'static str)],
+    handler: Handler,
+}
+
+/// Every prompt command, in the order `help` lists them.
+const COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        name: "help",
+        aliases: &[],
+        forms: &[("", "show this help")],
+        handler: cmd_help,
+    },
+    CommandSpec {
+        name: "continue",
+        aliases: &["cont", "c"],
+        forms: &[
+            ("", "resume the program"),
+            ("<n>", "resume, stopping at the nth breakpoint hit"),
+            ("<sig>", "resume, delivering a signal named like SIGUSR1"),
+        ],
+        handler: cmd_continue,
+    },
+    CommandSpec {
+        name: "run",
+        aliases: &["r"],
+        forms: &[("", "restart the program from the beginning")],
+        handler: cmd_run,
+    },
+    CommandSpec {
+        name: "starti",
+        aliases: &[],
+        forms: &[("", "restart and stop at the program's entry point")],
+        handler: cmd_starti,
+    },
+    CommandSpec {
+        name: "step",
+        aliases: &["s"],
+        forms: &[("[n]", "step to the next source line, or n lines")],
+        handler: cmd_step,
+    },
+    CommandSpec {
+        name: "stepi",
+        aliases: &["si"],
+        forms: &[("[n]", "execute one instruction, or n of them")],
+        handler: cmd_step,
+    },
+    CommandSpec {
+        name: "nexti",
+        aliases: &["ni", "next"],
+        forms: &[("[n]", "step one instruction, or n, over calls")],
+        handler: cmd_step,
+    },
+    CommandSpec {
+        name: "finish",
+        aliases: &["fin"],
+        forms: &[("", "run until the current function returns")],
+        handler: cmd_step,
+    },
+    CommandSpec {
+        name: "advance",
+        aliases: &["until"],
+        forms: &[("<loc>", "run to loc or until the current function returns")],
+        handler: cmd_advance,
+    },
+    CommandSpec {
+        name: "return",
+        aliases: &["ret"],
+        forms: &[(
+            "[e]",
+            "return from the current function now, with value e if given",
+        )],
+        handler: cmd_return,
+    },
+    CommandSpec {
+        name: "jump",
+        aliases: &["j"],
+        forms: &[("<loc>", "resume at loc instead of the current pc")],
+        handler: cmd_jump,
+    },
+    CommandSpec {
+        name: "break",
+        aliases: &["b"],
+        forms: &[
+            (
+                "<loc>",
+                "set a breakpoint at a function, file:line, address, or *<loc>",
+            ),
+            (
+                "<loc> if <e>",
+                "stop at loc only when expression e is non-zero",
+            ),
+        ],
+        handler: cmd_break,
+    },
+    CommandSpec {
+        name: "tbreak",
+        aliases: &[],
+        forms: &[("<loc>", "set a breakpoint that is deleted when first hit")],
+        handler: cmd_break,
+    },
+    CommandSpec {
+        name: "condition",
+        aliases: &[],
+        forms: &[("<id> [e]", "set or clear a breakpoint's condition")],
+        handler: cmd_condition,
+    },
+    CommandSpec {
+        name: "ignore",
+        aliases: &[],
+        forms: &[("<id> <n>", "skip the next n hits of a breakpoint")],
+        handler: cmd_ignore,
+    },
+    CommandSpec {
+        name: "backtrace",
+        aliases: &["bt"],
+        forms: &[
+            ("", "show the call stack with arguments and source lines"),
+            ("full", "show the call stack and the locals of every frame"),
+            ("[full] <n>", "show only the innermost n frames"),
+        ],
+        handler: cmd_backtrace,
+    },
+    CommandSpec {
+        name: "frame",
+        aliases: &["f"],
+        forms: &[("[n]", "show the selected frame, or select frame n")],
+        handler: cmd_frame,
+    },
+    CommandSpec {
+        name: "up",
+        aliases: &[],
+        forms: &[("[n]", "select the frame n levels up, towards main")],
+        handler: cmd_frame,
+    },
+    CommandSpec {
+        name: "down",
+        aliases: &[],
+        forms: &[("[n]", "select the frame n levels down, towards the pc")],
+        handler: cmd_frame,
+    },
+    CommandSpec {
+        name: "list",
+        aliases: &["l"],
+        forms: &[(
+            "[loc]",
+            "show source around the pc, a function, or file:line",
+        )],
+        handler: cmd_list,
+    },
+    CommandSpec {
+        name: "enable",
+        aliases: &[],
+        forms: &[("<id>", "re-enable a breakpoint")],
+        handler: cmd_enable,
+    },
+    CommandSpec {
+        name: "disable",
+        aliases: &[],
+        forms: &[("<id>", "disable a breakpoint")],
+        handler: cmd_enable,
+    },
+    CommandSpec {
+        name: "delete",
+        aliases: &[],
+        forms: &[("<id>", "delete a breakpoint or watchpoint")],
+        handler: cmd_delete,
+    },
+    CommandSpec {
+        name: "watch",
+        aliases: &[],
+        forms: &[
+            ("<a> [size]", "stop when memory at a is written"),
+            ("<var>", "stop when the variable var is written"),
+        ],
+        handler: cmd_watch,
+    },
+    CommandSpec {
+        name: "rwatch",
+        aliases: &[],
+        forms: &[("<a> [size]", "stop when memory at a is read")],
+        handler: cmd_watch,
+    },
+    CommandSpec {
+        name: "awatch",
+        aliases: &[],
+        forms: &[("<a> [size]", "stop on any access to memory at a")],
+        handler: cmd_watch,
+    },
+    CommandSpec {
+        name: "print",
+        aliases: &["p"],
+        forms: &[
+            ("<var>", "show the value of a variable"),
+            ("<e>", "evaluate an expression, e.g. $sp + 8 or $var"),
+        ],
+        handler: cmd_print,
+    },
+    CommandSpec {
+        name: "display",
+        aliases: &[],
+        forms: &[
+            ("<e>", "print variable or expression e at every stop"),
+            ("", "print the display expressions now"),
+        ],
+        handler: cmd_display,
+    },
+    CommandSpec {
+        name: "undisplay",
+        aliases: &[],
+        forms: &[("[n]", "stop displaying expression n, or all of them")],
+        handler: cmd_undisplay,
+    },
+    CommandSpec {
+        name: "regs",
+        aliases: &[],
+        forms: &[("", "dump general-purpose registers")],
+        handler: cmd_regs,
+    },
+    CommandSpec {
+        name: "set",
+        aliases: &[],
+        forms: &[
+            (
+                "reg <r> <val>",
+                "write a register; stN takes a float, xmmN up to 128 bits",
+            ),
+            (
+                "$name = <e>",
+                "set a convenience variable or register such as $pc (set is optional)",
+            ),
+            ("<setting> <value>", "change a setting; show lists them all"),
+        ],
+        handler: cmd_set,
+    },
+    CommandSpec {
+        name: "show",
+        aliases: &[],
+        forms: &[("[setting]", "show one setting, or all with what they do")],
+        handler: cmd_show,
+    },
+    CommandSpec {
+        name: "inferiors",
+        aliases: &[],
+        forms: &[("", "list traced processes")],
+        handler: cmd_inferiors,
+    },
+    CommandSpec {
+        name: "inferior",
+        aliases: &[],
+        forms: &[("<n>", "switch to another traced process")],
+        handler: cmd_inferior,
+    },
+    CommandSpec {
+        name: "mem",
+        aliases: &[],
+        forms: &[
+            ("read <a> <n>", "hexdump n bytes at address a"),
+            ("write <a> <h>", "write hex bytes h at address a"),
+        ],
+        handler: cmd_mem,
+    },
+    CommandSpec {
+        name: "dump",
+        aliases: &[],
+        forms: &[(
+            "binary memory <f> <a> <b>",
+            "write memory from a up to b to file f",
+        )],
+        handler: cmd_dump,
+    },
+    CommandSpec {
+        name: "restore",
+        aliases: &[],
+        forms: &[("<f> <a>", "write the bytes of file f at address a")],
+        handler: cmd_restore,
+    },
+    CommandSpec {
+        name: "find",
+        aliases: &[],
+        forms: &[
+            ("<a> <b> <hex>", "find hex bytes between addresses a and b"),
+            (
+                "<a> <b> \"text\"",
+                "find a string between addresses a and b",
+            ),
+        ],
+        handler: cmd_find,
+    },
+    CommandSpec {
+        name: "disassemble",
+        aliases: &["disas"],
+        forms: &[
+            (
+                "[loc] [n]",
+                "disassemble n instructions at loc (default: the pc)",
+            ),
+            ("<function>", "disassemble a whole function with its source"),
+        ],
+        handler: cmd_disassemble,
+    },
+    CommandSpec {
+        name: "x",
+        aliases: &[],
+        forms: &[("/NFU <addr>", "examine memory (F: x d u o t, U: b h w g)")],
+        handler: cmd_examine,
+    },
+    CommandSpec {
+        name: "info",
+        aliases: &[],
+        forms: &[
+            ("", "show process info"),
+            (
+                "breakpoints",
+                "list breakpoints, watchpoints and catchpoints",
+            ),
+            ("display", "list the display expressions"),
+            ("frame", "show the current frame's layout on the stack"),
+            ("convenience", "list the convenience variables"),
+            ("registers [r]", "show registers, or one (rflags decoded)"),
+            ("all-registers", "also show orig_rax and debug registers"),
+            ("proc mappings", "list the memory map"),
+            ("sharedlibrary", "list the loaded shared libraries"),
+            ("locals", "show the local variables of the current function"),
+            ("float", "show the x87 stack and control registers"),
+            ("vector", "show the SSE registers and MXCSR"),
+            ("threads", "list the threads of the process"),
+        ],
+        handler: cmd_info,
+    },
+    CommandSpec {
+        name: "thread",
+        aliases: &[],
+        forms: &[("[tid]", "show or switch the current thread")],
+        handler: cmd_thread,
+    },
+    CommandSpec {
+        name: "catch",
+        aliases: &[],
+        forms: &[
+            (
+                "syscall [s]",
+                "stop at syscalls (all, or those named); 'off' stops",
+            ),
+            ("exit [off]", "stop just before the process exits"),
+            ("exec", "stop when the process execs a new program"),
+            ("fork", "stop when the process forks"),
+            ("signal <sig>", "stop when the process receives <sig>"),
+        ],
+        handler: cmd_catch,
+    },
+    CommandSpec {
+        name: "handle",
+        aliases: &[],
+        forms: &[(
+            "<sig> ...",
+            "set [no]stop, [no]print, [no]pass for a signal",
+        )],
+        handler: cmd_handle,
+    },
+    CommandSpec {
+        name: "signal",
+        aliases: &[],
+        forms: &[("<sig> [--force]", "send a signal, delivered on resume")],
+        handler: cmd_signal,
+    },
+    CommandSpec {
+        name: "source",
+        aliases: &[],
+        forms: &[("<file>", "run the commands in a file")],
+        handler: cmd_source,
+    },
+    CommandSpec {
+        name: "save",
+        aliases: &[],
+        forms: &[("breakpoints <file>", "save breakpoints to a file")],
+        handler: cmd_save,
+    },
+    CommandSpec {
+        name: "kill",
+        aliases: &[],
+        forms: &[("", "kill the process")],
+        handler: cmd_kill,
+    },
+    CommandSpec {
+        name: "detach",
+        aliases: &[],
+        forms: &[("", "release the process and exit")],
+        handler: cmd_detach,
+    },
+    CommandSpec {
+        name: "quit",
+        aliases: &["exit"],
+        forms: &[("", "exit debugger")],
+        handler: cmd_quit,
+    },
+];
+
+/// Find the command `word` names: an exact name or alias first, then the
+/// only command that `word` is a prefix of.
+///
+/// An `x/NFU` word is looked up as `x`.
+fn resolve_command(word: &str) -> Result<&'static CommandSpec> {
+    let word = match word.split_once('/') {
+        Some(("x", _)) => "x",
+        _ => word,
+    };
+    let names =
+        |spec: &'static CommandSpec| std::iter::once(spec.name).chain(spec.aliases.iter().copied());
+    if let Some(spec) = COMMANDS
+        .iter()
+        .find(|spec| names(spec).any(|name| name == word))
+    {
+        return Ok(spec);
+    }
+    let candidates: Vec<&CommandSpec> = COMMANDS
+        .iter()
+        .filter(|spec| names(spec).any(|name| is_prefix(word, name)))
+        .collect();
+    match candidates[..] {
+        [spec] => Ok(spec),
+        [] => bail!("unknown command: {word} (try \"help\")"),
         _ => {
-            eprintln!("Unknown command");
+            let names: Vec<&str> = candidates.iter().map(|spec| spec.name).collect();
+            bail!("ambiguous command '{word}': {}", names.join(", "))
+        }
+    }
+}
+
+/// The repeat count of `continue`, `step`, `stepi` and `nexti`.
+fn parse_count(arg: &str) -> Result<u32> {
+    arg.parse()
+        .with_context(|| format!("invalid repeat count: {arg}"))
+}
+
+fn handle_command(session: &mut Session, line: &str) -> Result<()> {
+    let args = split_whitespace(line);
+    let Some(word) = args.first() else {
+        return Ok(());
+    };
+    if let Some(assignment) = word.strip_prefix('$') {
+        let rest = line.trim_start().strip_prefix(word).unwrap_or_default();
+        return assign_convenience(session, &format!("{assignment}{rest}"));
+    }
+    let spec = resolve_command(word)?;
+    let invocation = Invocation {
+        name: spec.name,
+        args,
+        line,
+    };
+    (spec.handler)(session, &invocation)
+}
+
+fn cmd_help(_: &mut Session, _: &Invocation) -> Result<()> {
+    println!("Available commands:");
+    for spec in COMMANDS {
+        let names = std::iter::once(spec.name)
+            .chain(spec.aliases.iter().copied())
+            .collect::<Vec<_>>()
+            .join("|");
+        for (i, (synopsis, description)) in spec.forms.iter().enumerate() {
+            // Aliases are listed once, on the first form.
+            let name = if i == 0 { names.as_str() } else { spec.name };
+            let usage = match *synopsis {
+                "" => name.to_string(),
+                s if s.starts_with('/') => format!("{name}{s}"),
+                s => format!("{name} {s}"),
+            };
+            println!("  {usage:<17} - {description}");
         }
     }
     Ok(())
 }
 
-fn split_whitespace(line: &str) -> Vec<&str> {
-    // GDB/LLDB-style CLIs generally treat any whitespace as a separator.
-    line.split_whitespace().collect()
+/// `continue [n|sig]`. Breakpoint hits before the nth are passed over
+/// without being reported.
+fn cmd_continue(session: &mut Session, inv: &Invocation) -> Result<()> {
+    let (reporter, timeout) = (session.reporter, session.settings.wait_timeout);
+    let process = session.process();
+    let (signal, hits) = match inv.args.get(1) {
+        Some(arg) if arg.starts_with(|c: char| c.is_ascii_digit()) => (None, parse_count(arg)?),
+        Some(name) => (Some(parse_signal(name)?), 1),
+        None => (None, 1),
+    };
+    // An explicit signal is delivered as-is; otherwise the signal policy
+    // decides whether the stop signal is passed on.
+    match signal {
+        Some(signal) => process.resume_with_signal(signal)?,
+        None => process.resume()?, // lib method (PTRACE_CONT)
+    }
+    let mut state = interrupt::wait_for_stop(process, timeout)?;
+    for _ in 1..hits {
+        let ProcessState::Stopped(StopReason {
+            cause: StopCause::Breakpoint(_),
+            ..
+        }) = state
+        else {
+            break;
+        };
+        process.resume()?;
+        state = interrupt::wait_for_stop(process, timeout)?;
+    }
+    report_stop(reporter, process, state)?;
+    session.stopped();
+    Ok(())
+}
+
+fn cmd_run(session: &mut Session, _: &Invocation) -> Result<()> {
+    let (reporter, timeout) = (session.reporter, session.settings.wait_timeout);
+    let process = session.process();
+    process.relaunch()?;
+    println!("Starting program: {}", process.executable()?.display());
+    process.resume()?;
+    wait_and_report(reporter, process, timeout)?;
+    session.stopped();
+    Ok(())
+}
+
+fn cmd_starti(session: &mut Session, _: &Invocation) -> Result<()> {
+    let reporter = session.reporter;
+    let process = session.process();
+    process.relaunch()?;
+    println!("Starting program: {}", process.executable()?.display());
+    match process.run_to_entry()? {
+        // Statically linked programs begin at the entry point.
+        ProcessState::Initialized => {
+            println!("Program stopped at {:#x}.", process.entry_point()?)
+        }
+        state => reporter.state(process, state)?,
+    }
+    session.stopped();
+    Ok(())
+}
+
+/// `step`, `stepi`, `nexti` and `finish`.
+fn cmd_step(session: &mut Session, inv: &Invocation) -> Result<()> {
+    let flags_on_step = session.settings.flags_on_step;
+    let reporter = session.reporter;
+    let count = match inv.args.get(1) {
+        Some(n) if matches!(inv.name, "step" | "stepi" | "nexti") => Some(parse_count(n)?),
+        _ => None,
+    };
+    let process = session.process();
+    let _armed = interrupt::arm(process);
+    let state = match (inv.name, count) {
+        ("step", Some(n)) => process.step_line_n(n)?,
+        ("stepi", Some(n)) => process.single_step_n(n)?,
+        ("nexti", Some(n)) => process.step_over_n(n)?,
+        ("stepi", None) => process.single_step()?,
+        ("nexti", None) => process.step_over()?,
+        ("finish", _) => process.step_out()?,
+        _ => process.step_line()?,
+    };
+    reporter.state(process, state)?;
+    if let Some(n) = count
+        && process.steps_completed() < n
+    {
+        println!("Stopped after {} of {n} steps.", process.steps_completed());
+    }
+    if inv.name == "stepi" && flags_on_step && matches!(state, ProcessState::Stopped(_)) {
+        println!("{}", format_rflags(process.get_registers()?.rflags()));
+    }
+    session.stopped();
+    Ok(())
+}
+
+fn cmd_jump(session: &mut Session, inv: &Invocation) -> Result<()> {
+    let Some(loc) = inv.args.get(1) else {
+        bail!("usage: jump <loc>");
+    };
+    let (reporter, timeout) = (session.reporter, session.settings.wait_timeout);
+    let process = session.process();
+    let target = code_location(process, loc)?;
+    let function = |addr| process.symbol_at(addr).map(|sym| sym.address);
+    if function(target) != function(process.pc()?) {
+        println!("Warning: {target:#x} is outside the current function.");
+    }
+    println!("Continuing at {target:#x}.");
+    process.jump(target)?;
+    wait_and_report(reporter, process, timeout)?;
+    session.stopped();
+    Ok(())
+}
+
+fn cmd_return(session: &mut Session, inv: &Invocation) -> Result<()> {
+    let reporter = session.reporter;
+    let process = session.process();
+    let value = match inv.line.trim().split_once(char::is_whitespace) {
+        Some((_, expr)) => Some(process.evaluate_str(expr)? as u64),
+        None => None,
+    };
+    process.force_return(value)?;
+    session.selected_frame = 0;
+    let process = session.process();
+    let frames = process.backtrace()?;
+    reporter.backtrace(&mut io::stdout(), &frames[..1])?;
+    if let Some((file, line)) = process.addr_to_line(frames[0].pc) {
+        print_source(&file, line, 0);
+    }
+    Ok(())
+}
+
+fn cmd_advance(session: &mut Session, inv: &Invocation) -> Result<()> {
+    let Some(arg) = inv.args.get(1) else {
+        bail!("usage: {} <loc>", inv.name);
+    };
+    let reporter = session.reporter;
+    let process = session.process();
+    let location = code_location(process, arg)?;
+    let _armed = interrupt::arm(process);
+    let state = process.run_until(location)?;
+    reporter.state(process, state)?;
+    session.stopped();
+    Ok(())
+}
+
+/// `break` and `tbreak`.
+fn cmd_break(session: &mut Session, inv: &Invocation) -> Result<()> {
+    let process = session.process();
+    let (cmd, args, line) = (inv.name, &inv.args, inv.line);
+    let Some(arg) = args.get(1) else {
+        bail!("usage: {cmd} <loc> [if <expr>]");
+    };
+    let condition = match args.get(2..) {
+        Some(["if", ..]) => Some(line.split_once(" if ").map_or("", |(_, cond)| cond)),
+        Some([]) | None => None,
+        Some(_) => bail!("usage: {cmd} <loc> [if <expr>]"),
+    };
+    if condition.is_some_and(|cond| cond.trim().is_empty()) {
+        bail!("missing condition after 'if'");
+    }
+    // Check the condition before planting anything.
+    if let Some(cond) = condition {
+        Expr::parse(cond)?;
+    }
+    let id = set_breakpoint_at(process, arg, cmd == "tbreak")?;
+    let source_line = source_line(arg);
+    let (addr, kind) =
+        process
+            .breakpoints()
+            .find(|bp| bp.id() == id)
+            .map_or((0, "Breakpoint"), |bp| {
+                let kind = if bp.is_temporary() {
+                    "Temporary breakpoint"
+                } else {
+                    "Breakpoint"
+                };
+                (bp.addr(), kind)
+            });
+    match process.addr_to_line(addr) {
+        Some((file, line)) => {
+            if let Some((_, wanted)) = source_line
+                && wanted != line
+            {
+                println!("line {wanted} has no code, using line {line}");
+            }
+            println!("{kind} {id} at {addr:#x}: {}:{line}", file.display());
+        }
+        None => println!("{kind} {id} at {addr:#x}"),
+    }
+    if let Some(cond) = condition {
+        process.set_breakpoint_condition(id, cond)?;
+    }
+    Ok(())
+}
+
+fn cmd_condition(session: &mut Session, inv: &Invocation) -> Result<()> {
+    let Some(arg) = inv.args.get(1) else {
+        bail!("usage: condition <id> [expr]");
+    };
+    let id = parse_breakpoint_id(arg)?;
+    let cond = inv
+        .line
+        .trim()
+        .splitn(3, char::is_whitespace)
+        .nth(2)
+        .unwrap_or("");
+    session.process().set_breakpoint_condition(id, cond)?;
+    if cond.trim().is_empty() {
+        println!("Breakpoint {id} now unconditional.");
+    }
+    Ok(())
+}
+
+fn cmd_ignore(session: &mut Session, inv: &Invocation) -> Result<()> {
+    let (Some(arg), Some(count)) = (inv.args.get(1), inv.args.get(2)) else {
+        bail!("usage: ignore <id> <count>");
+    };
+    let id = parse_breakpoint_id(arg)?;
+    let count: u32 = count
+        .parse()
+        .with_context(|| format!("invalid ignore count: {count}"))?;
+    session.process().set_ignore_count(id, count)?;
+    match count {
+        0 => println!("Will stop next time breakpoint {id} is reached."),
+        1 => println!("Will ignore next crossing of breakpoint {id}."),
+        n => println!("Will ignore next {n} crossings of breakpoint {id}."),
+    }
+    Ok(())
+}
+
+/// `enable` and `disable`.
+fn cmd_enable(session: &mut Session, inv: &Invocation) -> Result<()> {
+    let Some(arg) = inv.args.get(1) else {
+        bail!("usage: {} <id>", inv.name);
+    };
+    let id = parse_breakpoint_id(arg)?;
+    if inv.name == "enable" {
+        session.process().enable_breakpoint(id)?;
+    } else {
+        session.process().disable_breakpoint(id)?;
+    }
+    Ok(())
+}
+
+fn cmd_delete(session: &mut Session, inv: &Invocation) -> Result<()> {
+    let Some(arg) = inv.args.get(1) else {
+        bail!("usage: delete <id>");
+    };
+    let id = parse_breakpoint_id(arg)?;
+    let process = session.process();
+    if process.watchpoints().any(|wp| wp.id() == id) {
+        process.remove_watchpoint(id)?;
+    } else if process.catchpoints().any(|cp| cp.id() == id) {
+        process.remove_catchpoint(id)?;
+    } else {
+        process.delete_breakpoint(id)?;
+    }
+    Ok(())
+}
+
+/// `watch`, `rwatch` and `awatch`.
+fn cmd_watch(session: &mut Session, inv: &Invocation) -> Result<()> {
+    let Some(addr) = inv.args.get(1) else {
+        bail!("usage: {} <addr> [1|2|4|8]", inv.name);
+    };
+    if inv.name == "watch" && inv.args.len() == 2 && is_identifier(addr) {
+        let id = session.process().watch_variable(addr)?;
+        println!("Watchpoint {id}: {addr}");
+        return Ok(());
+    }
+    let addr = parse_address(addr)?;
+    let size = match inv.args.get(2) {
+        Some(size) => size
+            .parse()
+            .ok()
+            .and_then(WatchSize::from_bytes)
+            .with_context(|| format!("watch size must be 1, 2, 4 or 8: {size}"))?,
+        None => WatchSize::Eight,
+    };
+    let kind = match inv.name {
+        "rwatch" => WatchKind::Read,
+        "awatch" => WatchKind::ReadWrite,
+        _ => WatchKind::Write,
+    };
+    let id = session.process().set_watchpoint(addr, size, kind)?;
+    println!("Watchpoint {id} at {addr:#x}");
+    Ok(())
+}
+
+fn cmd_backtrace(session: &mut Session, inv: &Invocation) -> Result<()> {
+    let (full, count) = match inv.args[1..] {
+        [] => (false, None),
+        ["full"] => (true, None),
+        [n] => (false, Some(n)),
+        ["full", n] => (true, Some(n)),
+        _ => bail!("usage: backtrace [full] [n]"),
+    };
+    let count = count
+        .map(|n| {
+            n.parse::<usize>()
+                .with_context(|| format!("invalid number of frames: {n}"))
+        })
+        .transpose()?;
+    let mut out = session.pager();
+    let process = session.process();
+    let backtrace = if full {
+        process.backtrace_full()?
+    } else {
+        process.backtrace()?
+    };
+    let shown = count.map_or(backtrace.len(), |n| n.min(backtrace.len()));
+    session.reporter.backtrace(&mut out, &backtrace[..shown])?;
+    if shown < backtrace.len() {
+        writeln!(out, "(More stack frames follow...)")?;
+    } else if let Some(e) = backtrace.error() {
+        writeln!(out, "Backtrace stopped: {e}")?;
+    }
+    Ok(())
+}
+
+/// `frame`, `up` and `down`.
+fn cmd_frame(session: &mut Session, inv: &Invocation) -> Result<()> {
+    let count = match inv.args.get(1) {
+        Some(n) => Some(
+            n.parse::<usize>()
+                .with_context(|| format!("invalid frame number: {n}"))?,
+        ),
+        None => None,
+    };
+    let current = session.selected_frame;
+    let level = match (inv.name, count) {
+        ("frame", count) => count.unwrap_or(current),
+        ("up", count) => current + count.unwrap_or(1),
+        (_, count) => current
+            .checked_sub(count.unwrap_or(1))
+            .context("Bottom (innermost) frame selected; you cannot go down.")?,
+    };
+    let process = session.process();
+    let frames = process.backtrace()?;
+    let Some(frame) = frames.get(level) else {
+        if inv.name == "up" {
+            bail!("Initial frame selected; you cannot go up.");
+        }
+        bail!("no frame at level {level}; the stack has {}", frames.len());
+    };
+    println!("{}", format_frame(level, frame));
+    if let Some((file, line)) = &frame.location {
+        print_source(file, *line, 0);
+    }
+    session.selected_frame = level;
+    Ok(())
+}
+
+fn cmd_list(session: &mut Session, inv: &Invocation) -> Result<()> {
+    let process = session.process();
+    let (file, line) = match inv.args.get(1) {
+        Some(loc) => source_location(process, loc)?,
+        None => process
+            .addr_to_line(process.pc()?)
+            .context("no line information for the current pc")?,
+    };
+    print_source(&file, line, LIST_CONTEXT);
+    Ok(())
+}
+
+fn cmd_print(session: &mut Session, inv: &Invocation) -> Result<()> {
+    let Some((_, expr)) = inv.line.trim().split_once(char::is_whitespace) else {
+        bail!("usage: print <variable|expr>");
+    };
+    let expr = expr.trim();
+    let frame = session.selected_frame;
+    let process = session.process();
+    if let Some(operand) = char_pointer_cast(expr) {
+        let addr = process.evaluate_in_frame(&Expr::parse(operand)?, frame)? as u64;
+        println!("{expr} = {}", format_c_string(process, addr));
+    } else if is_identifier(expr) {
+        let value = process.read_variable_in_frame(expr, frame)?;
+        match value.char_pointer() {
+            Some(addr) => println!("{expr} = {}", format_c_string(process, addr)),
+            None => println!("{expr} = {value}"),
+        }
+    } else {
+        let value = process.evaluate_in_frame(&Expr::parse(expr)?, frame)?;
+        println!("{expr} = {value:#x}");
+    }
+    Ok(())
+}
+
+/// The operand of `expr` if it starts with a `(char *)` or
+/// `(const char *)` cast.
+fn char_pointer_cast(expr: &str) -> Option<&str> {
+    let (ty, operand) = expr.strip_prefix('(')?.split_once(')')?;
+    let ty: String = ty.split_whitespace().collect();
+    matches!(ty.as_str(), "char*" | "constchar*").then_some(operand)
+}
+
+/// A `char *` as GDB prints it: the address, then the string it points
+/// to or why that could not be read.
+fn format_c_string(process: &ProcessHandle, addr: u64) -> String {
+    match process.read_c_string(addr, DEFAULT_STRING_LIMIT) {
+        Ok(s) => format!("{addr:#x} \"{}\"", s.escape_debug()),
+        Err(e) => format!("{addr:#x} <error: {e}>"),
+    }
+}
+
+fn cmd_display(session: &mut Session, inv: &Invocation) -> Result<()> {
+    let Some((_, expr)) = inv.line.trim().split_once(char::is_whitespace) else {
+        session.show_displays();
+        return Ok(());
+    };
+    let expr = expr.trim();
+    // Refuse what can be neither a variable nor an expression.
+    if !is_identifier(expr) {
+        Expr::parse(expr)?;
+    }
+    let display = AutoDisplay {
+        id: session.next_display_id,
+        expr: expr.to_string(),
+    };
+    session.next_display_id += 1;
+    let process = &session.inferiors[session.current];
+    if matches!(process.state(), ProcessState::Stopped(_)) {
+        show_display(process, &display, session.selected_frame);
+    }
+    session.displays.push(display);
+    Ok(())
+}
+
+fn cmd_undisplay(session: &mut Session, inv: &Invocation) -> Result<()> {
+    let Some(arg) = inv.args.get(1) else {
+        session.displays.clear();
+        return Ok(());
+    };
+    let id: u32 = arg
+        .parse()
+        .with_context(|| format!("invalid display number: {arg}"))?;
+    let idx = session
+        .displays
+        .iter()
+        .position(|display| display.id == id)
+        .with_context(|| format!("no display number {id}"))?;
+    session.displays.remove(idx);
+    Ok(())
+}
+
+/// Print `display` as `n: expr = value` in frame `frame`. A name is read as
+/// a variable when one is visible; anything else is evaluated as an
+/// expression.
+fn show_display(process: &ProcessHandle, display: &AutoDisplay, frame: usize) {
+    let expr = &display.expr;
+    let value = if is_identifier(expr) {
+        match process.read_variable_in_frame(expr, frame) {
+            Ok(value) => Ok(value.to_string()),
+            // A function name stands for its address, as in expressions.
+            Err(e) => process
+                .resolve_symbol(expr)
+                .map(|addr| format!("{addr:#x}"))
+                .ok_or(e),
+        }
+    } else {
+        Expr::parse(expr)
+            .and_then(|parsed| process.evaluate_in_frame(&parsed, frame))
+            .map(|value| format!("{value:#x}"))
+    };
+    match value {
+        Ok(value) => println!("{}: {expr} = {value}", display.id),
+        Err(e) => println!("{}: {expr} = <error: {e}>", display.id),
+    }
+}
+
+fn is_identifier(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn list_displays(displays: &[AutoDisplay]) {
+    if displays.is_empty() {
+        println!("There are no auto-display expressions now.");
+        return;
+    }
+    println!("Auto-display expressions now in effect:");
+    for display in displays {
+        println!("{:<4} {}", format!("{}:", display.id), display.expr);
+    }
+}
+
+fn cmd_regs(session: &mut Session, _: &Invocation) -> Result<()> {
+    let frame = session.selected_frame;
+    let regs = session.process().frame_registers(frame)?;
+    session.reporter.registers(&regs);
+    Ok(())
+}
+
+fn cmd_thread(session: &mut Session, inv: &Invocation) -> Result<()> {
+    if inv.args.len() > 1 {
+        session.selected_frame = 0;
+    }
+    let process = session.process();
+    match inv.args.get(1) {
+        None => println!("Current thread is {}", process.current_thread()),
+        Some(tid) => {
+            let tid = tid
+                .parse()
+                .with_context(|| format!("invalid thread id: {tid}"))?;
+            process.select_thread(tid)?;
+            let pc = process.pc()?;
+            match process.symbol_at(pc) {
+                Some(sym) => println!("Switching to thread {tid}: {pc:#x} in {}", sym.name),
+                None => println!("Switching to thread {tid}: {pc:#x}"),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `$name = <expr>`, also accepted after `set`. Register names set the
+/// register.
+fn assign_convenience(session: &mut Session, assignment: &str) -> Result<()> {
+    let Some((name, expr)) = assignment
+        .split_once('=')
+        .filter(|(_, expr)| !expr.starts_with('='))
+    else {
+        bail!("usage: $name = <expr>");
+    };
+    let name = name.trim();
+    let process = session.process();
+    let value = process.evaluate_str(expr)?;
+    // `$pc` and the like write the register instead.
+    match Expr::parse(&format!("${name}")) {
+        Ok(Expr::Register(reg)) => process.set_register(&reg, value as u64)?,
+        _ => process.set_convenience_var(name, value)?,
+    }
+    println!("${name} = {value:#x}");
+    Ok(())
+}
+
+fn cmd_set(session: &mut Session, inv: &Invocation) -> Result<()> {
+    if let Some(assignment) = inv.args.get(1).and_then(|arg| arg.strip_prefix('$')) {
+        let rest = inv
+            .line
+            .split_once(inv.args[1])
+            .map_or("", |(_, rest)| rest);
+        return assign_convenience(session, &format!("{assignment}{rest}"));
+    }
+    match inv.args.get(1..) {
+        Some(["reg", name, value]) if fpregs::is_fp_register(name) => {
+            let invalid = || format!("invalid value: {value}");
+            let value = if name.starts_with("st") && !value.starts_with("0x") {
+                let float: f64 = value.parse().with_context(invalid)?;
+                let mut bytes = [0; 16];
+                bytes[..10].copy_from_slice(&fpregs::f64_to_x87(float));
+                u128::from_le_bytes(bytes)
+            } else {
+                parse_u128(value).with_context(invalid)?
+            };
+            session.process().set_fpreg(name, value)?;
+        }
+        Some(["reg", name, value]) => {
+            let value = parse_u64(value).with_context(|| format!("invalid value: {value}"))?;
+            session.process().set_register(name, value)?;
+        }
+        Some([]) | None => {
+            bail!("usage: set reg <r> <val> | set $name = <e> | set <setting> <value>")
+        }
+        Some(words) => settings::set(session, words)?,
+    }
+    Ok(())
+}
+
+fn cmd_show(session: &mut Session, inv: &Invocation) -> Result<()> {
+    settings::show(session, &inv.args[1..])
+}
+
+fn cmd_inferiors(session: &mut Session, _: &Invocation) -> Result<()> {
+    for (i, process) in session.inferiors.iter().enumerate() {
+        let marker = if i == session.current { "*" } else { " " };
+        println!("{marker} {:<3} process {}", i + 1, process.pid());
+    }
+    Ok(())
+}
+
+fn cmd_inferior(session: &mut Session, inv: &Invocation) -> Result<()> {
+    let Some(n) = inv.args.get(1) else {
+        bail!("usage: inferior <n>");
+    };
+    match n.parse::<usize>() {
+        Ok(n) if (1..=session.inferiors.len()).contains(&n) => {
+            session.current = n - 1;
+            session.selected_frame = 0;
+            println!(
+                "Switching to inferior {n} (process {})",
+                session.process().pid()
+            );
+            Ok(())
+        }
+        _ => bail!("no inferior {n}"),
+    }
+}
+
+fn cmd_disassemble(session: &mut Session, inv: &Invocation) -> Result<()> {
+    let mut out = session.pager();
+    let bytes = session.settings.disassembly_bytes;
+    let process = session.process();
+    let pc = process.pc()?;
+    match inv.args[1..] {
+        // A bare function name shows its whole body with source.
+        [name] if parse_u64(name).is_none() => {
+            let insns = process.disassemble_function(name)?;
+            let listing = Listing {
+                source: true,
+                bytes,
+            };
+            print_disassembly(&mut out, process, &insns, pc, listing)?;
+        }
+        [] => {
+            let insns = process.disassemble(pc, DISASSEMBLE_COUNT)?;
+            let listing = Listing {
+                source: false,
+                bytes,
+            };
+            print_disassembly(&mut out, process, &insns, pc, listing)?;
+        }
+        [loc, ref count @ ..] if count.len() <= 1 => {
+            let count = match count.first() {
+                Some(count) => count
+                    .parse()
+                    .with_context(|| format!("invalid instruction count: {count}"))?,
+                None => DISASSEMBLE_COUNT,
+            };
+            let insns = process.disassemble(resolve_location(process, loc)?, count)?;
+            let listing = Listing {
+                source: false,
+                bytes,
+            };
+            print_disassembly(&mut out, process, &insns, pc, listing)?;
+        }
+        _ => bail!("usage: disassemble [function | <loc> [count]]"),
+    }
+    Ok(())
+}
+
+/// Most bytes one `mem read` shows.
+const MEM_READ_MAX_LEN: u64 = 1 << 20;
+
+fn cmd_mem(session: &mut Session, inv: &Invocation) -> Result<()> {
+    let mut out = session.pager();
+    let process = session.process();
+    match inv.args.get(1..) {
+        Some(["read", addr, len]) => {
+            let addr = parse_address(addr)?;
+            let len = parse_u64(len).with_context(|| format!("invalid length: {len}"))?;
+            if len > MEM_READ_MAX_LEN {
+                bail!("length must be at most {MEM_READ_MAX_LEN}: {len}");
+            }
+            match process.read_memory(addr, len as usize) {
+                Ok(bytes) => write!(out, "{}", format_hexdump(addr, &bytes))?,
+                Err(FdbError::PartialRead { data, .. }) if !data.is_empty() => {
+                    write!(out, "{}", format_hexdump(addr, &data))?;
+                    eprintln!("(only {} of {len} bytes readable)", data.len());
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Some(["write", addr, hex @ ..]) if !hex.is_empty() => {
+            let addr = parse_address(addr)?;
+            let bytes = parse_hex_bytes(&hex.concat())?;
+            process.write_memory(addr, &bytes)?;
+        }
+        _ => bail!("usage: mem read <addr> <len> | mem write <addr> <hexbytes>"),
+    }
+    Ok(())
+}
+
+fn cmd_dump(session: &mut Session, inv: &Invocation) -> Result<()> {
+    let ["binary", "memory", path, start, end] = inv.args[1..] else {
+        bail!("usage: dump binary memory <file> <start> <end>");
+    };
+    let (start, end) = (parse_address(start)?, parse_address(end)?);
+    if end < start {
+        bail!("end address {end:#x} is before start {start:#x}");
+    }
+    let len = (end - start) as usize;
+    match session.process().dump_memory(start, len, Path::new(path)) {
+        Ok(()) => println!("Wrote {len} bytes to {path}"),
+        Err(e @ FdbError::PartialDump { written, .. }) => {
+            println!("Wrote {written} bytes to {path}");
+            return Err(e.into());
+        }
+        Err(e) => return Err(e.into()),
+    }
+    Ok(())
+}
+
+fn cmd_restore(session: &mut Session, inv: &Invocation) -> Result<()> {
+    let [path, addr] = inv.args[1..] else {
+        bail!("usage: restore <file> <addr>");
+    };
+    let addr = parse_address(addr)?;
+    session.process().restore_memory(addr, Path::new(path))?;
+    println!("Restored {path} at {addr:#x}");
+    Ok(())
+}
+
+fn cmd_find(session: &mut Session, inv: &Invocation) -> Result<()> {
+    const USAGE: &str = "usage: find <start> <end> <hexbytes> | find <start> <end> \"text\"";
+    let (Some(start), Some(end), Some(pattern)) =
+        (inv.args.get(1), inv.args.get(2), inv.args.get(3))
+    else {
+        bail!(USAGE);
+    };
+    let (start, end) = (parse_address(start)?, parse_address(end)?);
+    let needle = if pattern.starts_with('"') {
+        // Text may contain spaces, so take it from the raw line.
+        let quoted = &inv.line[inv.line.find('"').context(USAGE)?..];
+        parse_quoted(quoted.trim_end())?
+    } else {
+        parse_hex_bytes(&inv.args[3..].concat())?
+    };
+    let found = session.process().search_memory(start, end, &needle)?;
+    for addr in &found {
+        println!("{addr:#x}");
+    }
+    match found.len() {
+        0 => println!("Pattern not found."),
+        1 => println!("1 pattern found."),
+        n => println!("{n} patterns found."),
+    }
+    Ok(())
+}
+
+/// The bytes of a double-quoted string with C escapes such as `\n`, `\t`,
+/// `\\`, `\"` and `\0`.
+fn parse_quoted(s: &str) -> Result<Vec<u8>> {
+    let inner = s
+        .strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .with_context(|| format!("unterminated string: {s}"))?;
+    let mut bytes = Vec::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        let c = match c {
+            '\\' => match chars.next() {
+                Some('n') => '\n',
+                Some('t') => '\t',
+                Some('r') => '\r',
+                Some('0') => '\0',
+                Some(c @ ('\\' | '"')) => c,
+                Some(c) => bail!("unknown escape: \\{c}"),
+                None => bail!("string ends with a backslash"),
+            },
+            c => c,
+        };
+        let mut buf = [0; 4];
+        bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+    }
+    Ok(bytes)
+}
+
+/// `x/NFU <addr>`.
+fn cmd_examine(session: &mut Session, inv: &Invocation) -> Result<()> {
+    let spec = inv.args[0].strip_prefix('x').unwrap_or_default();
+    let spec = parse_examine_spec(spec)?;
+    let Some(arg) = inv.args.get(1) else {
+        bail!("usage: x/NFU <addr>");
+    };
+    let mut out = session.pager();
+    let process = session.process();
+    let addr = resolve_location(process, arg)?;
+    examine_memory(&mut out, process, addr, spec)
+}
+
+fn cmd_info(session: &mut Session, inv: &Invocation) -> Result<()> {
+    if inv.args[1..] == ["display"] {
+        list_displays(&session.displays);
+        return Ok(());
+    }
+    let (reporter, frame) = (session.reporter, session.selected_frame);
+    let process = session.process();
+    match inv.args[1..] {
+        [] => {
+            println!("Process PID: {}", process.pid());
+            if let Some(code) = process.exit_status() {
+                println!("State: exited with code {code}");
+            } else if let Some(sig) = process.termination_signal() {
+                println!("State: killed by {sig}");
+            } else {
+                println!("State: {:?}", process.state());
+            }
+            println!("Load bias: {:#x}", process.load_bias());
+        }
+        ["breakpoints" | "break" | "b"] => list_breakpoints(process),
+        ["frame" | "f"] => print_frame_info(process)?,
+        ["convenience" | "conv"] => {
+            let mut any = false;
+            for (name, value) in process.convenience_vars() {
+                any = true;
+                println!("${name} = {value:#x}");
+            }
+            if !any {
+                println!("No convenience variables; set one with $name = <expr>.");
+            }
+        }
+        ["locals"] => {
+            let locals = process.locals_in_frame(frame)?;
+            if locals.is_empty() {
+                println!("No locals.");
+            }
+            for (name, value) in locals {
+                println!("{name} = {value}");
+            }
+        }
+        ["registers" | "reg" | "r"] => reporter.registers(&process.frame_registers(frame)?),
+        ["registers" | "reg" | "r", name] => {
+            let value = process.frame_registers(frame)?.get(name)?;
+            match name {
+                "rflags" | "eflags" => {
+                    println!("{name:<8} {value:#018x} {}", format_rflags(value))
+                }
+                _ => println!("{name:<8} {value:#018x}"),
+            }
+        }
+        ["all-registers"] => {
+            let snapshot = process.get_registers()?;
+            let mut regs = snapshot.general_purpose();
+            regs.push(("orig_rax", snapshot.orig_rax()));
+            regs.extend(process.debug_registers()?);
+            print_registers(&regs);
+        }
+        ["sharedlibrary" | "shared"] => {
+            let libs = process.shared_libraries()?;
+            if libs.is_empty() {
+                println!("No shared libraries loaded at this time.");
+                return Ok(());
+            }
+            println!("{:<18} {:<18} {:<9} library", "from", "to", "symbols");
+            for lib in libs {
+                let symbols = if lib.symbols_loaded { "yes" } else { "no" };
+                println!(
+                    "{:#018x} {:#018x} {symbols:<9} {}",
+                    lib.base, lib.end, lib.path
+                );
+            }
+        }
+        ["float"] => {
+            let fp = process.get_fpregs()?;
+            for n in 0..fpregs::ST_COUNT {
+                let raw = fp.st(n).unwrap_or_default();
+                let hex: String = raw.iter().rev().map(|b| format!("{b:02x}")).collect();
+                let tag = if fp.st_valid(n) { "" } else { " (empty)" };
+                println!("st{n}      0x{hex}  {}{tag}", fpregs::x87_to_f64(raw));
+            }
+            for name in ["fctrl", "fstat", "ftag", "fop"] {
+                println!("{name:<8} {:#06x}", fp.get(name)?);
+            }
+        }
+        ["vector"] => {
+            let fp = process.get_fpregs()?;
+            for n in 0..fpregs::XMM_COUNT {
+                let value = fp.xmm(n).unwrap_or_default();
+                println!("{:<8} {}", format!("xmm{n}"), fpregs::format_xmm(value));
+            }
+            let mxcsr = fp.mxcsr();
+            println!("mxcsr    {mxcsr:#010x} {}", fpregs::format_mxcsr(mxcsr));
+        }
+        ["threads"] => {
+            println!("  {:<8} state", "tid");
+            for thread in process.threads() {
+                let state = match thread.state {
+                    ThreadState::Running => "running",
+                    ThreadState::Stopped => "stopped",
+                };
+                let marker = if thread.tid == process.current_thread() {
+                    '*'
+                } else {
+                    ' '
+                };
+                println!("{marker} {:<8} {state}", thread.tid);
+            }
+        }
+        ["proc", "mappings"] => {
+            println!(
+                "{:>18} {:>18} {:>10} {:>10}  {:<5} file",
+                "start", "end", "size", "offset", "perms"
+            );
+            for region in process.memory_map()? {
+                println!(
+                    "{:#18x} {:#18x} {:#10x} {:#10x}  {:<5} {}",
+                    region.start,
+                    region.end,
+                    region.end - region.start,
+                    region.offset,
+                    region.perms.to_string(),
+                    region.pathname.as_deref().unwrap_or("")
+                );
+            }
+        }
+        _ => {
+            // Listed from the registry so that new forms are never missed.
+            let forms: Vec<&str> = COMMANDS
+                .iter()
+                .find(|spec| spec.name == inv.name)
+                .map(|spec| spec.forms)
+                .unwrap_or_default()
+                .iter()
+                .map(|(synopsis, _)| *synopsis)
+                .filter(|synopsis| !synopsis.is_empty())
+                .collect();
+            bail!("usage: info [{}]", forms.join(" | "))
+        }
+    }
+    Ok(())
+}
+
+fn cmd_catch(session: &mut Session, inv: &Invocation) -> Result<()> {
+    let process = session.process();
+    match inv.args.get(1..) {
+        Some(["syscall"]) => {
+            process.set_syscall_catch(Some(SyscallCatch::All));
+            println!("Catching all syscalls.");
+        }
+        Some(["syscall", "off"]) => {
+            process.set_syscall_catch(None);
+            println!("No longer catching syscalls.");
+        }
+        Some(["syscall", names @ ..]) => {
+            let numbers = parse_syscalls(names)?;
+            process.set_syscall_catch(Some(SyscallCatch::Only(numbers)));
+            println!("Catching syscalls: {}", names.join(" "));
+        }
+        Some(["exit"]) => {
+            process.set_catch_exit(true);
+            println!("Catching process exit.");
+        }
+        Some(["exit", "off"]) => {
+            process.set_catch_exit(false);
+            println!("No longer catching process exit.");
+        }
+        Some(["exec"]) => catchpoint(process, CatchKind::Exec),
+        Some(["fork"]) => catchpoint(process, CatchKind::Fork),
+        Some(["signal", sig]) => catchpoint(process, CatchKind::Signal(parse_signal(sig)?)),
+        _ => bail!(
+            "usage: catch syscall [off | <name|number>...] | catch exit [off] \
+             | catch exec | catch fork | catch signal <sig>"
+        ),
+    }
+    Ok(())
+}
+
+fn catchpoint(process: &mut ProcessHandle, kind: CatchKind) {
+    let id = process.add_catchpoint(kind);
+    println!("Catchpoint {id} ({kind})");
+}
+
+fn cmd_signal(session: &mut Session, inv: &Invocation) -> Result<()> {
+    let (name, force) = match inv.args[1..] {
+        [name] => (name, false),
+        [name, "--force"] => (name, true),
+        _ => bail!("usage: signal <sig> [--force]"),
+    };
+    let sig = parse_signal(name)?;
+    if sig == Signal::SIGKILL && !force {
+        bail!("refusing to send SIGKILL without --force (use 'kill' to end the process)");
+    }
+    let process = session.process();
+    process.send_signal(sig)?;
+    println!(
+        "Sent {sig} to process {}; it is delivered when the process resumes.",
+        process.pid()
+    );
+    Ok(())
+}
+
+fn cmd_handle(session: &mut Session, inv: &Invocation) -> Result<()> {
+    let Some(name) = inv.args.get(1) else {
+        bail!("usage: handle <signal> [no]stop [no]print [no]pass");
+    };
+    let process = session.process();
+    let sig = parse_signal(name)?;
+    let mut policy = process.signal_policy(sig);
+    for keyword in &inv.args[2..] {
+        match *keyword {
+            // As in GDB, stopping implies printing and silence implies not stopping.
+            "stop" => (policy.stop, policy.print) = (true, true),
+            "nostop" => policy.stop = false,
+            "print" => policy.print = true,
+            "noprint" => (policy.print, policy.stop) = (false, false),
+            "pass" | "noignore" => policy.pass = true,
+            "nopass" | "ignore" => policy.pass = false,
+            other => bail!("unrecognized handle keyword: {other}"),
+        }
+    }
+    process.set_signal_policy(sig, policy.stop, policy.print, policy.pass)?;
+    let yes_no = |b: bool| if b { "Yes" } else { "No" };
+    println!("Signal        Stop\tPrint\tPass");
+    println!(
+        "{:<14}{}\t{}\t{}",
+        sig.as_str(),
+        yes_no(policy.stop),
+        yes_no(policy.print),
+        yes_no(policy.pass)
+    );
+    Ok(())
+}
+
+fn cmd_source(session: &mut Session, inv: &Invocation) -> Result<()> {
+    let Some(path) = inv.args.get(1) else {
+        bail!("usage: source <file>");
+    };
+    source_file(session, Path::new(path))
+}
+
+fn cmd_save(session: &mut Session, inv: &Invocation) -> Result<()> {
+    let (Some(&"breakpoints"), Some(path)) = (inv.args.get(1), inv.args.get(2)) else {
+        bail!("usage: save breakpoints <file>");
+    };
+    let specs = session.process().export_breakpoints();
+    let contents: String = specs.iter().map(|spec| format!("{spec}\n")).collect();
+    std::fs::write(path, contents).with_context(|| format!("failed to write {path}"))?;
+    println!("Saved {} breakpoints to {path}", specs.len());
+    Ok(())
+}
+
+fn cmd_kill(session: &mut Session, _: &Invocation) -> Result<()> {
+    let process = session.process();
+    process.kill()?;
+    println!("Killed process {}", process.pid());
+    Ok(())
+}
+
+fn cmd_detach(session: &mut Session, _: &Invocation) -> Result<()> {
+    let process = session.process();
+    process.detach()?;
+    println!("Detached from process {}", process.pid());
+    Ok(())
+}
+
+fn cmd_quit(session: &mut Session, _: &Invocation) -> Result<()> {
+    session.quit = true;
+    Ok(())
+}
+
+/// Print name/value pairs two to a line.
+fn print_registers(regs: &[(&str, u64)]) {
+    for pair in regs.chunks(2) {
+        let line: Vec<String> = pair
+            .iter()
+            .map(|(name, value)| {
+                let pad = " ".repeat(8usize.saturating_sub(name.len()));
+                let name = output::paint(name, Style::Register);
+                format!("{name}{pad} {value:#018x}")
+            })
+            .collect();
+        println!("{}", line.join("    "));
+    }
+}
+
+/// `info frame`: where the current frame lies on the stack, how it was
+/// unwound, and where its caller's registers are saved.
+fn print_frame_info(process: &ProcessHandle) -> Result<()> {
+    let info = process.current_frame_info()?;
+    match info.cfa {
+        Some(cfa) => println!("Stack frame at {cfa:#x}:"),
+        None => println!("Stack frame at an unknown address:"),
+    }
+    let mut rip = format!(" rip = {:#x}", info.pc);
+    if let Some(function) = &info.function {
+        rip.push_str(&format!(" in {function}"));
+    }
+    if let Some((file, line)) = process.addr_to_line(info.pc) {
+        rip.push_str(&format!(" ({}:{line})", file.display()));
+    }
+    if let Some((_, saved)) = info.saved_rip {
+        rip.push_str(&format!("; saved rip = {saved:#x}"));
+    }
+    println!("{rip}");
+    if let Some(caller) = info.caller_cfa {
+        println!(" called by frame at {caller:#x}");
+    }
+    if let Some(cfa) = info.cfa {
+        println!(
+            " frame spans {:#x} to {cfa:#x} ({} bytes)",
+            info.sp,
+            cfa.wrapping_sub(info.sp)
+        );
+        let source = if info.from_cfi {
+            "call frame information"
+        } else {
+            "the frame pointer"
+        };
+        println!(" unwound using {source}");
+    }
+    let mut saved: Vec<String> = info
+        .saved_registers
+        .iter()
+        .map(|(name, slot)| format!("{name} at {slot:#x}"))
+        .collect();
+    if let Some((slot, _)) = info.saved_rip {
+        saved.push(format!("rip at {slot:#x}"));
+    }
+    if !saved.is_empty() {
+        println!(" Saved registers:");
+        println!("  {}", saved.join(", "));
+    }
+    Ok(())
+}
+
+/// Print the breakpoint and watchpoint table.
+fn list_breakpoints(process: &ProcessHandle) {
+    let mut any = false;
+    for bp in process.breakpoints() {
+        any = true;
+        let state = if bp.is_enabled() {
+            "enabled"
+        } else {
+            "disabled"
+        };
+        let kind = if bp.is_temporary() {
+            "tbreakpoint"
+        } else {
+            "breakpoint"
+        };
+        println!("{:<4} {kind} {:#018x} {state}", bp.id(), bp.addr());
+        if let Some(cond) = bp.condition() {
+            println!("        stop only if {cond}");
+        }
+        match bp.hit_count() {
+            0 => {}
+            1 => println!("        breakpoint already hit 1 time"),
+            n => println!("        breakpoint already hit {n} times"),
+        }
+        if bp.ignore_count() > 0 {
+            println!("        will ignore next {} crossings", bp.ignore_count());
+        }
+    }
+    for wp in process.watchpoints() {
+        any = true;
+        let variable = wp.variable().map(|name| format!(" {name}"));
+        println!(
+            "{:<4} watchpoint {:#018x} {} bytes {:?}{}",
+            wp.id(),
+            wp.addr(),
+            wp.size().bytes(),
+            wp.kind(),
+            variable.unwrap_or_default()
+        );
+    }
+    for cp in process.catchpoints() {
+        any = true;
+        println!("{:<4} catchpoint {}", cp.id(), cp.kind());
+        match cp.hit_count() {
+            0 => {}
+            1 => println!("        catchpoint already hit 1 time"),
+            n => println!("        catchpoint already hit {n} times"),
+        }
+    }
+    if !any {
+        println!("No breakpoints, watchpoints or catchpoints.");
+    }
+}
+
+/// Lines of context printed on either side of the current line on a stop.
+const STOP_CONTEXT: u32 = 2;
+/// Lines of context printed on either side of the target of `list`.
+const LIST_CONTEXT: u32 = 5;
+
+/// Resolve a `list` argument, either `file:line` or a function name.
+fn source_location(process: &ProcessHandle, loc: &str) -> Result<(PathBuf, u32)> {
+    if let Some((file, line)) = loc.rsplit_once(':')
+        && let Ok(line) = line.parse::<u32>()
+    {
+        let addr = process
+            .line_to_addr(file, line)
+            .with_context(|| format!("no code at {loc}"))?;
+        // Keep the requested line if it has no code of its own.
+        let (path, _) = process.addr_to_line(addr).context("no line information")?;
+        return Ok((path, line));
+    }
+    let addr = resolve_location(process, loc)?;
+    process
+        .addr_to_line(addr)
+        .with_context(|| format!("no line information for {loc}"))
+}
+
+/// Print `line` of `file` with `context` lines around it, marking `line`.
+fn print_source(file: &Path, line: u32, context: u32) {
+    let Ok(text) = std::fs::read_to_string(file) else {
+        println!("<source unavailable>");
+        return;
+    };
+    let first = line.saturating_sub(context).max(1);
+    for (number, text) in (first..).zip(text.lines().skip(first as usize - 1)) {
+        if number > line + context {
+            break;
+        }
+        let marker = if number == line { ">" } else { " " };
+        println!("{marker}{number:>5}  {text}");
+    }
+}
+
+/// Describe a syscall stop in the style of strace.
+fn format_syscall(process: &ProcessHandle, event: &SyscallEvent) -> String {
+    match *event {
+        SyscallEvent::Entry { number, args } => {
+            format!("syscall entry: {}", process.decode_syscall(number, &args))
+        }
+        SyscallEvent::Exit { number, ret } => {
+            format!("syscall exit: {} = {ret}", display_name(number))
+        }
+    }
+}
+
+/// Wait for the running process to stop and describe the stop. Ctrl-C, or
+/// running past `timeout`, interrupts the program.
+fn wait_and_report(
+    reporter: &dyn Reporter,
+    process: &mut ProcessHandle,
+    timeout: Option<Duration>,
+) -> Result<()> {
+    let state = interrupt::wait_for_stop(process, timeout)?;
+    report_stop(reporter, process, state)
+}
+
+/// Report `state`, after the signals passed on and the condition errors
+/// met on the way to it.
+fn report_stop(
+    reporter: &dyn Reporter,
+    process: &mut ProcessHandle,
+    state: ProcessState,
+) -> Result<()> {
+    for sig in process.take_signal_notices() {
+        println!("received signal {sig:?} (not stopping)");
+    }
+    for (id, e) in process.take_condition_errors() {
+        println!("error in condition of breakpoint {id}: {e}");
+    }
+    reporter.state(process, state)
+}
+
+/// Count, display format, and unit size parsed from an `x/NFU` command.
+#[derive(Debug, Clone, Copy)]
+struct ExamineSpec {
+    count: usize,
+    format: char,
+    unit: usize,
+}
+
+/// Most units one `x` command shows.
+const EXAMINE_MAX_COUNT: usize = 65536;
+
+/// Parse the `/NFU` suffix of `x`; missing parts default to `1`, `x`, and `w`.
+fn parse_examine_spec(spec: &str) -> Result<ExamineSpec> {
+    let mut parsed = ExamineSpec {
+        count: 1,
+        format: 'x',
+        unit: 4,
+    };
+    let Some(rest) = spec.strip_prefix('/') else {
+        return Ok(parsed);
+    };
+
+    let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits > 0 {
+        parsed.count = rest[..digits]
+            .parse()
+            .ok()
+            .filter(|&count| count <= EXAMINE_MAX_COUNT)
+            .with_context(|| {
+                format!(
+                    "count must be at most {EXAMINE_MAX_COUNT}: {}",
+                    &rest[..digits]
+                )
+            })?;
+    }
+    for c in rest[digits..].chars() {
+        match c {
+            'x' | 'd' | 'u' | 'o' | 't' => parsed.format = c,
+            'b' => parsed.unit = 1,
+            'h' => parsed.unit = 2,
+            'w' => parsed.unit = 4,
+            'g' => parsed.unit = 8,
+            _ => bail!("invalid format or unit letter '{c}' in x{spec}"),
+        }
+    }
+    Ok(parsed)
+}
+
+/// Instructions shown by `disassemble` without a count.
+const DISASSEMBLE_COUNT: usize = 10;
+
+/// Most instructions `disassemble-next-line` lists for one source line.
+const NEXT_LINE_MAX: usize = 32;
+
+/// Narrowest bytes column of a listing, which fits ten bytes.
+const BYTES_WIDTH: usize = 30;
+
+/// What a disassembly listing shows besides the instructions.
+#[derive(Debug, Clone, Copy)]
+struct Listing {
+    /// Each new source line, above its first instruction.
+    source: bool,
+    /// The bytes of each instruction, before its mnemonic.
+    bytes: bool,
+}
+
+/// Print decoded instructions, marking the one at `pc`.
+fn print_disassembly(
+    out: &mut impl Write,
+    process: &ProcessHandle,
+    insns: &[Instruction],
+    pc: u64,
+    listing: Listing,
+) -> io::Result<()> {
+    // Wide enough for the longest encoding, so the mnemonics line up.
+    let width = insns
+        .iter()
+        .map(|insn| insn.bytes.len() * 3)
+        .fold(BYTES_WIDTH, usize::max);
+    let mut current: Option<(PathBuf, u32)> = None;
+    let mut source_text: Option<(PathBuf, String)> = None;
+    for insn in insns {
+        // Line 0 marks code that belongs to no source line.
+        if listing.source
+            && let Some((file, line)) = process.addr_to_line(insn.address)
+            && line != 0
+            && current.as_ref() != Some(&(file.clone(), line))
+        {
+            if source_text.as_ref().is_none_or(|(path, _)| *path != file) {
+                let contents = std::fs::read_to_string(&file).unwrap_or_default();
+                source_text = Some((file.clone(), contents));
+            }
+            let line_text = source_text
+                .as_ref()
+                .and_then(|(_, contents)| contents.lines().nth(line as usize - 1))
+                .unwrap_or("");
+            writeln!(out, "{line:>5}  {line_text}")?;
+            current = Some((file, line));
+        }
+        let marker = if insn.address == pc { "=>" } else { "  " };
+        let bytes = if listing.bytes {
+            let bytes: Vec<String> = insn.bytes.iter().map(|b| format!("{b:02x}")).collect();
+            format!("{:<width$} ", bytes.join(" "))
+        } else {
+            String::new()
+        };
+        let target = insn
+            .target_symbol
+            .as_ref()
+            .map(|sym| format!(" <{sym}>"))
+            .unwrap_or_default();
+        let text = format!("{} {}", insn.mnemonic, insn.operands);
+        let line = format!(
+            "{marker} {:#018x}  {bytes}{}{target}",
+            insn.address,
+            text.trim_end()
+        );
+        if insn.address == pc {
+            writeln!(out, "{}", output::paint(&line, Style::CurrentInstruction))?;
+        } else {
+            writeln!(out, "{line}")?;
+        }
+    }
+    Ok(())
+}
+
+fn examine_memory(
+    out: &mut impl Write,
+    process: &ProcessHandle,
+    addr: u64,
+    spec: ExamineSpec,
+) -> Result<()> {
+    let len = spec
+        .count
+        .checked_mul(spec.unit)
+        .context("too many bytes to examine")?;
+    let bytes = process.read_memory(addr, len)?;
+    if spec.format == 'x' && spec.unit == 1 {
+        write!(out, "{}", format_hexdump(addr, &bytes))?;
+        return Ok(());
+    }
+
+    let per_line = (16 / spec.unit).max(1);
+    for (i, row) in bytes.chunks(per_line * spec.unit).enumerate() {
+        let values: Vec<String> = row
+            .chunks(spec.unit)
+            .map(|unit| {
+                let mut raw = [0u8; 8];
+                raw[..unit.len()].copy_from_slice(unit);
+                let value = u64::from_le_bytes(raw);
+                let width = spec.unit * 2;
+                match spec.format {
+                    'd' => {
+                        let shift = 64 - 8 * spec.unit as u32;
+                        (((value << shift) as i64) >> shift).to_string()
+                    }
+                    'u' => value.to_string(),
+                    'o' => format!("{value:#o}"),
+                    't' => format!("{value:0width$b}", width = spec.unit * 8),
+                    _ => format!("0x{value:0width$x}"),
+                }
+            })
+            .collect();
+        let line_addr = addr + (i * per_line * spec.unit) as u64;
+        writeln!(out, "{line_addr:#018x}: {}", values.join(" "))?;
+    }
+    Ok(())
+}
+
+/// Parse a signal given as `SIGUSR1`, `usr1`, or a number.
+fn parse_signal(s: &str) -> Result<Signal> {
+    if let Ok(num) = s.parse::<i32>() {
+        return Signal::try_from(num).with_context(|| format!("invalid signal number: {s}"));
+    }
+    let upper = s.to_ascii_uppercase();
+    let name = if upper.starts_with("SIG") {
+        upper
+    } else {
+        format!("SIG{upper}")
+    };
+    name.parse().with_context(|| format!("unknown signal: {s}"))
+}
+
+/// Parse a string of hex digit pairs such as `deadbeef` into bytes.
+fn parse_hex_bytes(s: &str) -> Result<Vec<u8>> {
+    let s = s.strip_prefix("0x").unwrap_or(s);
+    if let Some(bad) = s.chars().find(|c| !c.is_ascii_hexdigit()) {
+        bail!("invalid hex digit '{bad}' in {s}");
+    }
+    if s.is_empty() || !s.len().is_multiple_of(2) {
+        bail!("expected an even number of hex digits: {s}");
+    }
+    // All ASCII now, so every pair of bytes is a pair of digits.
+    Ok(s.as_bytes()
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).unwrap(), 16).unwrap())
+        .collect())
+}
+
+fn split_whitespace(line: &str) -> Vec<&str> {
+    // GDB/LLDB-style CLIs generally treat any whitespace as a separator.
+    line.split_whitespace().collect()
+}
+
+/// Address of `s`, given as a number or an expression such as a function
+/// name or `$var + 8`.
+fn resolve_location(process: &ProcessHandle, s: &str) -> Result<u64> {
+    match parse_u64(s) {
+        Some(addr) => Ok(addr),
+        None => Ok(process.evaluate_str(s)? as u64),
+    }
+}
+
+/// Plant a breakpoint at `loc`, given as for `break`, and return its id.
+fn set_breakpoint_at(process: &mut ProcessHandle, loc: &str, temporary: bool) -> Result<u32> {
+    if temporary {
+        return Ok(process.set_temp_breakpoint(code_location(process, loc)?)?);
+    }
+    // `*loc` is an exact address; a bare name skips the prologue.
+    Ok(match (loc.strip_prefix('*'), source_line(loc)) {
+        (Some(addr), _) => process.set_breakpoint(resolve_location(process, addr)?)?,
+        (None, Some((file, line))) => process.set_breakpoint_at_line(file, line)?,
+        (None, None) => match parse_u64(loc) {
+            Some(addr) => process.set_breakpoint(addr)?,
+            None => process.set_breakpoint_at_function(loc)?,
+        },
+    })
+}
+
+/// Split a `file:line` location.
+fn source_line(loc: &str) -> Option<(&str, u32)> {
+    loc.rsplit_once(':')
+        .and_then(|(file, line)| Some((file, line.parse::<u32>().ok()?)))
+}
+
+/// Address of a breakpoint-style location: `*loc` exactly, `file:line`, a
+/// number, or a function name past its prologue.
+fn code_location(process: &ProcessHandle, arg: &str) -> Result<u64> {
+    Ok(match (arg.strip_prefix('*'), source_line(arg)) {
+        (Some(loc), _) => resolve_location(process, loc)?,
+        (None, Some((file, line))) => process
+            .line_to_addr(file, line)
+            .with_context(|| format!("no code for {file}:{line}"))?,
+        (None, None) => match parse_u64(arg) {
+            Some(addr) => addr,
+            None => process
+                .function_breakpoint_addr(arg)
+                .ok_or_else(|| FdbError::UnknownSymbol(arg.to_string()))?,
+        },
+    })
+}
+
+/// Parse an address given as `0x`-prefixed hex or plain decimal.
+fn parse_address(s: &str) -> Result<u64> {
+    parse_u64(s).with_context(|| format!("invalid address: {s}"))
+}
+
+/// Parse an integer given as `0x`-prefixed hex or plain decimal.
+fn parse_u64(s: &str) -> Option<u64> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+fn parse_u128(s: &str) -> Option<u128> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u128::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+fn parse_breakpoint_id(s: &str) -> Result<u32> {
+    s.parse()
+        .with_context(|| format!("invalid breakpoint id: {s}"))
 }
 
 fn is_prefix<S: AsRef<str>>(s: S, of: S) -> bool {
@@ -0,0 +1,652 @@
+//! A subset of the GDB/MI machine interface, for editor front ends.
+//!
+//! Each input line is `[token]-operation [args...]`; every command answers
+//! with one `^done`, `^running`, `^error` or `^exit` result record carrying
+//! the token, and execution commands follow `^running` with `*running` and
+//! `*stopped` records. Output ends with the `(gdb)` prompt after each command.
+
+use std::fmt;
+use std::io::BufRead;
+
+use anyhow::{Result, bail};
+use libfdb::process::ProcessHandle;
+use libfdb::threads::ThreadState;
+use libfdb::watchpoint::WatchKind;
+use libfdb::{ExitStatus, ProcessState, StopCause};
+use nix::sys::signal::Signal;
+
+use crate::{
+    Session, adopt_fork_children, code_location, interrupt, resolve_location, set_breakpoint_at,
+};
+
+/// A value in an MI record: a quoted constant, a `{...}` tuple of results,
+/// or a `[...]` list of values or of results.
+#[derive(Debug, Clone)]
+enum Value {
+    Const(String),
+    Tuple(Vec<(&'static str, Value)>),
+    List(Vec<Value>),
+    ResultList(Vec<(&'static str, Value)>),
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::Const(s)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::Const(s.to_string())
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Const(s) => write!(f, "\"{}\"", escape(s)),
+            Value::Tuple(results) => write!(f, "{{{}}}", Results(results)),
+            Value::ResultList(results) => write!(f, "[{}]", Results(results)),
+            Value::List(values) => {
+                let values: Vec<String> = values.iter().map(Value::to_string).collect();
+                write!(f, "[{}]", values.join(","))
+            }
+        }
+    }
+}
+
+/// Comma-separated `name=value` pairs.
+struct Results<'a>(&'a [(&'static str, Value)]);
+
+impl fmt::Display for Results<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (name, value)) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{name}={value}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Escape `s` for use inside an MI C string.
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// One parsed MI input line.
+#[derive(Debug, PartialEq, Eq)]
+struct Command {
+    token: Option<u64>,
+    operation: String,
+    args: Vec<String>,
+}
+
+/// Parse `[token]-operation [args...]`; arguments may be C strings.
+fn parse_command(line: &str) -> Result<Command> {
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let token = match digits {
+        0 => None,
+        _ => Some(line[..digits].parse()?),
+    };
+    let Some(rest) = line[digits..].strip_prefix('-') else {
+        bail!("only MI commands are accepted: {line}");
+    };
+    let mut words = split_args(rest)?.into_iter();
+    let Some(operation) = words.next() else {
+        bail!("missing MI operation");
+    };
+    Ok(Command {
+        token,
+        operation,
+        args: words.collect(),
+    })
+}
+
+/// Split on whitespace, keeping `"..."` C strings whole and unescaped.
+fn split_args(s: &str) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut chars = s.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let mut word = String::new();
+        if c == '"' {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some('n') => word.push('\n'),
+                        Some('t') => word.push('\t'),
+                        Some(c) => word.push(c),
+                        None => bail!("unterminated string"),
+                    },
+                    Some(c) => word.push(c),
+                    None => bail!("unterminated string"),
+                }
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+        }
+        words.push(word);
+    }
+    Ok(words)
+}
+
+/// How a command finished, when it did not fail.
+enum Outcome {
+    /// `^done` with these results.
+    Done(Vec<(&'static str, Value)>),
+    /// `^running` was already printed, along with the stop that followed.
+    Ran,
+    /// `^exit`; the session ends.
+    Exit,
+}
+
+/// Read MI commands from stdin until `-gdb-exit` or end of input.
+pub(crate) fn run(session: &mut Session) -> Result<()> {
+    println!("(gdb) ");
+    for line in std::io::stdin().lock().lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            println!("(gdb) ");
+            continue;
+        }
+        let (token, outcome) = match parse_command(line) {
+            Ok(command) => (command.token, execute(session, &command)),
+            Err(e) => (None, Err(e)),
+        };
+        let token = token.map(|t| t.to_string()).unwrap_or_default();
+        match outcome {
+            Ok(Outcome::Done(results)) if results.is_empty() => println!("{token}^done"),
+            Ok(Outcome::Done(results)) => println!("{token}^done,{}", Results(&results)),
+            Ok(Outcome::Ran) => {}
+            Ok(Outcome::Exit) => {
+                println!("{token}^exit");
+                return Ok(());
+            }
+            Err(e) => println!("{token}^error,msg={}", Value::from(e.to_string())),
+        }
+        println!("(gdb) ");
+    }
+    Ok(())
+}
+
+fn execute(session: &mut Session, command: &Command) -> Result<Outcome> {
+    let operation = command.operation.as_str();
+    let args: Vec<&str> = command.args.iter().map(String::as_str).collect();
+    let token = command.token;
+    let process = session.process();
+    let results = match operation {
+        "gdb-exit" => return Ok(Outcome::Exit),
+        "exec-run" => {
+            process.relaunch()?;
+            return run_process(session, token, "", |p| {
+                p.resume()?;
+                p.wait_on_signal()
+            });
+        }
+        "exec-continue" => {
+            return run_process(session, token, "", |p| {
+                p.resume()?;
+                p.wait_on_signal()
+            });
+        }
+        "exec-step" => {
+            return run_process(session, token, "end-stepping-range", |p| p.step_line());
+        }
+        "exec-next-instruction" => {
+            return run_process(session, token, "end-stepping-range", |p| p.step_over());
+        }
+        "exec-step-instruction" => {
+            return run_process(session, token, "end-stepping-range", |p| p.single_step());
+        }
+        "exec-finish" => {
+            return run_process(session, token, "function-finished", |p| p.step_out());
+        }
+        "exec-until" => {
+            let [loc] = args[..] else {
+                bail!("usage: -exec-until LOCATION");
+            };
+            let addr = code_location(process, loc)?;
+            return run_process(session, token, "location-reached", |p| p.run_until(addr));
+        }
+        "break-insert" => break_insert(process, &args)?,
+        "break-delete" | "break-enable" | "break-disable" => {
+            for id in args {
+                let id = id.parse()?;
+                match operation {
+                    "break-delete" => process.delete_breakpoint(id)?,
+                    "break-enable" => process.enable_breakpoint(id)?,
+                    _ => process.disable_breakpoint(id)?,
+                }
+            }
+            vec![]
+        }
+        "break-condition" => {
+            let [id, ref cond @ ..] = args[..] else {
+                bail!("usage: -break-condition NUMBER [EXPR]");
+            };
+            process.set_breakpoint_condition(id.parse()?, &cond.join(" "))?;
+            vec![]
+        }
+        "break-after" => {
+            let [id, count] = args[..] else {
+                bail!("usage: -break-after NUMBER COUNT");
+            };
+            process.set_ignore_count(id.parse()?, count.parse()?)?;
+            vec![]
+        }
+        "break-list" => {
+            let body = process
+                .breakpoints()
+                .map(|bp| ("bkpt", breakpoint_tuple(process, bp.id())))
+                .collect();
+            vec![(
+                "BreakpointTable",
+                Value::Tuple(vec![("body", Value::ResultList(body))]),
+            )]
+        }
+        "stack-list-frames" => {
+            let frames = process
+                .backtrace()?
+                .iter()
+                .enumerate()
+                .map(|(level, frame)| {
+                    let mut tuple = vec![("level", Value::from(level.to_string()))];
+                    tuple.extend(location(process, frame.pc));
+                    ("frame", Value::Tuple(tuple))
+                })
+                .collect();
+            vec![("stack", Value::ResultList(frames))]
+        }
+        "stack-list-locals" => {
+            let [print_values] = args[..] else {
+                bail!("usage: -stack-list-locals PRINT-VALUES");
+            };
+            let with_values = !matches!(print_values, "0" | "--no-values");
+            let locals = process
+                .locals()?
+                .into_iter()
+                .map(|(name, value)| {
+                    let mut tuple = vec![("name", Value::from(name))];
+                    if with_values {
+                        tuple.push(("value", value.to_string().into()));
+                    }
+                    Value::Tuple(tuple)
+                })
+                .collect();
+            vec![("locals", Value::List(locals))]
+        }
+        "data-evaluate-expression" => {
+            let [name] = args[..] else {
+                bail!("usage: -data-evaluate-expression EXPR");
+            };
+            vec![("value", process.read_variable(name)?.to_string().into())]
+        }
+        "data-list-register-names" => {
+            let names = process
+                .get_registers()?
+                .general_purpose()
+                .into_iter()
+                .map(|(name, _)| Value::from(name))
+                .collect();
+            vec![("register-names", Value::List(names))]
+        }
+        "data-list-register-values" => {
+            // Values are always shown in hex, whatever format is asked for.
+            let values = process
+                .get_registers()?
+                .general_purpose()
+                .into_iter()
+                .enumerate()
+                .map(|(number, (_, value))| {
+                    Value::Tuple(vec![
+                        ("number", number.to_string().into()),
+                        ("value", format!("{value:#x}").into()),
+                    ])
+                })
+                .collect();
+            vec![("register-values", Value::List(values))]
+        }
+        "data-read-memory-bytes" => {
+            let [addr, count] = args[..] else {
+                bail!("usage: -data-read-memory-bytes ADDRESS COUNT");
+            };
+            let addr = resolve_location(process, addr)?;
+            let bytes = process.read_memory(addr, count.parse()?)?;
+            let contents: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+            vec![(
+                "memory",
+                Value::List(vec![Value::Tuple(vec![
+                    ("begin", format!("{addr:#x}").into()),
+                    ("offset", "0x0".into()),
+                    ("end", format!("{:#x}", addr + bytes.len() as u64).into()),
+                    ("contents", contents.into()),
+                ])]),
+            )]
+        }
+        "thread-info" => {
+            let threads = process
+                .threads()
+                .iter()
+                .map(|thread| {
+                    let state = match thread.state {
+                        ThreadState::Running => "running",
+                        ThreadState::Stopped => "stopped",
+                    };
+                    Value::Tuple(vec![
+                        ("id", thread.tid.to_string().into()),
+                        ("target-id", format!("Thread {}", thread.tid).into()),
+                        ("state", state.into()),
+                    ])
+                })
+                .collect();
+            vec![
+                ("threads", Value::List(threads)),
+                (
+                    "current-thread-id",
+                    process.current_thread().to_string().into(),
+                ),
+            ]
+        }
+        _ => bail!("Undefined MI command: {operation}"),
+    };
+    Ok(Outcome::Done(results))
+}
+
+/// `-break-insert [-t] [-c cond] [-i count] location`.
+fn break_insert(process: &mut ProcessHandle, args: &[&str]) -> Result<Vec<(&'static str, Value)>> {
+    let mut temporary = false;
+    let mut condition = None;
+    let mut ignore = None;
+    let mut location = None;
+    let mut args = args.iter().copied();
+    while let Some(arg) = args.next() {
+        match arg {
+            "-t" => temporary = true,
+            "-c" => condition = args.next(),
+            "-i" => ignore = args.next(),
+            "--" => location = args.next(),
+            _ if location.is_none() && !arg.starts_with('-') => location = Some(arg),
+            _ => bail!("unsupported -break-insert argument: {arg}"),
+        }
+    }
+    let Some(location) = location else {
+        bail!("-break-insert: missing location");
+    };
+    let id = set_breakpoint_at(process, location, temporary)?;
+    if let Some(cond) = condition {
+        process.set_breakpoint_condition(id, cond)?;
+    }
+    if let Some(count) = ignore {
+        process.set_ignore_count(id, count.parse()?)?;
+    }
+    Ok(vec![("bkpt", breakpoint_tuple(process, id))])
+}
+
+/// The `bkpt={...}` description of breakpoint `id`.
+fn breakpoint_tuple(process: &ProcessHandle, id: u32) -> Value {
+    let Some(bp) = process.breakpoints().find(|bp| bp.id() == id) else {
+        return Value::Tuple(vec![("number", id.to_string().into())]);
+    };
+    let mut tuple = vec![
+        ("number", id.to_string().into()),
+        ("type", "breakpoint".into()),
+        (
+            "disp",
+            if bp.is_temporary() { "del" } else { "keep" }.into(),
+        ),
+        ("enabled", if bp.is_enabled() { "y" } else { "n" }.into()),
+    ];
+    tuple.extend(location(process, bp.addr()));
+    if let Some(cond) = bp.condition() {
+        tuple.push(("cond", cond.into()));
+    }
+    if bp.ignore_count() > 0 {
+        tuple.push(("ignore", bp.ignore_count().to_string().into()));
+    }
+    tuple.push(("times", bp.hit_count().to_string().into()));
+    Value::Tuple(tuple)
+}
+
+/// `addr`, `func`, `file`, `fullname` and `line` results for `pc`, as far
+/// as they are known.
+fn location(process: &ProcessHandle, pc: u64) -> Vec<(&'static str, Value)> {
+    let mut results = vec![("addr", format!("{pc:#018x}").into())];
+    if let Some(sym) = process.symbol_at(pc) {
+        results.push(("func", sym.name.as_str().into()));
+    }
+    if let Some((path, line)) = process.addr_to_line(pc) {
+        let file = path.file_name().unwrap_or(path.as_os_str());
+        results.push(("file", file.to_string_lossy().into_owned().into()));
+        results.push(("fullname", path.display().to_string().into()));
+        results.push(("line", line.to_string().into()));
+    }
+    results
+}
+
+/// Run the process with `run` and report the outcome as `*stopped`.
+///
+/// `step_reason` names a stop that no breakpoint, watchpoint, or signal
+/// explains, e.g. the end of a step; empty when there is none to report.
+fn run_process(
+    session: &mut Session,
+    token: Option<u64>,
+    step_reason: &str,
+    run: impl FnOnce(&mut ProcessHandle) -> libfdb::FdbResult<ProcessState>,
+) -> Result<Outcome> {
+    let process = session.process();
+    if !process.is_alive() {
+        bail!("The program is not being run.");
+    }
+    println!(
+        "{}^running",
+        token.map(|t| t.to_string()).unwrap_or_default()
+    );
+    println!("*running,thread-id=\"all\"");
+    let _armed = interrupt::arm(process);
+    let state = run(process);
+    for sig in process.take_signal_notices() {
+        println!(
+            "&{}",
+            Value::from(format!("received signal {sig:?} (not stopping)\n"))
+        );
+    }
+    for (id, e) in process.take_condition_errors() {
+        println!(
+            "&{}",
+            Value::from(format!("error in condition of breakpoint {id}: {e}\n"))
+        );
+    }
+    match state {
+        Ok(state) => println!(
+            "*stopped,{}",
+            Results(&stopped(process, state, step_reason))
+        ),
+        Err(e) => println!("&{}", Value::from(format!("{e}\n"))),
+    }
+    let Session {
+        inferiors, current, ..
+    } = session;
+    if let Err(e) = adopt_fork_children(inferiors, current) {
+        println!("&{}", Value::from(format!("{e}\n")));
+    }
+    Ok(Outcome::Ran)
+}
+
+/// Results of the `*stopped` record for `state`.
+fn stopped(
+    process: &ProcessHandle,
+    state: ProcessState,
+    step_reason: &str,
+) -> Vec<(&'static str, Value)> {
+    let event = match state {
+        ProcessState::Exited(0) => return vec![("reason", "exited-normally".into())],
+        ProcessState::Exited(code) => {
+            return vec![
+                ("reason", "exited".into()),
+                ("exit-code", format!("{code:02o}").into()),
+            ];
+        }
+        ProcessState::Terminated(sig) => {
+            return vec![
+                ("reason", "exited-signalled".into()),
+                ("signal-name", sig.as_str().into()),
+            ];
+        }
+        _ => match process.last_stop() {
+            Some(event) => event,
+            None => return vec![],
+        },
+    };
+
+    let mut results: Vec<(&'static str, Value)> = match event.cause {
+        StopCause::Breakpoint(id) => vec![
+            ("reason", "breakpoint-hit".into()),
+            ("disp", "keep".into()),
+            ("bkptno", id.to_string().into()),
+        ],
+        StopCause::TemporaryBreakpoint(id) => vec![
+            ("reason", "breakpoint-hit".into()),
+            ("disp", "del".into()),
+            ("bkptno", id.to_string().into()),
+        ],
+        StopCause::Watchpoint { id, old, new } => {
+            let wp = process.watchpoints().find(|wp| wp.id() == id);
+            let show = |raw: u64| match wp.and_then(|wp| wp.typed_value(raw)) {
+                Some(value) => value.to_string(),
+                None => format!("{raw:#x}"),
+            };
+            let mut wpt = vec![("number", id.to_string().into())];
+            if let Some(name) = wp.and_then(|wp| wp.variable()) {
+                wpt.push(("exp", name.into()));
+            }
+            let reason = match wp.map(|wp| wp.kind()) {
+                Some(WatchKind::Read) => "read-watchpoint-trigger",
+                Some(WatchKind::ReadWrite) => "access-watchpoint-trigger",
+                _ => "watchpoint-trigger",
+            };
+            vec![
+                ("reason", reason.into()),
+                ("wpt", Value::Tuple(wpt)),
+                (
+                    "value",
+                    Value::Tuple(vec![("old", show(old).into()), ("new", show(new).into())]),
+                ),
+            ]
+        }
+        StopCause::Syscall(syscall) => {
+            let reason = match syscall {
+                libfdb::syscalls::SyscallEvent::Entry { .. } => "syscall-entry",
+                libfdb::syscalls::SyscallEvent::Exit { .. } => "syscall-return",
+            };
+            vec![
+                ("reason", reason.into()),
+                ("syscall-number", syscall.number().to_string().into()),
+                (
+                    "syscall-name",
+                    libfdb::syscalls::display_name(syscall.number()).into(),
+                ),
+            ]
+        }
+        StopCause::Fork { child } => vec![
+            ("reason", "fork".into()),
+            ("newpid", child.to_string().into()),
+        ],
+        StopCause::Exec => {
+            let mut results = vec![("reason", "exec".into())];
+            if let Ok(path) = process.executable() {
+                results.push(("new-exec", path.display().to_string().into()));
+            }
+            results
+        }
+        StopCause::Exiting(ExitStatus::Code(code)) => vec![
+            ("reason", "exiting".into()),
+            ("exit-code", format!("{code:02o}").into()),
+        ],
+        StopCause::Exiting(ExitStatus::Signal(sig)) => vec![
+            ("reason", "exiting".into()),
+            ("signal-name", sig.as_str().into()),
+        ],
+        StopCause::Signal if event.signal == Signal::SIGTRAP => match step_reason {
+            "" => vec![],
+            reason => vec![("reason", reason.into())],
+        },
+        StopCause::Signal => vec![
+            ("reason", "signal-received".into()),
+            ("signal-name", event.signal.as_str().into()),
+        ],
+        // Reported as GDB reports the SIGINT it stops the program with.
+        StopCause::Interrupted => vec![
+            ("reason", "signal-received".into()),
+            ("signal-name", "SIGINT".into()),
+            ("signal-meaning", "Interrupt".into()),
+        ],
+    };
+    if let Some(id) = event.catchpoint {
+        results.push(("bkptno", id.to_string().into()));
+    }
+    results.push(("frame", Value::Tuple(location(process, event.pc))));
+    results.push(("thread-id", event.tid.to_string().into()));
+    results.push(("stopped-threads", "all".into()));
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_parse_tokens_and_c_strings() {
+        assert_eq!(
+            parse_command(r#"12-break-insert -c "x == \"a b\"" main"#).unwrap(),
+            Command {
+                token: Some(12),
+                operation: "break-insert".to_string(),
+                args: vec!["-c".into(), "x == \"a b\"".into(), "main".into()],
+            }
+        );
+        assert_eq!(parse_command("-gdb-exit").unwrap().token, None);
+        assert!(parse_command("break main").is_err());
+    }
+
+    #[test]
+    fn values_format_as_mi_records() {
+        let value = Value::Tuple(vec![
+            ("number", "1".into()),
+            ("file", "a\"b\n".into()),
+            (
+                "frames",
+                Value::ResultList(vec![("frame", Value::Tuple(vec![("level", "0".into())]))]),
+            ),
+            ("names", Value::List(vec!["rax".into(), "rbx".into()])),
+        ]);
+        assert_eq!(
+            value.to_string(),
+            r#"{number="1",file="a\"b\n",frames=[frame={level="0"}],names=["rax","rbx"]}"#
+        );
+    }
+}
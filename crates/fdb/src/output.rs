@@ -0,0 +1,345 @@
+//! Reporting of stops, registers and backtraces, either as text for people
+//! or as one JSON object per line for editors and scripts.
+
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use libfdb::process::ProcessHandle;
+use libfdb::registers::Registers;
+use libfdb::unwind::Frame;
+use libfdb::{ExitStatus, ProcessState, StopCause, StopEvent};
+use nix::sys::signal::Signal;
+use serde_json::json;
+
+use crate::{STOP_CONTEXT, format_syscall, print_registers, print_source};
+
+/// Output style chosen with `--format`.
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum Format {
+    #[default]
+    Text,
+    Json,
+}
+
+/// Setting of `set color`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ColorMode {
+    /// Color when stdout is a terminal and `NO_COLOR` is unset or empty.
+    #[default]
+    Auto,
+    On,
+    Off,
+}
+
+/// Whether `paint` adds escape codes; off until `set_color` says otherwise,
+/// so MI output stays plain.
+static COLOR: AtomicBool = AtomicBool::new(false);
+
+/// Turn colored text output on or off as `mode` says.
+pub fn set_color(mode: ColorMode) {
+    let on = match mode {
+        ColorMode::On => true,
+        ColorMode::Off => false,
+        ColorMode::Auto => {
+            io::stdout().is_terminal()
+                && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+        }
+    };
+    COLOR.store(on, Ordering::Relaxed);
+}
+
+/// What a piece of highlighted text is.
+#[derive(Debug, Clone, Copy)]
+pub enum Style {
+    /// The instruction at the pc in a disassembly listing.
+    CurrentInstruction,
+    Register,
+    /// A signal that stopped or killed the program.
+    Signal,
+}
+
+/// `text` wrapped in the ANSI escapes for `style` when color is on.
+pub fn paint(text: &str, style: Style) -> String {
+    if !COLOR.load(Ordering::Relaxed) {
+        return text.to_string();
+    }
+    let code = match style {
+        Style::CurrentInstruction => "1;32",
+        Style::Register => "36",
+        Style::Signal => "1;31",
+    };
+    format!("\x1b[{code}m{text}\x1b[0m")
+}
+
+/// Presents what the debugger observed about the process.
+pub trait Reporter {
+    /// Describe a stop, exit or termination the process just went through.
+    fn state(&self, process: &ProcessHandle, state: ProcessState) -> Result<()>;
+    /// Dump the general-purpose registers.
+    fn registers(&self, regs: &Registers);
+    /// Show the call stack, innermost frame first.
+    fn backtrace(&self, out: &mut dyn Write, frames: &[Frame]) -> io::Result<()>;
+}
+
+/// The reporter for `format`.
+pub fn reporter(format: Format) -> &'static dyn Reporter {
+    match format {
+        Format::Text => &TextReporter,
+        Format::Json => &JsonReporter,
+    }
+}
+
+/// Human-readable output with source context on stops.
+pub struct TextReporter;
+
+impl Reporter for TextReporter {
+    fn state(&self, process: &ProcessHandle, state: ProcessState) -> Result<()> {
+        match state {
+            ProcessState::Stopped(_) => {
+                let event = process.last_stop().context("no stop was recorded")?;
+                let place = stop_place(event);
+                if let Some(id) = event.catchpoint {
+                    print!("catchpoint {id}: ");
+                }
+                match event.cause {
+                    StopCause::Breakpoint(id) => println!("hit breakpoint {id} at {place}"),
+                    StopCause::TemporaryBreakpoint(id) => {
+                        println!("temporary breakpoint {id} hit at {place}")
+                    }
+                    StopCause::Watchpoint { id, old, new } => {
+                        let wp = process.watchpoints().find(|wp| wp.id() == id);
+                        let typed = wp.and_then(|wp| {
+                            Some((wp.variable()?, wp.typed_value(old)?, wp.typed_value(new)?))
+                        });
+                        match typed {
+                            Some((name, old, new)) => {
+                                println!("watchpoint {id}: {name} old={old} new={new} at {place}")
+                            }
+                            None => {
+                                println!("watchpoint {id}: old={old:#x} new={new:#x} at {place}")
+                            }
+                        }
+                    }
+                    StopCause::Syscall(event) => println!("{}", format_syscall(process, &event)),
+                    StopCause::Fork { child } => println!("process forked child {child}"),
+                    StopCause::Exec => match process.executable() {
+                        Ok(path) => println!(
+                            "process {} is executing new program: {}",
+                            process.pid(),
+                            path.display()
+                        ),
+                        Err(_) => println!("process {} is executing a new program", process.pid()),
+                    },
+                    StopCause::Exiting(ExitStatus::Code(code)) => {
+                        println!(
+                            "process {} is exiting with code {code} at {place}",
+                            process.pid()
+                        )
+                    }
+                    StopCause::Exiting(ExitStatus::Signal(sig)) => {
+                        println!(
+                            "process {} is being killed by {} at {place}",
+                            process.pid(),
+                            paint(sig.as_str(), Style::Signal)
+                        )
+                    }
+                    StopCause::Signal if event.signal == Signal::SIGTRAP => {
+                        println!("stopped at {place}")
+                    }
+                    StopCause::Interrupted => println!("interrupted at {place}"),
+                    StopCause::Signal => {
+                        let signal = paint(event.signal.as_str(), Style::Signal);
+                        match &event.siginfo {
+                            Some(info) => {
+                                println!("stopped by signal {signal} ({info}) at {place}")
+                            }
+                            None => println!("stopped by signal {signal} at {place}"),
+                        }
+                    }
+                }
+                if let Some((file, line)) = &event.location {
+                    print_source(file, *line, STOP_CONTEXT);
+                }
+            }
+            ProcessState::Exited(code) => {
+                println!("process exited with code {code}");
+            }
+            ProcessState::Terminated(sig) => {
+                println!(
+                    "process terminated by signal: {}",
+                    paint(sig.as_str(), Style::Signal)
+                );
+            }
+            other => {
+                println!("status: {:?}", other);
+            }
+        }
+        Ok(())
+    }
+
+    fn registers(&self, regs: &Registers) {
+        print_registers(&regs.general_purpose());
+    }
+
+    fn backtrace(&self, out: &mut dyn Write, frames: &[Frame]) -> io::Result<()> {
+        for (i, frame) in frames.iter().enumerate() {
+            writeln!(out, "{}", format_frame(i, frame))?;
+            let Some(locals) = &frame.locals else {
+                continue;
+            };
+            if locals.is_empty() {
+                writeln!(out, "        No locals.")?;
+            }
+            for (name, value) in locals {
+                writeln!(out, "        {name} = {value}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// One line of a backtrace, e.g. `#1  0x401176 in middle(x=1) at
+/// /src/nested.c:11`. Frames without line information show where in their
+/// function they are instead, as `func+offset`.
+pub fn format_frame(level: usize, frame: &Frame) -> String {
+    let function = frame.function.as_deref().unwrap_or("??");
+    let place = match &frame.location {
+        Some((file, line)) => {
+            let args: Vec<String> = frame
+                .arguments
+                .iter()
+                .map(|(name, value)| format!("{name}={value}"))
+                .collect();
+            format!(
+                "{function}({}) at {}:{line}",
+                args.join(", "),
+                file.display()
+            )
+        }
+        None if frame.function.is_some() && frame.offset != 0 => {
+            format!("{function}+{}", frame.offset)
+        }
+        None => function.to_string(),
+    };
+    format!("#{level:<2} {:#018x} in {place}", frame.pc)
+}
+
+/// Where a stop happened: the pc, then its function and source line when
+/// known, e.g. `0x401136 in tick at /src/loop.c:5`.
+fn stop_place(event: &StopEvent) -> String {
+    let mut place = format!("{:#x}", event.pc);
+    if let Some(function) = &event.function {
+        place.push_str(&format!(" in {function}"));
+    }
+    if let Some((file, line)) = &event.location {
+        place.push_str(&format!(" at {}:{line}", file.display()));
+    }
+    place
+}
+
+/// One JSON object per line, tagged by an `event` field.
+pub struct JsonReporter;
+
+impl Reporter for JsonReporter {
+    fn state(&self, process: &ProcessHandle, state: ProcessState) -> Result<()> {
+        let line = match state {
+            ProcessState::Stopped(_) => {
+                let event = process.last_stop().context("no stop was recorded")?;
+                json!({ "event": "stop", "stop": event })
+            }
+            ProcessState::Exited(code) => json!({ "event": "exited", "code": code }),
+            ProcessState::Terminated(sig) => {
+                json!({ "event": "terminated", "signal": sig.as_str() })
+            }
+            other => json!({ "event": "status", "state": format!("{other:?}") }),
+        };
+        println!("{line}");
+        Ok(())
+    }
+
+    fn registers(&self, regs: &Registers) {
+        println!("{}", json!({ "event": "registers", "registers": regs }));
+    }
+
+    fn backtrace(&self, out: &mut dyn Write, frames: &[Frame]) -> io::Result<()> {
+        writeln!(out, "{}", json!({ "event": "backtrace", "frames": frames }))
+    }
+}
+
+/// Writes to stdout a screenful at a time, waiting for Enter between pages.
+/// Answering `q` drops the rest of the output.
+pub struct Pager {
+    /// Lines per page, counting the prompt; `None` never pauses.
+    height: Option<usize>,
+    /// Lines written since the last pause.
+    written: usize,
+    quit: bool,
+}
+
+impl Pager {
+    /// A pager pausing every `height` lines, or never for `None`.
+    pub fn new(height: Option<usize>) -> Self {
+        Pager {
+            // A page must have room for a line besides the prompt.
+            height: height.filter(|&height| height > 1),
+            written: 0,
+            quit: false,
+        }
+    }
+
+    /// Ask whether to go on; a read error or end of input counts as `q`.
+    fn pause(&mut self, stdout: &mut impl Write) -> io::Result<()> {
+        write!(stdout, "--Type <RET> for more, q to quit--")?;
+        stdout.flush()?;
+        let mut answer = String::new();
+        let read = io::stdin().lock().read_line(&mut answer);
+        self.quit = !matches!(read, Ok(n) if n > 0) || answer.trim() == "q";
+        self.written = 0;
+        Ok(())
+    }
+}
+
+impl Write for Pager {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut stdout = io::stdout().lock();
+        for line in buf.split_inclusive(|&b| b == b'\n') {
+            if self.quit {
+                break;
+            }
+            stdout.write_all(line)?;
+            if line.ends_with(b"\n") {
+                self.written += 1;
+                if self.height.is_some_and(|height| self.written + 1 >= height) {
+                    self.pause(&mut stdout)?;
+                }
+            }
+        }
+        // Output dropped after `q` still counts as written, so callers
+        // finish quietly instead of failing.
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stdout().flush()
+    }
+}
+
+/// Rows of the terminal stdout is on, when both it and stdin are
+/// terminals; with either redirected there is nobody to page for.
+pub fn terminal_height() -> Option<usize> {
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return None;
+    }
+    let mut size = nix::libc::winsize {
+        ws_row: 0,
+        ws_col: 0,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    // SAFETY: TIOCGWINSZ only fills in the `winsize` it is pointed at.
+    let ret =
+        unsafe { nix::libc::ioctl(nix::libc::STDOUT_FILENO, nix::libc::TIOCGWINSZ, &mut size) };
+    (ret == 0 && size.ws_row > 0).then_some(size.ws_row as usize)
+}
@@ -0,0 +1,320 @@
+//! Options changed with `set` and shown with `show`, kept in one registry
+//! so each is parsed and printed the same way everywhere.
+
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow, bail};
+use libfdb::disasm::AsmSyntax;
+use libfdb::fork::FollowForkMode;
+use libfdb::process::ProcessHandle;
+
+use crate::Session;
+use crate::output::{self, ColorMode};
+
+/// Session options changed with `set`.
+#[derive(Debug)]
+pub struct Settings {
+    /// Print the set RFLAGS bits after each `stepi`.
+    pub flags_on_step: bool,
+    /// What a sourced script does when one of its commands fails.
+    pub source_on_error: SourceOnError,
+    /// How long `continue` and `run` wait before interrupting the program;
+    /// `None` waits for as long as it takes.
+    pub wait_timeout: Option<Duration>,
+    /// Pause long listings after each screenful at the prompt.
+    pub pagination: bool,
+    /// Lines per page; `None` takes the terminal's height.
+    pub height: Option<usize>,
+    /// Whether text output is colored.
+    pub color: ColorMode,
+    /// List the instructions of the next line at every stop.
+    pub disassemble_next_line: bool,
+    /// Show each instruction's encoding in disassembly listings.
+    pub disassembly_bytes: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            flags_on_step: false,
+            source_on_error: SourceOnError::default(),
+            wait_timeout: None,
+            pagination: true,
+            height: None,
+            color: ColorMode::default(),
+            disassemble_next_line: false,
+            disassembly_bytes: true,
+        }
+    }
+}
+
+/// Setting of `set source-on-error`.
+#[derive(Debug, Default, Clone, Copy)]
+pub enum SourceOnError {
+    /// Abandon the rest of the script.
+    #[default]
+    Stop,
+    /// Report the error and run the next command.
+    Continue,
+}
+
+/// A named option of `set` and `show`.
+struct SettingSpec {
+    /// Name as typed, possibly several words such as `backtrace limit`.
+    name: &'static str,
+    /// Values accepted, as shown in help and errors.
+    values: &'static str,
+    description: &'static str,
+    /// Parse a value and apply it.
+    set: fn(&mut Session, &str) -> Result<()>,
+    /// The current value, in the form `set` accepts.
+    show: fn(&Session) -> String,
+}
+
+/// Every setting, ordered by name.
+const SETTINGS: &[SettingSpec] = &[
+    SettingSpec {
+        name: "backtrace limit",
+        values: "<n>|unlimited",
+        description: "most frames a backtrace walks; 0 means unlimited",
+        set: |session, value| {
+            let limit = match value {
+                "unlimited" => 0,
+                n => n
+                    .parse()
+                    .with_context(|| format!("invalid number of frames: {n}"))?,
+            };
+            for process in &mut session.inferiors {
+                process.set_backtrace_limit(limit);
+            }
+            Ok(())
+        },
+        show: |session| match current(session).backtrace_limit() {
+            usize::MAX => "unlimited".to_string(),
+            limit => limit.to_string(),
+        },
+    },
+    SettingSpec {
+        name: "color",
+        values: "on|off|auto",
+        description: "color stops, registers and disassembly; auto on a terminal",
+        set: |session, value| {
+            let mode = match value {
+                "on" => ColorMode::On,
+                "off" => ColorMode::Off,
+                "auto" => ColorMode::Auto,
+                _ => bail!("color must be on, off or auto"),
+            };
+            session.settings.color = mode;
+            output::set_color(mode);
+            Ok(())
+        },
+        show: |session| match session.settings.color {
+            ColorMode::On => "on".to_string(),
+            ColorMode::Off => "off".to_string(),
+            ColorMode::Auto => "auto".to_string(),
+        },
+    },
+    SettingSpec {
+        name: "disassemble-next-line",
+        values: "on|off",
+        description: "list the next line's instructions at every stop",
+        set: |session, value| {
+            session.settings.disassemble_next_line = parse_on_off("disassemble-next-line", value)?;
+            Ok(())
+        },
+        show: |session| on_off(session.settings.disassemble_next_line),
+    },
+    SettingSpec {
+        name: "disassembly-bytes",
+        values: "on|off",
+        description: "show instruction bytes in disassembly",
+        set: |session, value| {
+            session.settings.disassembly_bytes = parse_on_off("disassembly-bytes", value)?;
+            Ok(())
+        },
+        show: |session| on_off(session.settings.disassembly_bytes),
+    },
+    SettingSpec {
+        name: "disassembly-flavor",
+        values: "intel|att",
+        description: "assembly syntax of disassemble",
+        set: |session, value| {
+            let syntax = match value {
+                "intel" => AsmSyntax::Intel,
+                "att" => AsmSyntax::Att,
+                _ => bail!("disassembly-flavor must be intel or att"),
+            };
+            for process in &mut session.inferiors {
+                process.set_asm_syntax(syntax);
+            }
+            Ok(())
+        },
+        show: |session| match current(session).asm_syntax() {
+            AsmSyntax::Intel => "intel".to_string(),
+            AsmSyntax::Att => "att".to_string(),
+        },
+    },
+    SettingSpec {
+        name: "flags-on-step",
+        values: "on|off",
+        description: "print the set RFLAGS bits after stepi",
+        set: |session, value| {
+            session.settings.flags_on_step = parse_on_off("flags-on-step", value)?;
+            Ok(())
+        },
+        show: |session| on_off(session.settings.flags_on_step),
+    },
+    SettingSpec {
+        name: "follow-fork-mode",
+        values: "parent|child|both",
+        description: "which side of a fork to debug",
+        set: |session, value| {
+            let mode = match value {
+                "parent" => FollowForkMode::Parent,
+                "child" => FollowForkMode::Child,
+                "both" => FollowForkMode::Both,
+                _ => bail!("follow-fork-mode must be parent, child or both"),
+            };
+            for process in &mut session.inferiors {
+                process.set_follow_fork(mode);
+            }
+            Ok(())
+        },
+        show: |session| match current(session).follow_fork() {
+            FollowForkMode::Parent => "parent".to_string(),
+            FollowForkMode::Child => "child".to_string(),
+            FollowForkMode::Both => "both".to_string(),
+        },
+    },
+    SettingSpec {
+        name: "height",
+        values: "<n>|auto",
+        description: "lines per page; 0 never pauses, auto asks the terminal",
+        set: |session, value| {
+            session.settings.height = match value {
+                "auto" => None,
+                n => Some(
+                    n.parse()
+                        .with_context(|| format!("invalid number of lines: {n}"))?,
+                ),
+            };
+            Ok(())
+        },
+        show: |session| match session.settings.height {
+            Some(height) => height.to_string(),
+            None => "auto".to_string(),
+        },
+    },
+    SettingSpec {
+        name: "pagination",
+        values: "on|off",
+        description: "pause long listings after each page at the prompt",
+        set: |session, value| {
+            session.settings.pagination = parse_on_off("pagination", value)?;
+            Ok(())
+        },
+        show: |session| on_off(session.settings.pagination),
+    },
+    SettingSpec {
+        name: "source-on-error",
+        values: "stop|continue",
+        description: "whether a failing command ends a sourced script",
+        set: |session, value| {
+            session.settings.source_on_error = match value {
+                "stop" => SourceOnError::Stop,
+                "continue" => SourceOnError::Continue,
+                _ => bail!("source-on-error must be stop or continue"),
+            };
+            Ok(())
+        },
+        show: |session| match session.settings.source_on_error {
+            SourceOnError::Stop => "stop".to_string(),
+            SourceOnError::Continue => "continue".to_string(),
+        },
+    },
+    SettingSpec {
+        name: "wait-timeout",
+        values: "<secs>|off",
+        description: "interrupt a program still running after continue",
+        set: |session, value| {
+            session.settings.wait_timeout = match value {
+                "off" => None,
+                secs => {
+                    let secs: f64 = secs
+                        .parse()
+                        .ok()
+                        .filter(|secs: &f64| secs.is_finite() && *secs > 0.0)
+                        .with_context(|| format!("invalid number of seconds: {secs}"))?;
+                    Some(Duration::from_secs_f64(secs))
+                }
+            };
+            Ok(())
+        },
+        show: |session| match session.settings.wait_timeout {
+            Some(timeout) => timeout.as_secs_f64().to_string(),
+            None => "off".to_string(),
+        },
+    },
+];
+
+/// `set <name> <value>`, with `words` being everything after `set`.
+pub fn set(session: &mut Session, words: &[&str]) -> Result<()> {
+    let (spec, rest) = find(words)?;
+    let [value] = rest else {
+        bail!("usage: set {} {}", spec.name, spec.values);
+    };
+    (spec.set)(session, value)
+}
+
+/// `show [name]`: one setting, or all of them with what they do.
+pub fn show(session: &Session, words: &[&str]) -> Result<()> {
+    if words.is_empty() {
+        for spec in SETTINGS {
+            let value = (spec.show)(session);
+            println!("{:<21} {value:<10} {}", spec.name, spec.description);
+        }
+        return Ok(());
+    }
+    match find(words)? {
+        (spec, []) => println!("{} = {}", spec.name, (spec.show)(session)),
+        (spec, _) => bail!("usage: show {}", spec.name),
+    }
+    Ok(())
+}
+
+/// The setting `words` starts with, and the words after its name.
+fn find<'a, 'w>(words: &'a [&'w str]) -> Result<(&'static SettingSpec, &'a [&'w str])> {
+    SETTINGS
+        .iter()
+        .find_map(|spec| {
+            let name: Vec<&str> = spec.name.split(' ').collect();
+            words
+                .starts_with(&name)
+                .then(|| (spec, &words[name.len()..]))
+        })
+        .ok_or_else(|| {
+            let names: Vec<&str> = SETTINGS.iter().map(|spec| spec.name).collect();
+            anyhow!(
+                "unknown setting: {}; valid settings are {}",
+                words.first().unwrap_or(&""),
+                names.join(", ")
+            )
+        })
+}
+
+fn current(session: &Session) -> &ProcessHandle {
+    &session.inferiors[session.current]
+}
+
+fn parse_on_off(name: &str, value: &str) -> Result<bool> {
+    match value {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => bail!("{name} must be on or off"),
+    }
+}
+
+fn on_off(value: bool) -> String {
+    if value { "on" } else { "off" }.to_string()
+}
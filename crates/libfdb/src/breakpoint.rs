@@ -0,0 +1,621 @@
+//! Software breakpoints implemented by patching `int3` into the inferior.
+
+use std::fmt;
+use std::str::FromStr;
+
+use nix::sys::ptrace;
+use nix::unistd::Pid;
+
+use crate::errors::{FdbError, FdbResult};
+use crate::expr::Expr;
+use crate::memory;
+use crate::process::ProcessHandle;
+use crate::threads;
+
+/// Opcode of the single-byte `int3` trap instruction.
+pub const INT3: u8 = 0xCC;
+
+/// `si_code` the kernel reports for a SIGTRAP raised by `int3`.
+const SI_KERNEL: i32 = 0x80;
+
+/// A software breakpoint installed at a fixed address.
+#[derive(Debug, Clone)]
+pub struct Breakpoint {
+    id: u32,
+    addr: u64,
+    saved_byte: u8,
+    enabled: bool,
+    /// Set by the debugger itself (e.g. for `next`) and hidden from users.
+    internal: bool,
+    /// Deleted the first time it stops the process.
+    temporary: bool,
+    /// Function the breakpoint was set on, to find it again after a rerun.
+    function: Option<String>,
+    /// Expression that must be non-zero for a hit to stop, as typed and
+    /// parsed.
+    condition: Option<(String, Expr)>,
+    /// Number of upcoming hits that continue instead of stopping.
+    ignore_count: u32,
+    /// Hits that passed the condition, ignored ones included.
+    hit_count: u32,
+}
+
+impl Breakpoint {
+    /// Identifier handed back to the user when the breakpoint was created.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Address of the patched instruction.
+    pub fn addr(&self) -> u64 {
+        self.addr
+    }
+
+    /// Whether `int3` is currently written at `addr`.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Whether the debugger set this breakpoint for its own use.
+    pub(crate) fn is_internal(&self) -> bool {
+        self.internal
+    }
+
+    /// Whether the breakpoint deletes itself on its first stop.
+    pub fn is_temporary(&self) -> bool {
+        self.temporary
+    }
+
+    /// Function named when the breakpoint was set, if it was set that way.
+    pub fn function(&self) -> Option<&str> {
+        self.function.as_deref()
+    }
+
+    /// Condition a hit must satisfy to stop, if any.
+    pub fn condition(&self) -> Option<&str> {
+        self.condition.as_ref().map(|(src, _)| src.as_str())
+    }
+
+    /// Hits still to be skipped before the breakpoint stops again.
+    pub fn ignore_count(&self) -> u32 {
+        self.ignore_count
+    }
+
+    /// Times the breakpoint has been reached with its condition true.
+    pub fn hit_count(&self) -> u32 {
+        self.hit_count
+    }
+
+    /// Original instruction byte replaced by `int3`.
+    pub fn saved_byte(&self) -> u8 {
+        self.saved_byte
+    }
+
+    /// Insert `int3`, saving the byte it replaces. No-op when already enabled,
+    /// so the saved byte is never clobbered with `0xCC`.
+    pub(crate) fn enable(&mut self, pid: Pid) -> FdbResult<()> {
+        if self.enabled {
+            return Ok(());
+        }
+        let mut saved = [0u8];
+        memory::peek(pid, self.addr, &mut saved).1?;
+        memory::poke(pid, self.addr, &[INT3])?;
+        self.saved_byte = saved[0];
+        self.enabled = true;
+        Ok(())
+    }
+
+    /// Restore the saved byte. No-op when already disabled.
+    pub(crate) fn disable(&mut self, pid: Pid) -> FdbResult<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+        memory::poke(pid, self.addr, &[self.saved_byte])?;
+        self.enabled = false;
+        Ok(())
+    }
+
+    /// Mark as disabled without touching memory, for when the patched
+    /// image is already gone (e.g. replaced by exec).
+    pub(crate) fn forget_insertion(&mut self) {
+        self.enabled = false;
+    }
+}
+
+/// Where a saved breakpoint goes, in a form that survives a rebuild or a
+/// different load address where possible.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BreakpointLocation {
+    /// After the prologue of a function, written `name`.
+    Function(String),
+    /// First statement of a source line, written `file:line`.
+    Line { file: String, line: u32 },
+    /// Offset into a function, written `*name+offset`.
+    Symbol { name: String, offset: u64 },
+    /// Runtime address, written `*0xaddr`.
+    Address(u64),
+}
+
+impl fmt::Display for BreakpointLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BreakpointLocation::Function(name) => write!(f, "{name}"),
+            BreakpointLocation::Line { file, line } => write!(f, "{file}:{line}"),
+            BreakpointLocation::Symbol { name, offset: 0 } => write!(f, "*{name}"),
+            BreakpointLocation::Symbol { name, offset } => write!(f, "*{name}+{offset:#x}"),
+            BreakpointLocation::Address(addr) => write!(f, "*{addr:#x}"),
+        }
+    }
+}
+
+impl FromStr for BreakpointLocation {
+    type Err = FdbError;
+
+    fn from_str(s: &str) -> FdbResult<Self> {
+        let invalid = || FdbError::InvalidBreakpointSpec(s.to_string());
+        if let Some(loc) = s.strip_prefix('*') {
+            if let Some(hex) = loc.strip_prefix("0x") {
+                let addr = u64::from_str_radix(hex, 16).map_err(|_| invalid())?;
+                return Ok(BreakpointLocation::Address(addr));
+            }
+            let (name, offset) = match loc.split_once('+') {
+                Some((name, offset)) => {
+                    let offset = offset.strip_prefix("0x").ok_or_else(invalid)?;
+                    (
+                        name,
+                        u64::from_str_radix(offset, 16).map_err(|_| invalid())?,
+                    )
+                }
+                None => (loc, 0),
+            };
+            return Ok(BreakpointLocation::Symbol {
+                name: name.to_string(),
+                offset,
+            });
+        }
+        if let Some((file, line)) = s.rsplit_once(':')
+            && let Ok(line) = line.parse()
+        {
+            return Ok(BreakpointLocation::Line {
+                file: file.to_string(),
+                line,
+            });
+        }
+        if s.is_empty() {
+            return Err(invalid());
+        }
+        Ok(BreakpointLocation::Function(s.to_string()))
+    }
+}
+
+/// A user breakpoint as saved between sessions, one per line:
+/// `break|tbreak <location> [disabled] [ignore <n>] [if <condition>]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BreakpointSpec {
+    pub location: BreakpointLocation,
+    pub temporary: bool,
+    pub enabled: bool,
+    pub condition: Option<String>,
+    pub ignore_count: u32,
+}
+
+impl fmt::Display for BreakpointSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = if self.temporary { "tbreak" } else { "break" };
+        write!(f, "{kind} {}", self.location)?;
+        if !self.enabled {
+            write!(f, " disabled")?;
+        }
+        if self.ignore_count > 0 {
+            write!(f, " ignore {}", self.ignore_count)?;
+        }
+        if let Some(cond) = &self.condition {
+            write!(f, " if {cond}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for BreakpointSpec {
+    type Err = FdbError;
+
+    fn from_str(s: &str) -> FdbResult<Self> {
+        let invalid = || FdbError::InvalidBreakpointSpec(s.to_string());
+        let (head, condition) = match s.split_once(" if ") {
+            Some((head, cond)) => (head, Some(cond.trim().to_string())),
+            None => (s, None),
+        };
+        let mut words = head.split_whitespace();
+        let temporary = match words.next() {
+            Some("break") => false,
+            Some("tbreak") => true,
+            _ => return Err(invalid()),
+        };
+        let location = words.next().ok_or_else(invalid)?.parse()?;
+        let mut spec = BreakpointSpec {
+            location,
+            temporary,
+            enabled: true,
+            condition,
+            ignore_count: 0,
+        };
+        while let Some(word) = words.next() {
+            match word {
+                "disabled" => spec.enabled = false,
+                "ignore" => {
+                    let count = words.next().ok_or_else(invalid)?;
+                    spec.ignore_count = count.parse().map_err(|_| invalid())?;
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(spec)
+    }
+}
+
+impl ProcessHandle {
+    /// Install a software breakpoint at `addr` and return its id.
+    ///
+    /// Setting a breakpoint where one already exists returns the existing id.
+    pub fn set_breakpoint(&mut self, addr: u64) -> FdbResult<u32> {
+        self.ensure_alive()?;
+        if let Some(bp) = self
+            .breakpoints
+            .values_mut()
+            .find(|bp| !bp.internal && bp.addr == addr)
+        {
+            bp.enable(self.tid)?;
+            return Ok(bp.id);
+        }
+
+        let id = self.next_breakpoint_id;
+        let mut bp = Breakpoint {
+            id,
+            addr,
+            saved_byte: 0,
+            enabled: false,
+            internal: false,
+            temporary: false,
+            function: None,
+            condition: None,
+            ignore_count: 0,
+            hit_count: 0,
+        };
+        bp.enable(self.tid)?;
+        self.breakpoints.insert(id, bp);
+        self.next_breakpoint_id += 1;
+        Ok(id)
+    }
+
+    /// Install a breakpoint at `addr` that is deleted the first time it
+    /// stops the process, and return its id.
+    ///
+    /// If a user breakpoint already exists at `addr`, its id is returned and
+    /// it stays permanent.
+    pub fn set_temp_breakpoint(&mut self, addr: u64) -> FdbResult<u32> {
+        let existing = self.breakpoints().any(|bp| bp.addr == addr);
+        let id = self.set_breakpoint(addr)?;
+        if !existing {
+            self.breakpoint_mut(id)?.temporary = true;
+        }
+        Ok(id)
+    }
+
+    /// Break on entry to function `name` and return the breakpoint id.
+    ///
+    /// With line information the breakpoint goes after the prologue, on the
+    /// function's first source statement; otherwise on its first instruction.
+    pub fn set_breakpoint_at_function(&mut self, name: &str) -> FdbResult<u32> {
+        let addr = self
+            .function_breakpoint_addr(name)
+            .ok_or_else(|| FdbError::UnknownSymbol(name.to_string()))?;
+        let id = self.set_breakpoint(addr)?;
+        self.breakpoint_mut(id)?.function = Some(name.to_string());
+        Ok(id)
+    }
+
+    /// Where a breakpoint on function `name` goes.
+    pub fn function_breakpoint_addr(&self, name: &str) -> Option<u64> {
+        let sym = self.symbols.lookup(name)?;
+        let (addr, size) = (sym.address, sym.size);
+        Some(self.skip_prologue(addr, size).unwrap_or(addr))
+    }
+
+    /// Recreate `bp` from an earlier run of the program at `addr`, keeping
+    /// its id and whether it is enabled but starting a fresh hit count. If
+    /// it cannot be inserted it is kept disabled.
+    pub(crate) fn restore_breakpoint(&mut self, bp: &Breakpoint, addr: u64) {
+        let mut copy = Breakpoint {
+            addr,
+            saved_byte: 0,
+            enabled: false,
+            hit_count: 0,
+            ..bp.clone()
+        };
+        if bp.enabled
+            && let Err(e) = copy.enable(self.tid)
+        {
+            log::warn!("breakpoint {}: cannot insert at {addr:#x}: {e}", bp.id);
+        }
+        self.breakpoints.insert(bp.id, copy);
+    }
+
+    /// Plant a hidden breakpoint at `addr` for the debugger's own use.
+    ///
+    /// Returns `None` when an enabled breakpoint already traps there.
+    pub(crate) fn set_internal_breakpoint(&mut self, addr: u64) -> FdbResult<Option<u32>> {
+        if self.enabled_breakpoint_at(addr).is_some() {
+            return Ok(None);
+        }
+
+        let id = self.next_internal_id;
+        let mut bp = Breakpoint {
+            id,
+            addr,
+            saved_byte: 0,
+            enabled: false,
+            internal: true,
+            temporary: false,
+            function: None,
+            condition: None,
+            ignore_count: 0,
+            hit_count: 0,
+        };
+        bp.enable(self.tid)?;
+        self.breakpoints.insert(id, bp);
+        self.next_internal_id -= 1;
+        Ok(Some(id))
+    }
+
+    /// Re-insert `int3` for breakpoint `id`.
+    pub fn enable_breakpoint(&mut self, id: u32) -> FdbResult<()> {
+        self.ensure_alive()?;
+        let tid = self.tid;
+        self.breakpoint_mut(id)?.enable(tid)
+    }
+
+    /// Only stop at breakpoint `id` when `cond` evaluates to non-zero. An
+    /// empty condition makes the breakpoint unconditional again.
+    pub fn set_breakpoint_condition(&mut self, id: u32, cond: &str) -> FdbResult<()> {
+        let cond = cond.trim();
+        let condition = if cond.is_empty() {
+            None
+        } else {
+            Some((cond.to_string(), Expr::parse(cond)?))
+        };
+        self.breakpoint_mut(id)?.condition = condition;
+        Ok(())
+    }
+
+    /// Let the next `count` hits of breakpoint `id` pass without stopping.
+    /// Hits where the condition is false do not count.
+    pub fn set_ignore_count(&mut self, id: u32, count: u32) -> FdbResult<()> {
+        self.breakpoint_mut(id)?.ignore_count = count;
+        Ok(())
+    }
+
+    /// Whether a hit on breakpoint `id` should stop the process.
+    ///
+    /// A condition that fails to evaluate stops, with the error queued for
+    /// [`ProcessHandle::take_condition_errors`]. Only hits that pass the
+    /// condition use up the ignore count.
+    pub(crate) fn breakpoint_should_stop(&mut self, id: u32) -> bool {
+        if let Some((_, expr)) = self
+            .breakpoints
+            .get(&id)
+            .and_then(|bp| bp.condition.as_ref())
+        {
+            match self.evaluate(expr) {
+                Ok(0) => return false,
+                Ok(_) => {}
+                Err(e) => {
+                    self.condition_errors.push((id, e));
+                    return true;
+                }
+            }
+        }
+        let Some(bp) = self.breakpoints.get_mut(&id) else {
+            return true;
+        };
+        bp.hit_count += 1;
+        if bp.ignore_count > 0 {
+            bp.ignore_count -= 1;
+            return false;
+        }
+        true
+    }
+
+    /// Delete breakpoint `id` if it is temporary, returning whether it was.
+    ///
+    /// Called once a hit has stopped the process, with RIP already moved
+    /// back, so the next resume runs the original instruction.
+    pub(crate) fn retire_temp_breakpoint(&mut self, id: u32) -> FdbResult<bool> {
+        if !self.breakpoints.get(&id).is_some_and(|bp| bp.temporary) {
+            return Ok(false);
+        }
+        self.delete_breakpoint(id)?;
+        Ok(true)
+    }
+
+    /// Errors from evaluating breakpoint conditions since the last call.
+    pub fn take_condition_errors(&mut self) -> Vec<(u32, FdbError)> {
+        std::mem::take(&mut self.condition_errors)
+    }
+
+    /// How many times breakpoint `id` has been hit in this run, or `None`
+    /// if there is no such breakpoint.
+    pub fn breakpoint_hit_count(&self, id: u32) -> Option<u32> {
+        self.breakpoints()
+            .find(|bp| bp.id == id)
+            .map(|bp| bp.hit_count)
+    }
+
+    /// Restore the original byte for breakpoint `id`, keeping it in the table.
+    pub fn disable_breakpoint(&mut self, id: u32) -> FdbResult<()> {
+        let tid = self.tid;
+        self.breakpoint_mut(id)?.disable(tid)
+    }
+
+    /// Remove breakpoint `id`, restoring the original byte if it is enabled.
+    ///
+    /// The entry is dropped from the table even when the restore fails, e.g.
+    /// because the inferior has already exited.
+    pub fn delete_breakpoint(&mut self, id: u32) -> FdbResult<()> {
+        let mut bp = self
+            .breakpoints
+            .remove(&id)
+            .ok_or(FdbError::UnknownBreakpoint(id))?;
+        if let Err(e) = bp.disable(self.tid) {
+            log::debug!(
+                "breakpoint {id}: could not restore byte at {:#x}: {e}",
+                bp.addr
+            );
+        }
+        Ok(())
+    }
+
+    /// After a SIGTRAP raised by `int3`, move RIP back onto the breakpoint
+    /// address and report which breakpoint fired.
+    pub(crate) fn rewind_breakpoint_trap(&mut self) -> FdbResult<Option<u32>> {
+        // int3 reports SI_KERNEL; single-steps and other traps do not.
+        let info = ptrace::getsiginfo(self.tid)?;
+        if info.si_code != SI_KERNEL {
+            return Ok(None);
+        }
+
+        let addr = self.pc()?.wrapping_sub(1);
+        let Some(bp) = self
+            .breakpoints
+            .values()
+            .find(|bp| bp.enabled && bp.addr == addr)
+        else {
+            return Ok(None);
+        };
+
+        let id = bp.id;
+        self.set_pc(addr)?;
+        Ok(Some(id))
+    }
+
+    /// If thread `tid` sits on an enabled breakpoint, execute the original
+    /// instruction with the breakpoint lifted so it does not trap again.
+    pub(crate) fn step_over_breakpoint(&mut self, tid: Pid) -> FdbResult<()> {
+        let rip = ptrace::getregs(tid)?.rip;
+        let Some(bp) = self
+            .breakpoints
+            .values_mut()
+            .find(|bp| bp.enabled && bp.addr == rip)
+        else {
+            return Ok(());
+        };
+
+        bp.disable(tid)?;
+        ptrace::step(tid, None)?;
+        threads::wait_thread(tid)?;
+        bp.enable(tid)
+    }
+
+    /// Id of the enabled breakpoint at `addr`, if any.
+    pub(crate) fn enabled_breakpoint_at(&self, addr: u64) -> Option<u32> {
+        self.breakpoints
+            .values()
+            .find(|bp| bp.enabled && bp.addr == addr)
+            .map(|bp| bp.id)
+    }
+
+    /// Describe every user breakpoint so it can be set again in another
+    /// session, preferring function names and source lines over addresses.
+    pub fn export_breakpoints(&self) -> Vec<BreakpointSpec> {
+        self.breakpoints()
+            .map(|bp| BreakpointSpec {
+                location: self.breakpoint_location(bp),
+                temporary: bp.temporary,
+                enabled: bp.enabled,
+                condition: bp.condition().map(str::to_string),
+                ignore_count: bp.ignore_count,
+            })
+            .collect()
+    }
+
+    fn breakpoint_location(&self, bp: &Breakpoint) -> BreakpointLocation {
+        if let Some(name) = &bp.function {
+            return BreakpointLocation::Function(name.clone());
+        }
+        if let Some((file, line)) = self.addr_to_line(bp.addr) {
+            let file = file.to_string_lossy().into_owned();
+            // Only lines whose first statement is this exact address.
+            if self.line_to_addr(&file, line) == Some(bp.addr) {
+                return BreakpointLocation::Line { file, line };
+            }
+        }
+        // Symbol offsets stay valid wherever a PIE is loaded.
+        match self.symbol_at(bp.addr) {
+            Some(sym) => BreakpointLocation::Symbol {
+                name: sym.name,
+                offset: bp.addr - sym.address,
+            },
+            None => BreakpointLocation::Address(bp.addr),
+        }
+    }
+
+    /// Set the breakpoints described by `specs`, returning the id or error
+    /// for each in order. A spec that fails does not stop the rest.
+    pub fn import_breakpoints(&mut self, specs: &[BreakpointSpec]) -> Vec<FdbResult<u32>> {
+        specs
+            .iter()
+            .map(|spec| self.import_breakpoint(spec))
+            .collect()
+    }
+
+    fn import_breakpoint(&mut self, spec: &BreakpointSpec) -> FdbResult<u32> {
+        let id = match &spec.location {
+            _ if spec.temporary => {
+                let addr = self.location_addr(&spec.location)?;
+                self.set_temp_breakpoint(addr)?
+            }
+            BreakpointLocation::Function(name) => self.set_breakpoint_at_function(name)?,
+            BreakpointLocation::Line { file, line } => self.set_breakpoint_at_line(file, *line)?,
+            location => self.set_breakpoint(self.location_addr(location)?)?,
+        };
+        if let Some(cond) = &spec.condition {
+            self.set_breakpoint_condition(id, cond)?;
+        }
+        self.set_ignore_count(id, spec.ignore_count)?;
+        if !spec.enabled {
+            self.disable_breakpoint(id)?;
+        }
+        Ok(id)
+    }
+
+    /// Runtime address of `location` in the current program.
+    fn location_addr(&self, location: &BreakpointLocation) -> FdbResult<u64> {
+        match location {
+            BreakpointLocation::Function(name) => self
+                .function_breakpoint_addr(name)
+                .ok_or_else(|| FdbError::UnknownSymbol(name.clone())),
+            BreakpointLocation::Line { file, line } => {
+                self.line_to_addr(file, *line)
+                    .ok_or_else(|| FdbError::NoCodeForLine {
+                        file: file.clone(),
+                        line: *line,
+                    })
+            }
+            BreakpointLocation::Symbol { name, offset } => self
+                .resolve_symbol(name)
+                .map(|addr| addr + offset)
+                .ok_or_else(|| FdbError::UnknownSymbol(name.clone())),
+            BreakpointLocation::Address(addr) => Ok(*addr),
+        }
+    }
+
+    /// All user breakpoints ordered by id.
+    pub fn breakpoints(&self) -> impl Iterator<Item = &Breakpoint> {
+        self.breakpoints.values().filter(|bp| !bp.internal)
+    }
+
+    fn breakpoint_mut(&mut self, id: u32) -> FdbResult<&mut Breakpoint> {
+        self.breakpoints
+            .get_mut(&id)
+            .ok_or(FdbError::UnknownBreakpoint(id))
+    }
+}
@@ -0,0 +1,106 @@
+//! Catchpoints: stops on process events rather than at code addresses.
+
+use std::fmt;
+
+use nix::sys::signal::Signal;
+
+use crate::errors::{FdbError, FdbResult};
+use crate::process::ProcessHandle;
+use crate::{StopCause, StopReason};
+
+/// Event a catchpoint stops on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CatchKind {
+    /// The process calls `execve`.
+    Exec,
+    /// The process creates a child with `fork` or `vfork`.
+    Fork,
+    /// The process receives this signal, whatever its policy says.
+    Signal(Signal),
+}
+
+impl fmt::Display for CatchKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CatchKind::Exec => write!(f, "exec"),
+            CatchKind::Fork => write!(f, "fork"),
+            CatchKind::Signal(sig) => write!(f, "signal {sig}"),
+        }
+    }
+}
+
+/// A numbered request to stop on an event.
+#[derive(Debug, Clone)]
+pub struct Catchpoint {
+    id: u32,
+    kind: CatchKind,
+    hit_count: u32,
+}
+
+impl Catchpoint {
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn kind(&self) -> CatchKind {
+        self.kind
+    }
+
+    /// Times the event has stopped the process.
+    pub fn hit_count(&self) -> u32 {
+        self.hit_count
+    }
+}
+
+impl ProcessHandle {
+    /// Stop whenever `kind` of event happens and return the catchpoint id.
+    ///
+    /// Catchpoints share the id space with breakpoints. Without one, exec
+    /// and fork run through and signals follow their policy.
+    pub fn add_catchpoint(&mut self, kind: CatchKind) -> u32 {
+        let id = self.next_breakpoint_id;
+        self.next_breakpoint_id += 1;
+        self.catchpoints.insert(
+            id,
+            Catchpoint {
+                id,
+                kind,
+                hit_count: 0,
+            },
+        );
+        id
+    }
+
+    pub fn remove_catchpoint(&mut self, id: u32) -> FdbResult<()> {
+        self.catchpoints
+            .remove(&id)
+            .map(drop)
+            .ok_or(FdbError::UnknownBreakpoint(id))
+    }
+
+    /// All catchpoints ordered by id.
+    pub fn catchpoints(&self) -> impl Iterator<Item = &Catchpoint> {
+        self.catchpoints.values()
+    }
+
+    /// Whether some catchpoint stops on `kind`.
+    pub(crate) fn catches(&self, kind: CatchKind) -> bool {
+        self.catchpoints.values().any(|cp| cp.kind == kind)
+    }
+
+    /// Count a hit on the catchpoint that `reason` reports, if any, and
+    /// return its id.
+    pub(crate) fn hit_catchpoint(&mut self, reason: StopReason) -> Option<u32> {
+        let kind = match reason.cause {
+            StopCause::Exec => CatchKind::Exec,
+            StopCause::Fork { .. } => CatchKind::Fork,
+            StopCause::Signal if reason.signal != Signal::SIGTRAP => {
+                CatchKind::Signal(reason.signal)
+            }
+            _ => return None,
+        };
+        let cp = self.catchpoints.values_mut().find(|cp| cp.kind == kind)?;
+        cp.hit_count += 1;
+        Some(cp.id)
+    }
+}
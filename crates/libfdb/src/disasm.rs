@@ -0,0 +1,189 @@
+//! x86-64 instruction decoding built on capstone.
+
+use capstone::arch::x86::X86OperandType;
+use capstone::arch::{self, ArchOperand, BuildsCapstone, BuildsCapstoneSyntax};
+use capstone::{Capstone, Insn, InsnGroupType};
+
+use crate::errors::{FdbError, FdbResult};
+use crate::process::ProcessHandle;
+
+/// Longest possible x86-64 instruction encoding.
+pub const MAX_INSN_LEN: usize = 15;
+
+/// Instructions decoded for a function whose symbol has no size.
+pub const FUNCTION_FALLBACK_COUNT: usize = 32;
+
+/// Assembly syntax of disassembly listings, chosen with
+/// `set disassembly-flavor`.
+///
+/// Intel is the default rather than GDB's AT&T: it is what listings have
+/// always used here, and what the prologue checks in unwinding match on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AsmSyntax {
+    /// `mov rbp, rsp`: destination first, no sigils.
+    #[default]
+    Intel,
+    /// `movq %rsp, %rbp`: source first, `%` registers and `$` immediates.
+    Att,
+}
+
+/// A single decoded instruction.
+#[derive(Debug, Clone)]
+pub struct Instruction {
+    pub address: u64,
+    /// Original encoding, with breakpoint patches undone.
+    pub bytes: Vec<u8>,
+    pub mnemonic: String,
+    pub operands: String,
+    /// Destination of a direct jump or call.
+    pub target: Option<u64>,
+    /// `target` as `function` or `function+offset`, when a symbol covers it.
+    pub target_symbol: Option<String>,
+}
+
+/// A capstone handle for `syntax`. Built for every decode, so a change of
+/// flavor applies to the next listing with no engine to reconfigure.
+fn engine(syntax: AsmSyntax) -> FdbResult<Capstone> {
+    let syntax = match syntax {
+        AsmSyntax::Intel => arch::x86::ArchSyntax::Intel,
+        AsmSyntax::Att => arch::x86::ArchSyntax::Att,
+    };
+    Ok(Capstone::new()
+        .x86()
+        .mode(arch::x86::ArchMode::Mode64)
+        .syntax(syntax)
+        .detail(true)
+        .build()?)
+}
+
+/// Decode up to `count` instructions of `code`, which starts at `addr`.
+fn decode_code(
+    code: &[u8],
+    addr: u64,
+    count: usize,
+    syntax: AsmSyntax,
+) -> FdbResult<Vec<Instruction>> {
+    let cs = engine(syntax)?;
+    let insns = cs.disasm_count(code, addr, count)?;
+    Ok(insns
+        .iter()
+        .map(|insn| Instruction {
+            address: insn.address(),
+            bytes: insn.bytes().to_vec(),
+            mnemonic: insn.mnemonic().unwrap_or_default().to_string(),
+            operands: insn.op_str().unwrap_or_default().to_string(),
+            target: branch_target(&cs, insn),
+            target_symbol: None,
+        })
+        .collect())
+}
+
+/// Immediate destination of `insn` if it is a jump or call.
+fn branch_target(cs: &Capstone, insn: &Insn) -> Option<u64> {
+    let detail = cs.insn_detail(insn).ok()?;
+    let branches = detail.groups().iter().any(|group| {
+        let group = u32::from(group.0);
+        group == InsnGroupType::CS_GRP_JUMP || group == InsnGroupType::CS_GRP_CALL
+    });
+    if !branches {
+        return None;
+    }
+    match detail.arch_detail().operands().first()? {
+        ArchOperand::X86Operand(op) => match op.op_type {
+            X86OperandType::Imm(target) => Some(target as u64),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+impl ProcessHandle {
+    /// Read `len` bytes of code at `addr` with any `int3` patches replaced by
+    /// the original bytes. Stops short at the end of mapped memory.
+    pub(crate) fn read_code(&self, addr: u64, len: usize) -> FdbResult<Vec<u8>> {
+        let mut code = match self.read_memory(addr, len) {
+            Ok(code) => code,
+            Err(FdbError::PartialRead { data, .. }) if !data.is_empty() => data,
+            Err(e) => return Err(e),
+        };
+        let end = addr + code.len() as u64;
+        for bp in self.breakpoints.values() {
+            if bp.is_enabled() && (addr..end).contains(&bp.addr()) {
+                code[(bp.addr() - addr) as usize] = bp.saved_byte();
+            }
+        }
+        Ok(code)
+    }
+
+    /// Decode up to `count` instructions starting at `addr`, in Intel
+    /// syntax whatever listings use.
+    pub(crate) fn decode(&self, addr: u64, count: usize) -> FdbResult<Vec<Instruction>> {
+        let code = self.read_code(addr, count * MAX_INSN_LEN)?;
+        decode_code(&code, addr, count, AsmSyntax::Intel)
+    }
+
+    /// Syntax `disassemble` and `disassemble_function` produce.
+    pub fn asm_syntax(&self) -> AsmSyntax {
+        self.asm_syntax
+    }
+
+    pub fn set_asm_syntax(&mut self, syntax: AsmSyntax) {
+        self.asm_syntax = syntax;
+    }
+
+    /// Decode up to `count` instructions starting at `addr`, naming the
+    /// targets of direct branches after the function they land in.
+    ///
+    /// Active breakpoints are shown as the instructions they replaced.
+    pub fn disassemble(&self, addr: u64, count: usize) -> FdbResult<Vec<Instruction>> {
+        let code = self.read_code(addr, count * MAX_INSN_LEN)?;
+        let mut insns = decode_code(&code, addr, count, self.asm_syntax)?;
+        self.name_targets(&mut insns);
+        Ok(insns)
+    }
+
+    /// Decode the whole body of function `name`, as given by its symbol
+    /// size. A symbol without a size yields [`FUNCTION_FALLBACK_COUNT`]
+    /// instructions from its start.
+    pub fn disassemble_function(&self, name: &str) -> FdbResult<Vec<Instruction>> {
+        let sym = self
+            .symbols
+            .lookup(name)
+            .ok_or_else(|| FdbError::UnknownSymbol(name.to_string()))?;
+        if sym.size == 0 {
+            return self.disassemble(sym.address, FUNCTION_FALLBACK_COUNT);
+        }
+        let code = self.read_code(sym.address, sym.size as usize)?;
+        // Every instruction is at least one byte long.
+        let mut insns = decode_code(&code, sym.address, code.len(), self.asm_syntax)?;
+        self.name_targets(&mut insns);
+        Ok(insns)
+    }
+
+    fn name_targets(&self, insns: &mut [Instruction]) {
+        for insn in insns {
+            insn.target_symbol = insn.target.and_then(|target| self.symbolize(target));
+        }
+    }
+
+    /// `addr` as `function` or `function+offset`, e.g. `printf@plt` or
+    /// `main+4`, when a symbol of the executable or a loaded library
+    /// covers it.
+    pub fn symbolize(&self, addr: u64) -> Option<String> {
+        let sym = self.symbol_at(addr)?;
+        Some(match addr - sym.address {
+            0 => sym.name,
+            offset => format!("{}+{offset}", sym.name),
+        })
+    }
+
+    /// Length of the instruction at `addr` if it is a `call`.
+    pub(crate) fn call_length_at(&self, addr: u64) -> FdbResult<Option<u64>> {
+        Ok(self
+            .decode(addr, 1)?
+            .into_iter()
+            .next()
+            .filter(|insn| insn.mnemonic == "call")
+            .map(|insn| insn.bytes.len() as u64))
+    }
+}
@@ -0,0 +1,233 @@
+//! Source line mapping from DWARF `.debug_line`.
+
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+
+use gimli::{EndianSlice, RunTimeEndian};
+use object::{Object, ObjectSection};
+
+use crate::errors::{FdbError, FdbResult};
+use crate::process::ProcessHandle;
+
+type Reader<'a> = EndianSlice<'a, RunTimeEndian>;
+
+/// One row of the line-number matrix, at a static (unrelocated) address.
+#[derive(Debug, Clone)]
+pub struct LineRow {
+    pub address: u64,
+    pub file: PathBuf,
+    pub line: u32,
+    /// Recommended breakpoint location for the line.
+    pub is_stmt: bool,
+    /// First address past a contiguous sequence; not itself a line.
+    pub end_sequence: bool,
+}
+
+/// Line rows of one compilation unit, sorted by address.
+#[derive(Debug, Default)]
+struct UnitLines {
+    rows: Vec<LineRow>,
+    /// Lowest row address and first address past the unit's code.
+    low: u64,
+    high: u64,
+}
+
+impl UnitLines {
+    fn new(mut rows: Vec<LineRow>) -> Self {
+        // Where one sequence ends and the next begins at the same address,
+        // order the end marker first so lookups land on the new sequence.
+        rows.sort_by_key(|row| (row.address, !row.end_sequence));
+        let low = rows.first().map_or(0, |row| row.address);
+        let high = rows.last().map_or(0, |row| row.address);
+        UnitLines { rows, low, high }
+    }
+
+    fn find(&self, addr: u64) -> Option<&LineRow> {
+        let idx = self.rows.partition_point(|row| row.address <= addr);
+        let row = self.rows.get(idx.checked_sub(1)?)?;
+        (!row.end_sequence).then_some(row)
+    }
+}
+
+/// Line rows of an executable, kept per compilation unit.
+#[derive(Debug, Default)]
+pub struct LineTable {
+    units: Vec<UnitLines>,
+}
+
+impl LineTable {
+    /// Parse the line programs of every compilation unit in `path`.
+    pub fn load(path: &Path) -> FdbResult<Self> {
+        let data = std::fs::read(path)?;
+        let object = object::File::parse(&*data)?;
+        let endian = if object.is_little_endian() {
+            RunTimeEndian::Little
+        } else {
+            RunTimeEndian::Big
+        };
+
+        let sections = gimli::DwarfSections::load(|id| -> Result<_, gimli::Error> {
+            Ok(object
+                .section_by_name(id.name())
+                .and_then(|section| section.uncompressed_data().ok())
+                .unwrap_or(Cow::Borrowed(&[])))
+        })?;
+        let dwarf = sections.borrow(|section| EndianSlice::new(section, endian));
+
+        let mut units = Vec::new();
+        let mut headers = dwarf.units();
+        while let Some(header) = headers.next()? {
+            let unit = dwarf.unit(header)?;
+            let Some(program) = unit.line_program.clone() else {
+                continue;
+            };
+            let mut rows = Vec::new();
+            let mut program_rows = program.rows();
+            while let Some((header, row)) = program_rows.next_row()? {
+                let file = row
+                    .file(header)
+                    .map(|entry| file_path(&dwarf, &unit, header, entry))
+                    .transpose()?
+                    .unwrap_or_default();
+                rows.push(LineRow {
+                    address: row.address(),
+                    file,
+                    line: row.line().map_or(0, |line| line.get() as u32),
+                    is_stmt: row.is_stmt(),
+                    end_sequence: row.end_sequence(),
+                });
+            }
+            if !rows.is_empty() {
+                units.push(UnitLines::new(rows));
+            }
+        }
+        Ok(LineTable { units })
+    }
+
+    /// Row covering static address `addr`, if it lies inside a sequence.
+    pub fn find(&self, addr: u64) -> Option<&LineRow> {
+        self.units
+            .iter()
+            .filter(|unit| (unit.low..=unit.high).contains(&addr))
+            .find_map(|unit| unit.find(addr))
+    }
+
+    /// Rows whose static address lies in `start..end`, taken from the unit
+    /// covering `start`.
+    pub fn rows_in(&self, start: u64, end: u64) -> &[LineRow] {
+        let Some(unit) = self.units.iter().find(|unit| unit.find(start).is_some()) else {
+            return &[];
+        };
+        let lo = unit.rows.partition_point(|row| row.address < start);
+        let hi = unit.rows.partition_point(|row| row.address < end);
+        &unit.rows[lo..hi]
+    }
+
+    /// Whether any row belongs to a file whose path ends with `file`.
+    pub fn has_file(&self, file: &Path) -> bool {
+        self.units
+            .iter()
+            .flat_map(|unit| &unit.rows)
+            .any(|row| row.file.ends_with(file))
+    }
+
+    /// Lowest static address of a statement for `line` in a file whose path
+    /// ends with `file`. Lines without code resolve to the next line that
+    /// has some.
+    pub fn line_to_addr(&self, file: &Path, line: u32) -> Option<u64> {
+        self.units
+            .iter()
+            .flat_map(|unit| &unit.rows)
+            .filter(|row| row.is_stmt && !row.end_sequence && row.line >= line)
+            .filter(|row| row.file.ends_with(file))
+            .min_by_key(|row| (row.line, row.address))
+            .map(|row| row.address)
+    }
+}
+
+fn file_path(
+    dwarf: &gimli::Dwarf<Reader<'_>>,
+    unit: &gimli::Unit<Reader<'_>>,
+    header: &gimli::LineProgramHeader<Reader<'_>>,
+    entry: &gimli::FileEntry<Reader<'_>>,
+) -> FdbResult<PathBuf> {
+    // Absolute components replace what came before, so this also handles
+    // DWARF 5 tables whose directories are already absolute.
+    let mut path = PathBuf::new();
+    if let Some(comp_dir) = unit.comp_dir {
+        path.push(&*comp_dir.to_string_lossy());
+    }
+    if let Some(dir) = entry.directory(header) {
+        path.push(&*dwarf.attr_string(unit, dir)?.to_string_lossy());
+    }
+    path.push(
+        &*dwarf
+            .attr_string(unit, entry.path_name())?
+            .to_string_lossy(),
+    );
+    Ok(path)
+}
+
+impl ProcessHandle {
+    /// Source file and line for runtime address `addr`.
+    pub fn addr_to_line(&self, addr: u64) -> Option<(PathBuf, u32)> {
+        let table = self.line_table()?;
+        let row = table.find(addr.wrapping_sub(self.load_bias()))?;
+        Some((row.file.clone(), row.line))
+    }
+
+    /// Runtime address of the first statement for `file:line`; `file` may be
+    /// a bare name or any trailing part of the compiled path.
+    pub fn line_to_addr(&self, file: &str, line: u32) -> Option<u64> {
+        let addr = self.line_table()?.line_to_addr(Path::new(file), line)?;
+        Some(addr.wrapping_add(self.load_bias()))
+    }
+
+    /// Break on the first statement of `file:line` and return the breakpoint
+    /// id. A line without code moves to the next one that has some; compare
+    /// [`Self::addr_to_line`] of the breakpoint address to detect this.
+    pub fn set_breakpoint_at_line(&mut self, file: &str, line: u32) -> FdbResult<u32> {
+        let Some(addr) = self.line_to_addr(file, line) else {
+            let known = self
+                .line_table()
+                .is_some_and(|table| table.has_file(Path::new(file)));
+            return Err(if known {
+                FdbError::NoCodeForLine {
+                    file: file.to_string(),
+                    line,
+                }
+            } else {
+                FdbError::UnknownSourceFile(file.to_string())
+            });
+        };
+        self.set_breakpoint(addr)
+    }
+
+    /// Line table row for runtime address `addr`.
+    pub(crate) fn line_row(&self, addr: u64) -> Option<&LineRow> {
+        self.line_table()?.find(addr.wrapping_sub(self.load_bias()))
+    }
+
+    /// Address of the second line-table statement in the function at runtime
+    /// address `addr`, which is where compilers end the frame setup.
+    pub(crate) fn skip_prologue(&self, addr: u64, size: u64) -> Option<u64> {
+        let bias = self.load_bias();
+        let start = addr.wrapping_sub(bias);
+        let rows = self.line_table()?.rows_in(start, start + size.max(1));
+        rows.first().filter(|row| row.address == start)?;
+        rows.iter()
+            .find(|row| row.address > start && row.is_stmt && !row.end_sequence)
+            .map(|row| row.address.wrapping_add(bias))
+    }
+
+    fn line_table(&self) -> Option<&LineTable> {
+        self.line_table
+            .get_or_init(|| {
+                let exe = format!("/proc/{}/exe", self.pid);
+                LineTable::load(Path::new(&exe))
+                    .inspect_err(|e| log::debug!("no line info for {exe}: {e}"))
+                    .ok()
+            })
+            .as_ref()
+    }
+}
@@ -17,9 +17,126 @@ pub enum FdbError {
     #[error("invalid process ID")]
     InvalidPid,
 
+    #[error("no breakpoint number {0}")]
+    UnknownBreakpoint(u32),
+
+    #[error("watchpoint address {addr:#x} is not aligned to {size} bytes")]
+    MisalignedWatchpoint { addr: u64, size: usize },
+
+    #[error("no free hardware debug register")]
+    NoFreeDebugRegister,
+
+    #[error("{0} is used by the debugger and must stop without being passed")]
+    ProtectedSignal(nix::sys::signal::Signal),
+
+    #[error("no thread with id {0}")]
+    UnknownThread(i32),
+
+    #[error("unknown register: {0}")]
+    UnknownRegister(String),
+
+    #[error("value does not fit in the {bits}-bit register {name}")]
+    RegisterValueTooWide { name: String, bits: u32 },
+
+    #[error("no function named {0}")]
+    UnknownSymbol(String),
+
+    #[error("no variable named {0} in the current scope")]
+    UnknownVariable(String),
+
+    #[error("{0} is not available at this point in the program")]
+    VariableUnavailable(String),
+
+    #[error("{0} is not stored in memory")]
+    VariableNotInMemory(String),
+
+    #[error("{name} is {size} bytes; a hardware watchpoint covers at most 8")]
+    WatchTooLarge { name: String, size: u64 },
+
+    #[error("backtrace limit of {0} frames reached")]
+    BacktraceLimit(usize),
+
+    #[error("frame at {pc:#x} with CFA {cfa:#x} repeats; the stack may be corrupt")]
+    RepeatedFrame { pc: u64, cfa: u64 },
+
+    #[error("the current frame has no caller to return to")]
+    NoCallerFrame,
+
+    #[error("no frame at level {0}")]
+    NoSuchFrame(usize),
+
+    #[error("invalid expression: {0}")]
+    InvalidExpression(String),
+
+    #[error("convenience variable ${0} is not set")]
+    UnsetConvenienceVariable(String),
+
+    #[error("invalid breakpoint spec: {0}")]
+    InvalidBreakpointSpec(String),
+
+    #[error("no source file {0} in the debug info")]
+    UnknownSourceFile(String),
+
+    #[error("no code at or after {file}:{line}")]
+    NoCodeForLine { file: String, line: u32 },
+
+    #[error("could only read {} of {requested} bytes at {addr:#x}", data.len())]
+    PartialRead {
+        addr: u64,
+        requested: usize,
+        /// The bytes that were readable before the fault.
+        data: Vec<u8>,
+    },
+
+    #[error("only {written} of {requested} bytes at {addr:#x} were readable and dumped")]
+    PartialDump {
+        addr: u64,
+        requested: usize,
+        written: usize,
+    },
+
+    #[error("memory at {0:#x} is not mapped writable")]
+    NotWritable(u64),
+
+    #[error("{len} bytes at {addr:#x} run past the end of the address space")]
+    AddressOverflow { addr: u64, len: usize },
+
+    #[error("cannot search for an empty pattern")]
+    EmptySearchPattern,
+
+    #[error("malformed /proc maps line: {0}")]
+    MalformedMaps(String),
+
+    #[error("disassembler error: {0}")]
+    Disassembler(#[from] capstone::Error),
+
+    #[error("object file error: {0}")]
+    Object(#[from] object::Error),
+
+    #[error("DWARF error: {0}")]
+    Dwarf(#[from] gimli::Error),
+
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
     #[error("system call failed: {0}")]
     SysCall(#[from] errno::Errno),
 
+    #[error("failed to launch program: {0}")]
+    LaunchFailed(String),
+
+    #[error("{0}: no such executable file")]
+    ProgramNotFound(String),
+
+    #[error("the program exited with code {0} and is not being run")]
+    ProcessExited(i32),
+
+    #[error("the program is not being run")]
+    NotRunning,
+
+    #[error("the program was attached to, not launched, so it cannot be run again")]
+    NotLaunched,
+
     #[error("C string error: {0}")]
     CStringError(#[from] NulError),
 }
@@ -0,0 +1,369 @@
+//! A small C-like expression language over the inferior's state.
+//!
+//! Expressions combine integer literals, registers (`$rax`, or `$pc`,
+//! `$sp` and `$fp`), convenience variables (any other `$name`), function
+//! names (their address) and 8-byte memory reads (`*addr`) with the usual C
+//! operators and precedence. Values are 64-bit signed integers; arithmetic
+//! wraps, and comparisons and logical operators yield 0 or 1.
+
+use std::fmt;
+
+use crate::errors::{FdbError, FdbResult};
+use crate::process::ProcessHandle;
+use crate::registers::register_by_name;
+
+/// A parsed expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Number(i64),
+    /// Register name without the `$`, aliases already resolved.
+    Register(String),
+    /// Convenience variable name without the `$`.
+    Convenience(String),
+    /// A function, standing for its runtime address.
+    Symbol(String),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+    BitNot,
+    /// Read the 8 bytes at the operand's address.
+    Deref,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Mul,
+    Div,
+    Rem,
+    Add,
+    Sub,
+    Shl,
+    Shr,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    BitAnd,
+    BitXor,
+    BitOr,
+    And,
+    Or,
+}
+
+impl BinaryOp {
+    /// Binding strength; higher binds tighter.
+    fn precedence(self) -> u8 {
+        use BinaryOp::*;
+        match self {
+            Or => 1,
+            And => 2,
+            BitOr => 3,
+            BitXor => 4,
+            BitAnd => 5,
+            Eq | Ne => 6,
+            Lt | Le | Gt | Ge => 7,
+            Shl | Shr => 8,
+            Add | Sub => 9,
+            Mul | Div | Rem => 10,
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        use BinaryOp::*;
+        match self {
+            Mul => "*",
+            Div => "/",
+            Rem => "%",
+            Add => "+",
+            Sub => "-",
+            Shl => "<<",
+            Shr => ">>",
+            Lt => "<",
+            Le => "<=",
+            Gt => ">",
+            Ge => ">=",
+            Eq => "==",
+            Ne => "!=",
+            BitAnd => "&",
+            BitXor => "^",
+            BitOr => "|",
+            And => "&&",
+            Or => "||",
+        }
+    }
+}
+
+/// Operators in the order the tokenizer tries them, longest first.
+const OPERATORS: &[&str] = &[
+    "<<", ">>", "<=", ">=", "==", "!=", "&&", "||", "*", "/", "%", "+", "-", "<", ">", "&", "^",
+    "|", "!", "~", "(", ")",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token<'a> {
+    Number(i64),
+    Register(&'a str),
+    Ident(&'a str),
+    Op(&'static str),
+}
+
+fn invalid(msg: impl Into<String>) -> FdbError {
+    FdbError::InvalidExpression(msg.into())
+}
+
+fn tokenize(src: &str) -> FdbResult<Vec<Token<'_>>> {
+    let mut tokens = Vec::new();
+    let mut rest = src.trim_start();
+    while let Some(c) = rest.chars().next() {
+        let word_len = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        let len = if c.is_ascii_digit() {
+            let word = &rest[..word_len];
+            let value = match word.strip_prefix("0x").or_else(|| word.strip_prefix("0X")) {
+                Some(hex) => u64::from_str_radix(hex, 16),
+                None => word.parse::<u64>(),
+            }
+            .map_err(|_| invalid(format!("bad number '{word}'")))?;
+            tokens.push(Token::Number(value as i64));
+            word_len
+        } else if c == '$' {
+            let name_len = rest[1..]
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len() - 1);
+            if name_len == 0 {
+                return Err(invalid(
+                    "'$' must be followed by a register or variable name",
+                ));
+            }
+            tokens.push(Token::Register(&rest[1..1 + name_len]));
+            1 + name_len
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            tokens.push(Token::Ident(&rest[..word_len]));
+            word_len
+        } else if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+            tokens.push(Token::Op(op));
+            op.len()
+        } else {
+            return Err(invalid(format!("unexpected character '{c}'")));
+        };
+        rest = rest[len..].trim_start();
+    }
+    Ok(tokens)
+}
+
+/// Precedence-climbing parser over a token list.
+struct Parser<'a> {
+    tokens: Vec<Token<'a>>,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&Token<'a>> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn binary_op(&self) -> Option<BinaryOp> {
+        use BinaryOp::*;
+        let Some(Token::Op(op)) = self.peek() else {
+            return None;
+        };
+        [
+            Mul, Div, Rem, Add, Sub, Shl, Shr, Lt, Le, Gt, Ge, Eq, Ne, BitAnd, BitXor, BitOr, And,
+            Or,
+        ]
+        .into_iter()
+        .find(|candidate| candidate.symbol() == *op)
+    }
+
+    fn expr(&mut self, min_precedence: u8) -> FdbResult<Expr> {
+        let mut lhs = self.unary()?;
+        while let Some(op) = self.binary_op() {
+            if op.precedence() < min_precedence {
+                break;
+            }
+            self.pos += 1;
+            let rhs = self.expr(op.precedence() + 1)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> FdbResult<Expr> {
+        let op = match self.peek() {
+            Some(Token::Op("-")) => UnaryOp::Neg,
+            Some(Token::Op("!")) => UnaryOp::Not,
+            Some(Token::Op("~")) => UnaryOp::BitNot,
+            Some(Token::Op("*")) => UnaryOp::Deref,
+            _ => return self.primary(),
+        };
+        self.pos += 1;
+        Ok(Expr::Unary(op, Box::new(self.unary()?)))
+    }
+
+    fn primary(&mut self) -> FdbResult<Expr> {
+        match self.next() {
+            Some(Token::Number(value)) => Ok(Expr::Number(value)),
+            Some(Token::Register(name)) => Ok(match register_alias(name) {
+                Some(reg) => Expr::Register(reg.to_string()),
+                None => Expr::Convenience(name.to_string()),
+            }),
+            Some(Token::Ident(name)) => Ok(Expr::Symbol(name.to_string())),
+            Some(Token::Op("(")) => {
+                let inner = self.expr(0)?;
+                match self.next() {
+                    Some(Token::Op(")")) => Ok(inner),
+                    _ => Err(invalid("missing ')'")),
+                }
+            }
+            Some(token) => Err(invalid(format!("unexpected {token}"))),
+            None => Err(invalid("unexpected end of expression")),
+        }
+    }
+}
+
+/// The register `$name` refers to, if it names one.
+fn register_alias(name: &str) -> Option<&str> {
+    let name = match name {
+        "pc" => "rip",
+        "sp" => "rsp",
+        "fp" => "rbp",
+        name => name,
+    };
+    register_by_name(name).map(|_| name)
+}
+
+impl fmt::Display for Token<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Number(value) => write!(f, "'{value}'"),
+            Token::Register(name) => write!(f, "'${name}'"),
+            Token::Ident(name) => write!(f, "'{name}'"),
+            Token::Op(op) => write!(f, "'{op}'"),
+        }
+    }
+}
+
+impl Expr {
+    pub fn parse(src: &str) -> FdbResult<Expr> {
+        let mut parser = Parser {
+            tokens: tokenize(src)?,
+            pos: 0,
+        };
+        let expr = parser.expr(0)?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(token) => Err(invalid(format!("unexpected {token}"))),
+        }
+    }
+}
+
+impl ProcessHandle {
+    /// Value of `expr` in the current thread.
+    pub fn evaluate(&self, expr: &Expr) -> FdbResult<i64> {
+        self.evaluate_in_frame(expr, 0)
+    }
+
+    /// Value of `expr` with registers read from frame `level` of the call
+    /// stack.
+    pub fn evaluate_in_frame(&self, expr: &Expr, level: usize) -> FdbResult<i64> {
+        let eval = |expr| self.evaluate_in_frame(expr, level);
+        Ok(match expr {
+            Expr::Number(value) => *value,
+            Expr::Register(name) => self.frame_registers(level)?.get(name)? as i64,
+            Expr::Convenience(name) => self
+                .convenience_var(name)
+                .ok_or_else(|| FdbError::UnsetConvenienceVariable(name.clone()))?,
+            Expr::Symbol(name) => {
+                self.resolve_symbol(name)
+                    .ok_or_else(|| FdbError::UnknownSymbol(name.clone()))? as i64
+            }
+            Expr::Unary(op, operand) => {
+                let value = eval(operand)?;
+                match op {
+                    UnaryOp::Neg => value.wrapping_neg(),
+                    UnaryOp::Not => (value == 0) as i64,
+                    UnaryOp::BitNot => !value,
+                    UnaryOp::Deref => {
+                        let bytes = self.read_memory(value as u64, 8)?;
+                        i64::from_le_bytes(bytes.try_into().expect("read 8 bytes"))
+                    }
+                }
+            }
+            // Only evaluate the right side when it decides the result.
+            Expr::Binary(BinaryOp::And, lhs, rhs) => (eval(lhs)? != 0 && eval(rhs)? != 0) as i64,
+            Expr::Binary(BinaryOp::Or, lhs, rhs) => (eval(lhs)? != 0 || eval(rhs)? != 0) as i64,
+            Expr::Binary(op, lhs, rhs) => {
+                let (a, b) = (eval(lhs)?, eval(rhs)?);
+                match op {
+                    BinaryOp::Mul => a.wrapping_mul(b),
+                    BinaryOp::Div | BinaryOp::Rem if b == 0 => {
+                        return Err(invalid("division by zero"));
+                    }
+                    BinaryOp::Div => a.wrapping_div(b),
+                    BinaryOp::Rem => a.wrapping_rem(b),
+                    BinaryOp::Add => a.wrapping_add(b),
+                    BinaryOp::Sub => a.wrapping_sub(b),
+                    BinaryOp::Shl => a.wrapping_shl(b as u32),
+                    BinaryOp::Shr => a.wrapping_shr(b as u32),
+                    BinaryOp::Lt => (a < b) as i64,
+                    BinaryOp::Le => (a <= b) as i64,
+                    BinaryOp::Gt => (a > b) as i64,
+                    BinaryOp::Ge => (a >= b) as i64,
+                    BinaryOp::Eq => (a == b) as i64,
+                    BinaryOp::Ne => (a != b) as i64,
+                    BinaryOp::BitAnd => a & b,
+                    BinaryOp::BitXor => a ^ b,
+                    BinaryOp::BitOr => a | b,
+                    BinaryOp::And | BinaryOp::Or => unreachable!("handled above"),
+                }
+            }
+        })
+    }
+
+    /// Parse and evaluate `src`.
+    pub fn evaluate_str(&self, src: &str) -> FdbResult<i64> {
+        self.evaluate(&Expr::parse(src)?)
+    }
+
+    /// Give convenience variable `$name` a value for later expressions.
+    ///
+    /// Register names are taken; the value lasts until the debugger exits,
+    /// across reruns of the program.
+    pub fn set_convenience_var(&mut self, name: &str, value: i64) -> FdbResult<()> {
+        let name = name.strip_prefix('$').unwrap_or(name);
+        let is_name = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !is_name || register_alias(name).is_some() {
+            return Err(invalid(format!("cannot assign to ${name}")));
+        }
+        self.convenience.insert(name.to_string(), value);
+        Ok(())
+    }
+
+    /// Value of convenience variable `$name`, if it has been set.
+    pub fn convenience_var(&self, name: &str) -> Option<i64> {
+        let name = name.strip_prefix('$').unwrap_or(name);
+        self.convenience.get(name).copied()
+    }
+
+    /// All convenience variables, ordered by name.
+    pub fn convenience_vars(&self) -> impl Iterator<Item = (&str, i64)> {
+        self.convenience
+            .iter()
+            .map(|(name, value)| (name.as_str(), *value))
+    }
+}
@@ -0,0 +1,152 @@
+//! Following the inferior across `fork`, `vfork` and `clone`.
+
+use nix::libc;
+use nix::sys::ptrace;
+use nix::unistd::Pid;
+
+use crate::catchpoint::CatchKind;
+use crate::errors::FdbResult;
+use crate::memory;
+use crate::process::ProcessHandle;
+use crate::threads;
+use crate::{ProcessState, StopCause, StopReason};
+
+/// Which side of a fork the debugger keeps tracing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FollowForkMode {
+    /// Keep debugging the parent; the child runs free.
+    #[default]
+    Parent,
+    /// Switch to the child. The parent is left for the caller to detach.
+    Child,
+    /// Keep both under the debugger.
+    Both,
+}
+
+impl ProcessHandle {
+    /// Choose what happens to new child processes.
+    ///
+    /// In [`FollowForkMode::Parent`] children are released, stopping the
+    /// parent only if a fork catchpoint is set.
+    /// Otherwise a fork stops the parent with [`StopCause::Fork`] and the
+    /// child's handle is available from [`ProcessHandle::take_fork_child`].
+    pub fn set_follow_fork(&mut self, mode: FollowForkMode) {
+        self.follow_fork = mode;
+    }
+
+    pub fn follow_fork(&self) -> FollowForkMode {
+        self.follow_fork
+    }
+
+    /// Hand over the child from the most recent [`StopCause::Fork`] stop.
+    pub fn take_fork_child(&mut self) -> Option<ProcessHandle> {
+        self.fork_children.pop()
+    }
+
+    /// Deal with a `PTRACE_EVENT_FORK`, `VFORK` or `CLONE` stop.
+    ///
+    /// Returns the stop to report, or `None` if the parent was resumed.
+    pub(crate) fn handle_fork_event(&mut self, event: i32) -> FdbResult<Option<ProcessState>> {
+        let child = Pid::from_raw(ptrace::getevent(self.tid)? as i32);
+        // The new tracee starts with a SIGSTOP that may not have arrived yet.
+        threads::wait_thread(child)?;
+
+        if is_thread(child) {
+            self.add_thread(child)?;
+        } else if self.follow_fork == FollowForkMode::Parent {
+            if event == libc::PTRACE_EVENT_VFORK {
+                // The child borrows our memory until it execs, so our int3s
+                // must be lifted until the vfork completes.
+                self.lift_breakpoints_for_vfork()?;
+            } else {
+                self.remove_breakpoints_from(child);
+            }
+            ptrace::detach(child, None)?;
+            log::info!("detached from child process {child}");
+            if self.catches(CatchKind::Fork) {
+                return Ok(Some(self.fork_stop(child)));
+            }
+        } else {
+            let handle = self.fork_handle(child)?;
+            self.fork_children.push(handle);
+            return Ok(Some(self.fork_stop(child)));
+        }
+        Ok(None)
+    }
+
+    fn fork_stop(&mut self, child: Pid) -> ProcessState {
+        self.state = ProcessState::Stopped(StopReason {
+            signal: nix::sys::signal::Signal::SIGTRAP,
+            cause: StopCause::Fork { child },
+        });
+        self.state
+    }
+
+    /// Re-insert breakpoints lifted for a vfork once the child lets go.
+    pub(crate) fn handle_vfork_done(&mut self) -> FdbResult<()> {
+        for id in std::mem::take(&mut self.vfork_lifted) {
+            if let Some(bp) = self.breakpoints.get_mut(&id) {
+                bp.enable(self.tid)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn lift_breakpoints_for_vfork(&mut self) -> FdbResult<()> {
+        for bp in self.breakpoints.values_mut() {
+            if bp.is_enabled() {
+                bp.disable(self.tid)?;
+                self.vfork_lifted.push(bp.id());
+            }
+        }
+        Ok(())
+    }
+
+    /// Undo our `int3` patches in a forked copy of the address space.
+    fn remove_breakpoints_from(&self, child: Pid) {
+        for bp in self.breakpoints.values().filter(|bp| bp.is_enabled()) {
+            if let Err(e) = memory::poke(child, bp.addr(), &[bp.saved_byte()]) {
+                log::debug!("restoring {:#x} in child {child}: {e}", bp.addr());
+            }
+        }
+    }
+
+    /// Build a handle for a forked child that inherits our settings and
+    /// user breakpoints. Watchpoints are not inherited: the kernel clears
+    /// debug registers on fork.
+    fn fork_handle(&self, child: Pid) -> FdbResult<ProcessHandle> {
+        let mut handle = ProcessHandle::new(child, self.attached)?;
+        for bp in self.breakpoints.values() {
+            if bp.is_internal() {
+                if bp.is_enabled() {
+                    memory::poke(child, bp.addr(), &[bp.saved_byte()])?;
+                }
+            } else {
+                handle.breakpoints.insert(bp.id(), bp.clone());
+            }
+        }
+        handle.next_breakpoint_id = self.next_breakpoint_id;
+        handle.signal_policies = self.signal_policies.clone();
+        handle.syscall_catch = self.syscall_catch.clone();
+        handle.catch_exit = self.catch_exit;
+        handle.follow_fork = self.follow_fork;
+        handle.backtrace_limit = self.backtrace_limit;
+        handle.asm_syntax = self.asm_syntax;
+        handle.catchpoints = self.catchpoints.clone();
+        handle.convenience = self.convenience.clone();
+        Ok(handle)
+    }
+}
+
+/// Whether `pid` is a thread of another process rather than a process.
+fn is_thread(pid: Pid) -> bool {
+    std::fs::read_to_string(format!("/proc/{pid}/status"))
+        .ok()
+        .and_then(|status| {
+            status
+                .lines()
+                .find_map(|line| line.strip_prefix("Tgid:"))
+                .and_then(|tgid| tgid.trim().parse::<i32>().ok())
+        })
+        .is_some_and(|tgid| tgid != pid.as_raw())
+}
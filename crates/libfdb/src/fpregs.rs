@@ -0,0 +1,343 @@
+//! Typed access to the x87, SSE and MXCSR state saved by `FXSAVE`.
+
+use nix::libc::user_fpregs_struct;
+use nix::sys::ptrace::{self, regset::NT_PRFPREG};
+
+use crate::errors::{FdbError, FdbResult};
+use crate::process::ProcessHandle;
+
+/// Number of XMM registers on x86-64.
+pub const XMM_COUNT: usize = 16;
+/// Number of x87 stack registers.
+pub const ST_COUNT: usize = 8;
+
+/// x87 control and status registers, with their width in bits.
+const CONTROL_REGISTERS: &[(&str, u32)] = &[
+    ("fctrl", 16),
+    ("fstat", 16),
+    ("ftag", 8),
+    ("fop", 11),
+    ("mxcsr", 32),
+];
+
+/// Snapshot of the floating-point and vector register file.
+#[derive(Clone, Copy)]
+pub struct FpRegisters {
+    regs: user_fpregs_struct,
+}
+
+impl FpRegisters {
+    /// XMM register `n` as a little-endian 128-bit value.
+    pub fn xmm(&self, n: usize) -> Option<u128> {
+        let words = self.regs.xmm_space.get(n * 4..n * 4 + 4)?;
+        Some(
+            words
+                .iter()
+                .rev()
+                .fold(0, |acc, &word| acc << 32 | u128::from(word)),
+        )
+    }
+
+    /// The 80-bit contents of `st(n)`, counted from the top of the stack.
+    pub fn st(&self, n: usize) -> Option<[u8; 10]> {
+        let words = self.regs.st_space.get(n * 4..n * 4 + 4)?;
+        let mut bytes = [0; 10];
+        for (chunk, word) in bytes.chunks_mut(4).zip(words) {
+            chunk.copy_from_slice(&word.to_le_bytes()[..chunk.len()]);
+        }
+        Some(bytes)
+    }
+
+    /// Whether `st(n)` holds a value, from the abridged tag word.
+    pub fn st_valid(&self, n: usize) -> bool {
+        let top = (self.regs.swd >> 11) & 7;
+        let physical = (top as usize + n) % ST_COUNT;
+        self.regs.ftw & (1 << physical) != 0
+    }
+
+    pub fn mxcsr(&self) -> u32 {
+        self.regs.mxcsr
+    }
+
+    /// x87 control word.
+    pub fn fctrl(&self) -> u16 {
+        self.regs.cwd
+    }
+
+    /// x87 status word.
+    pub fn fstat(&self) -> u16 {
+        self.regs.swd
+    }
+
+    /// Abridged x87 tag word: bit `i` is set when physical register `i` is
+    /// in use.
+    pub fn ftag(&self) -> u16 {
+        self.regs.ftw
+    }
+
+    /// Opcode of the last x87 instruction.
+    pub fn fop(&self) -> u16 {
+        self.regs.fop
+    }
+
+    /// Read the register called `name`: `xmm0`-`xmm15`, `st0`-`st7` (raw
+    /// 80 bits), or one of `fctrl`, `fstat`, `ftag`, `fop` and `mxcsr`.
+    pub fn get(&self, name: &str) -> FdbResult<u128> {
+        let unknown = || FdbError::UnknownRegister(name.to_string());
+        if let Some(n) = indexed(name, "xmm") {
+            return self.xmm(n).ok_or_else(unknown);
+        }
+        if let Some(n) = indexed(name, "st") {
+            let mut bytes = [0; 16];
+            bytes[..10].copy_from_slice(&self.st(n).ok_or_else(unknown)?);
+            return Ok(u128::from_le_bytes(bytes));
+        }
+        Ok(match name {
+            "fctrl" => self.fctrl().into(),
+            "fstat" => self.fstat().into(),
+            "ftag" => self.ftag().into(),
+            "fop" => self.fop().into(),
+            "mxcsr" => self.mxcsr().into(),
+            _ => return Err(unknown()),
+        })
+    }
+
+    /// Overwrite the register called `name` in this snapshot. The value
+    /// must fit the register, e.g. 80 bits for `st0`.
+    pub fn set(&mut self, name: &str, value: u128) -> FdbResult<()> {
+        let unknown = || FdbError::UnknownRegister(name.to_string());
+        let bits = register_bits(name).ok_or_else(unknown)?;
+        if bits < 128 && value >> bits != 0 {
+            return Err(FdbError::RegisterValueTooWide {
+                name: name.to_string(),
+                bits,
+            });
+        }
+        let words = value.to_le_bytes();
+        let words = words
+            .chunks(4)
+            .map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap()));
+        if let Some(n) = indexed(name, "xmm") {
+            let slot = self
+                .regs
+                .xmm_space
+                .get_mut(n * 4..n * 4 + 4)
+                .ok_or_else(unknown)?;
+            slot.iter_mut().zip(words).for_each(|(dst, src)| *dst = src);
+        } else if let Some(n) = indexed(name, "st") {
+            let slot = self
+                .regs
+                .st_space
+                .get_mut(n * 4..n * 4 + 4)
+                .ok_or_else(unknown)?;
+            slot.iter_mut().zip(words).for_each(|(dst, src)| *dst = src);
+        } else {
+            match name {
+                "fctrl" => self.regs.cwd = value as u16,
+                "fstat" => self.regs.swd = value as u16,
+                "ftag" => self.regs.ftw = value as u16,
+                "fop" => self.regs.fop = value as u16,
+                _ => self.regs.mxcsr = value as u32,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for FpRegisters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut map = f.debug_map();
+        for (name, _) in CONTROL_REGISTERS {
+            map.entry(name, &format_args!("{:#x}", self.get(name).unwrap_or(0)));
+        }
+        for n in 0..XMM_COUNT {
+            map.entry(
+                &format_args!("xmm{n}"),
+                &format_args!("{:#x}", self.xmm(n).unwrap_or(0)),
+            );
+        }
+        map.finish()
+    }
+}
+
+/// `n` if `name` is `prefix` followed by a decimal index.
+fn indexed(name: &str, prefix: &str) -> Option<usize> {
+    name.strip_prefix(prefix)?.parse().ok()
+}
+
+/// Width of the floating-point register `name`, if there is one.
+fn register_bits(name: &str) -> Option<u32> {
+    match (indexed(name, "xmm"), indexed(name, "st")) {
+        (Some(n), _) if n < XMM_COUNT => Some(128),
+        (_, Some(n)) if n < ST_COUNT => Some(80),
+        _ => CONTROL_REGISTERS
+            .iter()
+            .find(|(control, _)| *control == name)
+            .map(|&(_, bits)| bits),
+    }
+}
+
+/// Whether `name` is a register this module handles rather than a
+/// general-purpose one.
+pub fn is_fp_register(name: &str) -> bool {
+    register_bits(name).is_some()
+}
+
+/// Exception flags and masks of MXCSR, by bit position.
+const MXCSR_BITS: &[(u32, &str)] = &[
+    (0, "IE"),
+    (1, "DE"),
+    (2, "ZE"),
+    (3, "OE"),
+    (4, "UE"),
+    (5, "PE"),
+    (6, "DAZ"),
+    (7, "IM"),
+    (8, "DM"),
+    (9, "ZM"),
+    (10, "OM"),
+    (11, "UM"),
+    (12, "PM"),
+    (15, "FZ"),
+];
+
+/// Render `mxcsr` as GDB does, e.g. `[ IM DM ZM OM UM PM ]`.
+pub fn format_mxcsr(mxcsr: u32) -> String {
+    let mut out = String::from("[ ");
+    for (_, name) in MXCSR_BITS.iter().filter(|(bit, _)| mxcsr & (1 << bit) != 0) {
+        out.push_str(name);
+        out.push(' ');
+    }
+    out.push(']');
+    out
+}
+
+/// Render a 128-bit vector register as GDB does, as packed floats and
+/// packed integers of each width.
+pub fn format_xmm(value: u128) -> String {
+    let bytes = value.to_le_bytes();
+    let lanes = |width: usize, render: &dyn Fn(&[u8]) -> String| {
+        let lanes: Vec<String> = bytes.chunks(width).map(render).collect();
+        format!("{{{}}}", lanes.join(", "))
+    };
+    let float = |b: &[u8]| f32::from_le_bytes(b.try_into().unwrap()).to_string();
+    let double = |b: &[u8]| f64::from_le_bytes(b.try_into().unwrap()).to_string();
+    let int = |b: &[u8]| {
+        let mut word = [0; 8];
+        word[..b.len()].copy_from_slice(b);
+        format!("{:#x}", u64::from_le_bytes(word))
+    };
+    format!(
+        "{{v4_float = {}, v2_double = {}, v16_int8 = {}, v8_int16 = {}, v4_int32 = {}, \
+         v2_int64 = {}, uint128 = {value:#x}}}",
+        lanes(4, &float),
+        lanes(8, &double),
+        lanes(1, &int),
+        lanes(2, &int),
+        lanes(4, &int),
+        lanes(8, &int),
+    )
+}
+
+/// Convert an x87 80-bit extended-precision value to the nearest `f64`.
+pub fn x87_to_f64(bytes: [u8; 10]) -> f64 {
+    let mantissa = u64::from_le_bytes(bytes[..8].try_into().unwrap());
+    let sign_exp = u16::from_le_bytes([bytes[8], bytes[9]]);
+    let sign = if sign_exp & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exp = i32::from(sign_exp & 0x7fff);
+    let magnitude = match exp {
+        0 if mantissa == 0 => 0.0,
+        0x7fff if mantissa << 1 == 0 => f64::INFINITY,
+        0x7fff => f64::NAN,
+        _ => {
+            // Scale in two halves so neither step overflows on its own.
+            let unbiased = (if exp == 0 { 1 } else { exp }) - 16383;
+            let fraction = mantissa as f64 / 2f64.powi(63);
+            fraction * 2f64.powi(unbiased / 2) * 2f64.powi(unbiased - unbiased / 2)
+        }
+    };
+    sign * magnitude
+}
+
+/// Convert `value` to the x87 80-bit extended-precision format. Every
+/// `f64` is exactly representable.
+pub fn f64_to_x87(value: f64) -> [u8; 10] {
+    let bits = value.to_bits();
+    let sign = ((bits >> 63) as u16) << 15;
+    let exp = ((bits >> 52) & 0x7ff) as i32;
+    let fraction = bits & ((1 << 52) - 1);
+    let (exp, mantissa) = match exp {
+        0 if fraction == 0 => (0, 0),
+        0 => {
+            // Subnormal: normalise so the explicit integer bit is set.
+            let shift = fraction.leading_zeros();
+            (16383 - 1022 - (shift as i32 - 11), fraction << shift)
+        }
+        0x7ff if fraction == 0 => (0x7fff, 1 << 63),
+        0x7ff => (0x7fff, 0xc000_0000_0000_0000),
+        _ => (exp - 1023 + 16383, 1 << 63 | fraction << 11),
+    };
+    let mut bytes = [0; 10];
+    bytes[..8].copy_from_slice(&mantissa.to_le_bytes());
+    bytes[8..].copy_from_slice(&(sign | exp as u16).to_le_bytes());
+    bytes
+}
+
+impl ProcessHandle {
+    /// Read the x87 and SSE registers of the current thread with
+    /// `PTRACE_GETREGSET` (`NT_PRFPREG`).
+    pub fn get_fpregs(&self) -> FdbResult<FpRegisters> {
+        self.ensure_alive()?;
+        let regs = ptrace::getregset::<NT_PRFPREG>(self.tid)?;
+        Ok(FpRegisters { regs })
+    }
+
+    /// Write a full floating-point snapshot back to the current thread.
+    pub fn set_fpregs(&mut self, regs: &FpRegisters) -> FdbResult<()> {
+        self.ensure_alive()?;
+        ptrace::setregset::<NT_PRFPREG>(self.tid, regs.regs)?;
+        Ok(())
+    }
+
+    /// Update a single floating-point register by name, e.g. `"xmm0"`.
+    pub fn set_fpreg(&mut self, name: &str, value: u128) -> FdbResult<()> {
+        let mut regs = self.get_fpregs()?;
+        regs.set(name, value)?;
+        self.set_fpregs(&regs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_x87_extended_precision() {
+        for value in [
+            0.0,
+            -0.0,
+            1.0,
+            -2.5,
+            1e300,
+            f64::MIN_POSITIVE,
+            5e-324,
+            f64::MAX,
+        ] {
+            let bytes = f64_to_x87(value);
+            assert_eq!(x87_to_f64(bytes).to_bits(), value.to_bits(), "{value}");
+        }
+        assert_eq!(f64_to_x87(1.0), [0, 0, 0, 0, 0, 0, 0, 0x80, 0xff, 0x3f]);
+        assert_eq!(x87_to_f64(f64_to_x87(f64::INFINITY)), f64::INFINITY);
+        assert!(x87_to_f64(f64_to_x87(f64::NAN)).is_nan());
+    }
+
+    #[test]
+    fn formats_vector_lanes() {
+        let one = 1.0f32.to_bits() as u128;
+        let text = format_xmm(one);
+        assert!(text.starts_with("{v4_float = {1, 0, 0, 0}, v2_double = {"));
+        assert!(text.contains("v4_int32 = {0x3f800000, 0x0, 0x0, 0x0}"));
+        assert!(text.ends_with("uint128 = 0x3f800000}"));
+        assert_eq!(format_mxcsr(0x1f80), "[ IM DM ZM OM UM PM ]");
+    }
+}
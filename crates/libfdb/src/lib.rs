@@ -1,8 +1,26 @@
 //! Core library facade for the fdb debugger.
 //! Provides modules reused by the CLI front end.
 
+pub mod breakpoint;
+pub mod catchpoint;
+pub mod disasm;
+pub mod dwarf;
 pub mod errors;
+pub mod expr;
+pub mod fork;
+pub mod fpregs;
+pub mod maps;
+pub mod memory;
 pub mod process;
+pub mod registers;
+pub mod signals;
+pub mod symbols;
+pub mod syscall_decode;
+pub mod syscalls;
+pub mod threads;
+pub mod unwind;
+pub mod variables;
+pub mod watchpoint;
 
 pub use errors::{FdbError, FdbResult};
 
@@ -23,10 +41,129 @@ pub enum ProcessState {
     Exited(i32),
     /// The process was terminated by a signal
     Terminated(nix::sys::signal::Signal),
+    /// We stopped tracing the process and it continues on its own
+    Detached,
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct StopReason {
     /// Which signal caused the stop (SIGTRAP, SIGINT, etc.)
     pub signal: nix::sys::signal::Signal,
+    /// What the debugger attributes the stop to
+    pub cause: StopCause,
+}
+
+impl StopReason {
+    /// Id of the software breakpoint that trapped, if any
+    pub fn hit_breakpoint(&self) -> Option<u32> {
+        match self.cause {
+            StopCause::Breakpoint(id) | StopCause::TemporaryBreakpoint(id) => Some(id),
+            _ => None,
+        }
+    }
+}
+
+/// Everything the debugger knows about the most recent stop, resolved once
+/// when the stop is reported.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct StopEvent {
+    /// Thread that reported the stop
+    #[serde(serialize_with = "serialize_pid")]
+    pub tid: nix::unistd::Pid,
+    /// Program counter of that thread, after any breakpoint rewind
+    pub pc: u64,
+    /// Which signal caused the stop
+    #[serde(serialize_with = "serialize_signal")]
+    pub signal: nix::sys::signal::Signal,
+    /// What the debugger attributes the stop to
+    pub cause: StopCause,
+    /// Catchpoint that asked for the stop, if any
+    pub catchpoint: Option<u32>,
+    /// Function whose symbol covers `pc`
+    pub function: Option<String>,
+    /// Source file and line of `pc`
+    pub location: Option<(std::path::PathBuf, u32)>,
+    /// Details of the signal, for stops by a signal the program received
+    pub siginfo: Option<signals::SigInfo>,
+}
+
+impl StopEvent {
+    /// Id of the software breakpoint that trapped, if any
+    pub fn hit_breakpoint(&self) -> Option<u32> {
+        match self.cause {
+            StopCause::Breakpoint(id) | StopCause::TemporaryBreakpoint(id) => Some(id),
+            _ => None,
+        }
+    }
+
+    /// Id of the watchpoint that triggered, if any
+    pub fn hit_watchpoint(&self) -> Option<u32> {
+        match self.cause {
+            StopCause::Watchpoint { id, .. } => Some(id),
+            _ => None,
+        }
+    }
+
+    /// The system call entry or exit that stopped the process, if any
+    pub fn syscall(&self) -> Option<syscalls::SyscallEvent> {
+        match self.cause {
+            StopCause::Syscall(event) => Some(event),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopCause {
+    /// A signal not explained by a breakpoint or watchpoint (including the
+    /// SIGTRAP that ends a single-step)
+    Signal,
+    /// The debugger stopped the running process on request, as for Ctrl-C;
+    /// the signal is the SIGSTOP it used
+    Interrupted,
+    /// A software breakpoint trapped
+    Breakpoint(u32),
+    /// A temporary breakpoint trapped and has been deleted
+    TemporaryBreakpoint(u32),
+    /// A hardware watchpoint triggered; values are the watched bytes before
+    /// and after the access
+    Watchpoint { id: u32, old: u64, new: u64 },
+    /// The process entered or left a system call
+    Syscall(syscalls::SyscallEvent),
+    /// The process created a child, which the debugger is now tracing
+    /// unless following the parent only
+    Fork {
+        #[serde(serialize_with = "serialize_pid")]
+        child: nix::unistd::Pid,
+    },
+    /// The process replaced its program image with `execve`; symbols and
+    /// line information now describe the new program
+    Exec,
+    /// The process is about to exit; its memory and registers can still be
+    /// read, and resuming lets the exit finish
+    Exiting(ExitStatus),
+}
+
+/// How a process is leaving, as seen at its exit stop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExitStatus {
+    /// `exit` was called with this code
+    Code(i32),
+    /// This signal is killing it
+    Signal(#[serde(serialize_with = "serialize_signal")] nix::sys::signal::Signal),
+}
+
+/// Serialize a pid or tid as its number.
+fn serialize_pid<S: serde::Serializer>(pid: &nix::unistd::Pid, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_i32(pid.as_raw())
+}
+
+/// Serialize a signal by name, e.g. `"SIGTRAP"`.
+fn serialize_signal<S: serde::Serializer>(
+    sig: &nix::sys::signal::Signal,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    s.serialize_str(sig.as_str())
 }
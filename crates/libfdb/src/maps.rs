@@ -0,0 +1,181 @@
+//! The inferior's address space layout from `/proc/<pid>/maps`.
+
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use nix::unistd::Pid;
+
+use crate::errors::{FdbError, FdbResult};
+use crate::process::ProcessHandle;
+
+/// Access permissions of a mapping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Permissions {
+    pub read: bool,
+    pub write: bool,
+    pub execute: bool,
+    /// Shared mapping (`s`) rather than private copy-on-write (`p`).
+    pub shared: bool,
+}
+
+impl FromStr for Permissions {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let &[r, w, x, p] = s.as_bytes() else {
+            return Err(());
+        };
+        let flag = |b: u8, set: u8| match b {
+            b'-' => Ok(false),
+            b if b == set => Ok(true),
+            _ => Err(()),
+        };
+        Ok(Permissions {
+            read: flag(r, b'r')?,
+            write: flag(w, b'w')?,
+            execute: flag(x, b'x')?,
+            shared: match p {
+                b's' => true,
+                b'p' => false,
+                _ => return Err(()),
+            },
+        })
+    }
+}
+
+impl fmt::Display for Permissions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let flag = |set: bool, c: char| if set { c } else { '-' };
+        write!(
+            f,
+            "{}{}{}{}",
+            flag(self.read, 'r'),
+            flag(self.write, 'w'),
+            flag(self.execute, 'x'),
+            if self.shared { 's' } else { 'p' }
+        )
+    }
+}
+
+/// One line of `/proc/<pid>/maps`.
+#[derive(Debug, Clone)]
+pub struct MemoryRegion {
+    pub start: u64,
+    /// First address past the region.
+    pub end: u64,
+    pub perms: Permissions,
+    /// Offset into the mapped file.
+    pub offset: u64,
+    /// Backing file or pseudo-name such as `[stack]`; `None` if anonymous.
+    pub pathname: Option<String>,
+}
+
+impl MemoryRegion {
+    pub fn contains(&self, addr: u64) -> bool {
+        (self.start..self.end).contains(&addr)
+    }
+
+    /// Parse a single maps line:
+    /// `start-end perms offset dev inode [pathname]`.
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.splitn(6, ' ');
+        let (start, end) = fields.next()?.split_once('-')?;
+        let perms = fields.next()?.parse().ok()?;
+        let offset = u64::from_str_radix(fields.next()?, 16).ok()?;
+        let _dev = fields.next()?;
+        let _inode = fields.next()?;
+        let pathname = fields
+            .next()
+            .map(str::trim_start)
+            .filter(|name| !name.is_empty())
+            .map(str::to_string);
+        Some(MemoryRegion {
+            start: u64::from_str_radix(start, 16).ok()?,
+            end: u64::from_str_radix(end, 16).ok()?,
+            perms,
+            offset,
+            pathname,
+        })
+    }
+}
+
+/// A shared object mapped into the inferior.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedLib {
+    pub path: String,
+    /// Start of its lowest mapping.
+    pub base: u64,
+    /// First address past its highest mapping.
+    pub end: u64,
+    /// Whether the debugger has read symbols for it.
+    pub symbols_loaded: bool,
+}
+
+impl ProcessHandle {
+    /// Current mappings of the inferior, in address order.
+    pub fn memory_map(&self) -> FdbResult<Vec<MemoryRegion>> {
+        read_maps(self.pid)
+    }
+
+    /// Shared objects loaded in the inferior, in address order, one per
+    /// file however many mappings it has.
+    ///
+    /// These are the files other than the executable that have code mapped;
+    /// mapped data files and pseudo-mappings such as `[vdso]` are left out.
+    pub fn shared_libraries(&self) -> FdbResult<Vec<SharedLib>> {
+        let mut libs = shared_objects(self.pid)?;
+        for lib in &mut libs {
+            lib.symbols_loaded = self.symbols.is_loaded(&lib.path);
+        }
+        Ok(libs)
+    }
+}
+
+fn read_maps(pid: Pid) -> FdbResult<Vec<MemoryRegion>> {
+    let maps = std::fs::read_to_string(format!("/proc/{pid}/maps"))?;
+    maps.lines()
+        .map(|line| {
+            MemoryRegion::parse(line).ok_or_else(|| FdbError::MalformedMaps(line.to_string()))
+        })
+        .collect()
+}
+
+/// Shared objects mapped into process `pid`, without regard to symbols.
+pub(crate) fn shared_objects(pid: Pid) -> FdbResult<Vec<SharedLib>> {
+    let exe = std::fs::read_link(format!("/proc/{pid}/exe"))?;
+    let mut libs: Vec<SharedLib> = Vec::new();
+    let mut has_code: Vec<bool> = Vec::new();
+    for region in read_maps(pid)? {
+        let Some(path) = region.pathname.filter(|path| path.starts_with('/')) else {
+            continue;
+        };
+        if Path::new(&path) == exe {
+            continue;
+        }
+        match libs.iter().position(|lib| lib.path == path) {
+            Some(idx) => {
+                let lib = &mut libs[idx];
+                lib.base = lib.base.min(region.start);
+                lib.end = lib.end.max(region.end);
+                has_code[idx] |= region.perms.execute;
+            }
+            None => {
+                libs.push(SharedLib {
+                    path,
+                    base: region.start,
+                    end: region.end,
+                    symbols_loaded: false,
+                });
+                has_code.push(region.perms.execute);
+            }
+        }
+    }
+    let mut libs: Vec<SharedLib> = libs
+        .into_iter()
+        .zip(has_code)
+        .filter_map(|(lib, code)| code.then_some(lib))
+        .collect();
+    libs.sort_by_key(|lib| lib.base);
+    Ok(libs)
+}
@@ -0,0 +1,294 @@
+//! Reading and writing the inferior's address space.
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::os::unix::fs::FileExt;
+use std::path::Path;
+
+use nix::errno::Errno;
+use nix::libc::c_long;
+use nix::sys::ptrace;
+use nix::unistd::Pid;
+
+use crate::errors::{FdbError, FdbResult};
+use crate::process::ProcessHandle;
+
+const WORD: u64 = size_of::<u64>() as u64;
+
+/// Bytes read at a time when scanning or copying large ranges.
+const CHUNK: u64 = 64 * 1024;
+
+/// Bytes read at a time when looking for the end of a C string.
+const STRING_CHUNK: usize = 256;
+
+/// Longest C string `print` shows, as GDB's default `print elements`.
+pub const DEFAULT_STRING_LIMIT: usize = 200;
+
+impl ProcessHandle {
+    /// Read `len` bytes starting at `addr`.
+    ///
+    /// Uses `pread` on `/proc/<pid>/mem` and falls back to word-sized
+    /// `PTRACE_PEEKDATA` when the file is unavailable. If the range runs into
+    /// unmapped memory, [`FdbError::PartialRead`] carries the bytes that were
+    /// readable. The range is read a chunk at a time, so a huge `len` costs
+    /// only as much memory as there is to read.
+    pub fn read_memory(&self, addr: u64, len: usize) -> FdbResult<Vec<u8>> {
+        self.ensure_alive()?;
+        let mut buf = Vec::with_capacity(len.min(CHUNK as usize));
+        while buf.len() < len {
+            let start = buf.len();
+            let want = (len - start).min(CHUNK as usize);
+            buf.resize(start + want, 0);
+            let cur = addr + start as u64;
+            let read = match self.read_proc_mem(cur, &mut buf[start..]) {
+                Ok(n) => n,
+                Err(e) => {
+                    log::debug!("/proc/{}/mem unavailable ({e}), using ptrace", self.pid);
+                    peek(self.tid, cur, &mut buf[start..]).0
+                }
+            };
+
+            if read < want {
+                buf.truncate(start + read);
+                return Err(FdbError::PartialRead {
+                    addr,
+                    requested: len,
+                    data: buf,
+                });
+            }
+        }
+        Ok(buf)
+    }
+
+    /// The NUL-terminated string at `addr`, cut off after `max` bytes and
+    /// decoded as UTF-8 with invalid sequences replaced.
+    ///
+    /// A string running into unmapped memory ends there; only an `addr`
+    /// that cannot be read at all is an error.
+    pub fn read_c_string(&self, addr: u64, max: usize) -> FdbResult<String> {
+        let mut bytes = Vec::new();
+        while bytes.len() < max {
+            let want = STRING_CHUNK.min(max - bytes.len());
+            let (chunk, partial) = match self.read_memory(addr + bytes.len() as u64, want) {
+                Ok(chunk) => (chunk, false),
+                Err(FdbError::PartialRead { data, .. })
+                    if !data.is_empty() || !bytes.is_empty() =>
+                {
+                    (data, true)
+                }
+                Err(e) => return Err(e),
+            };
+            if let Some(nul) = chunk.iter().position(|&b| b == 0) {
+                bytes.extend_from_slice(&chunk[..nul]);
+                break;
+            }
+            bytes.extend_from_slice(&chunk);
+            if partial {
+                break;
+            }
+        }
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    /// Write `data` starting at `addr`; unaligned edges are read-modify-written.
+    pub fn write_memory(&mut self, addr: u64, data: &[u8]) -> FdbResult<()> {
+        self.ensure_alive()?;
+        poke(self.tid, addr, data)?;
+        Ok(())
+    }
+
+    /// Addresses in `[start, end)` where `needle` occurs, in ascending order.
+    ///
+    /// Only readable mappings are scanned, a chunk at a time; parts of a
+    /// mapping that cannot be read (such as `[vvar]`) are skipped. Matches
+    /// may overlap but never span two mappings.
+    pub fn search_memory(&self, start: u64, end: u64, needle: &[u8]) -> FdbResult<Vec<u64>> {
+        self.ensure_alive()?;
+        if needle.is_empty() {
+            return Err(FdbError::EmptySearchPattern);
+        }
+        let mut found = Vec::new();
+        for region in self.memory_map()? {
+            let (lo, hi) = (start.max(region.start), end.min(region.end));
+            if !region.perms.read || lo >= hi {
+                continue;
+            }
+            let mut addr = lo;
+            while addr < hi {
+                let step = CHUNK.min(hi - addr);
+                // Read a little past the chunk so matches straddling the
+                // boundary are seen once.
+                let want = (step + needle.len() as u64 - 1).min(hi - addr);
+                let chunk = match self.read_memory(addr, want as usize) {
+                    Ok(bytes) => bytes,
+                    Err(FdbError::PartialRead { data, .. }) => data,
+                    Err(e) => return Err(e),
+                };
+                found.extend(
+                    chunk
+                        .windows(needle.len())
+                        .take(step as usize)
+                        .enumerate()
+                        .filter(|(_, window)| *window == needle)
+                        .map(|(i, _)| addr + i as u64),
+                );
+                if (chunk.len() as u64) < want {
+                    break;
+                }
+                addr += step;
+            }
+        }
+        Ok(found)
+    }
+
+    /// Copy `len` bytes starting at `addr` into the file at `path`, a chunk
+    /// at a time.
+    ///
+    /// If the range runs into unmapped memory, the readable prefix is still
+    /// written and [`FdbError::PartialDump`] says how much of it there was.
+    pub fn dump_memory(&self, addr: u64, len: usize, path: &Path) -> FdbResult<()> {
+        self.ensure_alive()?;
+        let mut file = io::BufWriter::new(File::create(path)?);
+        let mut written = 0;
+        while written < len {
+            let want = (CHUNK as usize).min(len - written);
+            let (chunk, partial) = match self.read_memory(addr + written as u64, want) {
+                Ok(bytes) => (bytes, false),
+                Err(FdbError::PartialRead { data, .. }) => (data, true),
+                Err(e) => return Err(e),
+            };
+            file.write_all(&chunk)?;
+            written += chunk.len();
+            if partial {
+                file.flush()?;
+                return Err(FdbError::PartialDump {
+                    addr,
+                    requested: len,
+                    written,
+                });
+            }
+        }
+        file.flush()?;
+        Ok(())
+    }
+
+    /// Write the contents of the file at `path` into memory at `addr`.
+    ///
+    /// The whole range must lie in writable mappings; otherwise nothing is
+    /// written and [`FdbError::NotWritable`] names the first address that
+    /// is not. A range running past the top of the address space is
+    /// [`FdbError::AddressOverflow`].
+    pub fn restore_memory(&mut self, addr: u64, path: &Path) -> FdbResult<()> {
+        self.ensure_alive()?;
+        let data = std::fs::read(path)?;
+        let end = addr
+            .checked_add(data.len() as u64)
+            .ok_or(FdbError::AddressOverflow {
+                addr,
+                len: data.len(),
+            })?;
+        let mut cursor = addr;
+        for region in self.memory_map()? {
+            if cursor >= end {
+                break;
+            }
+            if !region.contains(cursor) {
+                continue;
+            }
+            if !region.perms.write {
+                return Err(FdbError::NotWritable(cursor));
+            }
+            cursor = region.end;
+        }
+        if cursor < end {
+            return Err(FdbError::NotWritable(cursor));
+        }
+        self.write_memory(addr, &data)
+    }
+
+    fn read_proc_mem(&self, addr: u64, buf: &mut [u8]) -> io::Result<usize> {
+        let file = File::open(format!("/proc/{}/mem", self.pid))?;
+        let mut read = 0;
+        while read < buf.len() {
+            match file.read_at(&mut buf[read..], addr + read as u64) {
+                Ok(0) => break,
+                Ok(n) => read += n,
+                // EIO marks the first unmapped byte.
+                Err(e) if e.raw_os_error() == Some(Errno::EIO as i32) => break,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(read)
+    }
+}
+
+/// Copy `buf.len()` bytes from `addr` using aligned `PTRACE_PEEKDATA` words.
+///
+/// Returns how many bytes were copied alongside the first failure, if any.
+pub(crate) fn peek(pid: Pid, addr: u64, buf: &mut [u8]) -> (usize, nix::Result<()>) {
+    let mut read = 0;
+    while read < buf.len() {
+        let cur = addr + read as u64;
+        let aligned = cur & !(WORD - 1);
+        let word = match ptrace::read(pid, aligned as ptrace::AddressType) {
+            Ok(word) => word,
+            Err(e) => return (read, Err(e)),
+        };
+        let bytes = word.to_ne_bytes();
+        let skip = (cur - aligned) as usize;
+        let n = (bytes.len() - skip).min(buf.len() - read);
+        buf[read..read + n].copy_from_slice(&bytes[skip..skip + n]);
+        read += n;
+    }
+    (read, Ok(()))
+}
+
+/// Write `data` at `addr` with `PTRACE_POKEDATA`, merging partial words at
+/// either end with their current contents.
+pub(crate) fn poke(pid: Pid, addr: u64, data: &[u8]) -> nix::Result<()> {
+    let mut written = 0;
+    while written < data.len() {
+        let cur = addr + written as u64;
+        let aligned = cur & !(WORD - 1);
+        let skip = (cur - aligned) as usize;
+        let n = (WORD as usize - skip).min(data.len() - written);
+        let mut bytes = if n == WORD as usize {
+            [0u8; WORD as usize]
+        } else {
+            ptrace::read(pid, aligned as ptrace::AddressType)?.to_ne_bytes()
+        };
+        bytes[skip..skip + n].copy_from_slice(&data[written..written + n]);
+        ptrace::write(
+            pid,
+            aligned as ptrace::AddressType,
+            c_long::from_ne_bytes(bytes),
+        )?;
+        written += n;
+    }
+    Ok(())
+}
+
+/// Render `bytes` as 16-byte rows of hex with an ASCII gutter, labelling each
+/// row with its address. Non-printable bytes show as `.`.
+pub fn format_hexdump(addr: u64, bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for (i, row) in bytes.chunks(16).enumerate() {
+        let hex: Vec<String> = row.iter().map(|b| format!("{b:02x}")).collect();
+        let ascii: String = row
+            .iter()
+            .map(|&b| {
+                if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        out.push_str(&format!(
+            "{:#018x}: {:<47}  |{ascii}|\n",
+            addr + 16 * i as u64,
+            hex.join(" ")
+        ));
+    }
+    out
+}
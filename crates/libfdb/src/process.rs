@@ -1,49 +1,474 @@
 //! Process management wrapping `ptrace` interactions.
 
-use std::ffi::CStr;
+use std::cell::OnceCell;
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::{CStr, CString, OsStr};
+use std::fs::File;
+use std::io::{PipeWriter, Read, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
+use nix::errno::Errno;
+use nix::libc;
+use nix::sys::signal::{self, Signal};
 use nix::{sys::ptrace, unistd::Pid};
 use nix::{
-    sys::wait::waitpid,
-    unistd::{ForkResult, execvp, fork},
+    sys::wait::{WaitPidFlag, WaitStatus, waitpid},
+    unistd::{ForkResult, chdir, dup2_stderr, dup2_stdout, execvp, execvpe, fork},
 };
+use object::Object;
 
+use crate::breakpoint::Breakpoint;
+use crate::catchpoint::{CatchKind, Catchpoint};
+use crate::disasm::AsmSyntax;
+use crate::dwarf::LineTable;
 use crate::errors::{FdbError, FdbResult};
-use crate::{ProcessState, StopReason};
+use crate::fork::FollowForkMode;
+use crate::signals::{SignalPolicy, take_interrupt};
+use crate::symbols::SymbolDatabase;
+use crate::syscalls::SyscallCatch;
+use crate::threads::{ThreadInfo, ThreadState};
+use crate::unwind::{CfiTable, DEFAULT_BACKTRACE_LIMIT};
+use crate::variables::DebugInfo;
+use crate::watchpoint::Watchpoint;
+use crate::{ExitStatus, ProcessState, StopCause, StopEvent, StopReason};
+
+/// Exit codes the forked child uses to report a failure before exec.
+const EXIT_CHDIR_FAILED: i32 = 125;
+const EXIT_REDIRECT_FAILED: i32 = 126;
+const EXIT_EXEC_FAILED: i32 = 127;
+
+/// Options for every tracee. Syscall stops are marked as SIGTRAP | 0x80 so
+/// they can't be mistaken for breakpoints, and we hear about new children,
+/// threads and program images.
+pub(crate) const TRACE_OPTIONS: ptrace::Options = ptrace::Options::PTRACE_O_TRACESYSGOOD
+    .union(ptrace::Options::PTRACE_O_TRACEFORK)
+    .union(ptrace::Options::PTRACE_O_TRACEVFORK)
+    .union(ptrace::Options::PTRACE_O_TRACEVFORKDONE)
+    .union(ptrace::Options::PTRACE_O_TRACECLONE)
+    .union(ptrace::Options::PTRACE_O_TRACEEXEC)
+    .union(ptrace::Options::PTRACE_O_TRACEEXIT);
+
+/// How often [`ProcessHandle::wait_on_signal_timeout`] checks for a stop.
+pub const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Setup applied to a launched program between fork and exec.
+#[derive(Debug, Clone, Default)]
+pub struct LaunchOptions {
+    /// Complete environment as key/value pairs; `None` inherits ours.
+    pub env: Option<Vec<(CString, CString)>>,
+    /// Working directory to switch to.
+    pub cwd: Option<PathBuf>,
+    /// File to receive the program's stdout.
+    pub stdout: Option<PathBuf>,
+    /// File to receive the program's stderr.
+    pub stderr: Option<PathBuf>,
+}
+
+/// How a program was launched, kept so it can be run again.
+#[derive(Debug, Clone)]
+pub(crate) struct LaunchSpec {
+    program: CString,
+    args: Vec<CString>,
+    options: LaunchOptions,
+}
+
+/// Derive the load bias from `AT_ENTRY` in the auxiliary vector versus the
+/// ELF entry point.
+fn compute_load_bias(pid: Pid) -> FdbResult<u64> {
+    const AT_ENTRY: u64 = 9;
+    let auxv = std::fs::read(format!("/proc/{pid}/auxv"))?;
+    let runtime_entry = auxv
+        .chunks_exact(16)
+        .map(|pair| {
+            let (key, value) = pair.split_at(8);
+            let word = |b: &[u8]| u64::from_ne_bytes(b.try_into().unwrap());
+            (word(key), word(value))
+        })
+        .find(|&(key, _)| key == AT_ENTRY)
+        .map_or(0, |(_, value)| value);
+
+    let exe = std::fs::read(format!("/proc/{pid}/exe"))?;
+    let static_entry = object::File::parse(&*exe)?.entry();
+    Ok(runtime_entry.wrapping_sub(static_entry))
+}
+
+/// Search path `execvp` falls back to when `PATH` is unset.
+const DEFAULT_PATH: &str = "/bin:/usr/bin";
+
+/// The file `execvp` would run for `program` from `cwd`: the path itself if
+/// it has a slash, otherwise the first executable file of that name in the
+/// directories of our `PATH`, where an empty entry means `cwd`.
+fn find_program(program: &CStr, cwd: Option<&Path>) -> FdbResult<PathBuf> {
+    let name = Path::new(OsStr::from_bytes(program.to_bytes()));
+    let in_cwd = |path: PathBuf| match cwd {
+        Some(cwd) => cwd.join(path),
+        None => path,
+    };
+    let candidates: Vec<PathBuf> = if name.as_os_str().is_empty() {
+        Vec::new()
+    } else if program.to_bytes().contains(&b'/') {
+        vec![in_cwd(name.to_path_buf())]
+    } else {
+        let path = std::env::var_os("PATH").unwrap_or_else(|| DEFAULT_PATH.into());
+        std::env::split_paths(&path)
+            .map(|dir| in_cwd(dir.join(name)))
+            .collect()
+    };
+    candidates
+        .into_iter()
+        .find(|path| is_executable(path))
+        .ok_or_else(|| FdbError::ProgramNotFound(program.to_string_lossy().into_owned()))
+}
+
+/// Whether `path` is a file with an execute permission bit set.
+fn is_executable(path: &Path) -> bool {
+    std::fs::metadata(path)
+        .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+/// Pids of the processes whose name contains `name`, in ascending order.
+///
+/// A process's name is its `/proc/<pid>/comm`, which the kernel cuts to
+/// 15 bytes, or the file name of its `argv[0]`. Kernel threads and zombies
+/// have no command line and cannot be attached to, so they are left out,
+/// as is our own process.
+pub fn find_pid_by_name(name: &str) -> FdbResult<Vec<i32>> {
+    let own = std::process::id() as i32;
+    let mut pids = Vec::new();
+    for entry in std::fs::read_dir("/proc")? {
+        let Some(pid) = entry?
+            .file_name()
+            .to_str()
+            .and_then(|name| name.parse::<i32>().ok())
+        else {
+            continue;
+        };
+        // Processes may exit while the list is read; skip those that do.
+        let Ok(cmdline) = std::fs::read(format!("/proc/{pid}/cmdline")) else {
+            continue;
+        };
+        if pid == own || cmdline.is_empty() {
+            continue;
+        }
+        let comm = std::fs::read_to_string(format!("/proc/{pid}/comm")).unwrap_or_default();
+        let argv0 = cmdline.split(|&b| b == 0).next().unwrap_or_default();
+        let argv0 = Path::new(OsStr::from_bytes(argv0))
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy();
+        if comm.trim_end().contains(name) || argv0.contains(name) {
+            pids.push(pid);
+        }
+    }
+    pids.sort_unstable();
+    Ok(pids)
+}
+
+/// The arguments process `pid` was started with, joined by spaces.
+pub fn command_line(pid: i32) -> FdbResult<String> {
+    let cmdline = std::fs::read(format!("/proc/{pid}/cmdline"))?;
+    let args: Vec<_> = cmdline
+        .split(|&b| b == 0)
+        .filter(|arg| !arg.is_empty())
+        .map(String::from_utf8_lossy)
+        .collect();
+    Ok(args.join(" "))
+}
+
+/// Describe a child that exited before reaching exec.
+fn launch_failure(code: i32, errno: Option<Errno>) -> String {
+    let stage = match code {
+        EXIT_CHDIR_FAILED => "could not change to the working directory".into(),
+        EXIT_REDIRECT_FAILED => "could not redirect output".into(),
+        EXIT_EXEC_FAILED => "could not execute program".into(),
+        code => format!("child exited with code {code} before exec"),
+    };
+    match errno {
+        Some(errno) => format!("{stage}: {}", errno.desc()),
+        None => stage,
+    }
+}
+
+/// In the forked child: send `errno` to the parent and exit with `code`,
+/// which names the step that failed.
+fn child_failed(report: &mut PipeWriter, code: i32, errno: Errno) -> ! {
+    let _ = report.write_all(&(errno as i32).to_ne_bytes());
+    std::process::exit(code);
+}
+
+/// How the process was last set running, so internal restarts match it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Resumption {
+    /// `PTRACE_CONT` or a single step; no syscall stops.
+    Continue,
+    /// `PTRACE_SYSCALL`, reporting every syscall stop.
+    Syscall,
+    /// `PTRACE_SYSCALL`, reporting only syscalls that match the catch.
+    CaughtSyscall,
+}
 
 /// Represents a traced process under the debugger's control.
 #[derive(Debug)]
 pub struct ProcessHandle {
-    pid: Pid,
-    state: ProcessState,
+    pub(crate) pid: Pid,
+    pub(crate) state: ProcessState,
+    /// Details of the stop last reported by `wait_on_signal`.
+    pub(crate) last_stop: Option<StopEvent>,
+    pub(crate) breakpoints: BTreeMap<u32, Breakpoint>,
+    pub(crate) next_breakpoint_id: u32,
+    /// Internal breakpoints count down from `u32::MAX` to stay clear of user ids.
+    pub(crate) next_internal_id: u32,
+    pub(crate) watchpoints: BTreeMap<u32, Watchpoint>,
+    pub(crate) catchpoints: BTreeMap<u32, Catchpoint>,
+    /// `$name` values set for use in expressions.
+    pub(crate) convenience: BTreeMap<String, i64>,
+    /// Parsed lazily the first time source lines are needed.
+    pub(crate) line_table: OnceCell<Option<LineTable>>,
+    /// Runtime minus link-time address of the main executable, fixed once
+    /// the program image is mapped.
+    load_bias: u64,
+    /// Each module's table is loaded the first time it is searched.
+    pub(crate) symbols: SymbolDatabase,
+    /// Loaded lazily on the first backtrace.
+    pub(crate) cfi: OnceCell<Option<CfiTable>>,
+    /// Parsed lazily the first time a variable is read.
+    pub(crate) debug_info: OnceCell<Option<DebugInfo>>,
+    pub(crate) signal_policies: HashMap<Signal, SignalPolicy>,
+    pub(crate) signal_notices: Vec<Signal>,
+    /// [`ProcessHandle::interrupt`] sent a SIGSTOP not yet seen.
+    pub(crate) interrupting: bool,
+    /// Breakpoint conditions that could not be evaluated on a hit.
+    pub(crate) condition_errors: Vec<(u32, FdbError)>,
+    pub(crate) syscall_catch: Option<SyscallCatch>,
+    /// Whether the process stops just before it exits.
+    pub(crate) catch_exit: bool,
+    pub(crate) resumption: Resumption,
+    /// Steps completed by the last `single_step_n` or `step_over_n`.
+    steps_completed: u32,
+    pub(crate) follow_fork: FollowForkMode,
+    pub(crate) backtrace_limit: usize,
+    pub(crate) asm_syntax: AsmSyntax,
+    /// Children of forks followed under `Child`/`Both`, awaiting a taker.
+    pub(crate) fork_children: Vec<ProcessHandle>,
+    /// Breakpoints lifted while a vfork child shares our memory.
+    pub(crate) vfork_lifted: Vec<u32>,
+    /// Whether we attached to an existing process rather than launching it.
+    pub(crate) attached: bool,
+    /// Set for processes we launched ourselves, which can be run again.
+    launch: Option<LaunchSpec>,
+    pub(crate) threads: Vec<ThreadInfo>,
+    /// Thread that registers are read from and execution is stepped in.
+    pub(crate) tid: Pid,
+    /// Thread that reported the last stop.
+    pub(crate) stopped_thread: Pid,
+    /// Stops reported by other threads while the process was being halted.
+    pub(crate) held_stops: Vec<WaitStatus>,
 }
 
 impl ProcessHandle {
-    /// Launch a new debugger process.
+    pub(crate) fn new(pid: Pid, attached: bool) -> FdbResult<Self> {
+        ptrace::setoptions(pid, TRACE_OPTIONS)?;
+        let load_bias = compute_load_bias(pid)
+            .inspect_err(|e| log::debug!("cannot compute load bias: {e}"))
+            .unwrap_or(0);
+        Ok(ProcessHandle {
+            pid,
+            state: ProcessState::Initialized,
+            last_stop: None,
+            breakpoints: BTreeMap::new(),
+            next_breakpoint_id: 1,
+            next_internal_id: u32::MAX,
+            watchpoints: BTreeMap::new(),
+            catchpoints: BTreeMap::new(),
+            convenience: BTreeMap::new(),
+            line_table: OnceCell::new(),
+            load_bias,
+            symbols: SymbolDatabase::new(pid, load_bias),
+            cfi: OnceCell::new(),
+            debug_info: OnceCell::new(),
+            signal_policies: HashMap::new(),
+            signal_notices: Vec::new(),
+            interrupting: false,
+            condition_errors: Vec::new(),
+            syscall_catch: None,
+            catch_exit: false,
+            resumption: Resumption::Continue,
+            steps_completed: 0,
+            follow_fork: FollowForkMode::default(),
+            backtrace_limit: DEFAULT_BACKTRACE_LIMIT,
+            asm_syntax: AsmSyntax::default(),
+            fork_children: Vec::new(),
+            vfork_lifted: Vec::new(),
+            attached,
+            launch: None,
+            threads: vec![ThreadInfo::new(pid, ThreadState::Stopped)],
+            tid: pid,
+            stopped_thread: pid,
+            held_stops: Vec::new(),
+        })
+    }
+
+    /// Launch a new debugger process that inherits our environment.
     pub fn launch(program: &CStr, args: &[&CStr]) -> FdbResult<Self> {
+        Self::launch_with_options(program, args, &LaunchOptions::default())
+    }
+
+    /// Launch a new debugger process with exactly the environment `env`.
+    pub fn launch_with_env(
+        program: &CStr,
+        args: &[&CStr],
+        env: &[(&CStr, &CStr)],
+    ) -> FdbResult<Self> {
+        let options = LaunchOptions {
+            env: Some(
+                env.iter()
+                    .map(|(key, val)| ((*key).to_owned(), (*val).to_owned()))
+                    .collect(),
+            ),
+            ..LaunchOptions::default()
+        };
+        Self::launch_with_options(program, args, &options)
+    }
+
+    /// Launch a new debugger process, applying `options` before exec.
+    ///
+    /// Output files are opened (and truncated) here, relative to our own
+    /// working directory, so a bad path is reported before forking. The
+    /// child reports a failed `chdir`, redirection or `exec` by writing the
+    /// errno to a close-on-exec pipe, which a successful exec closes.
+    pub fn launch_with_options(
+        program: &CStr,
+        args: &[&CStr],
+        options: &LaunchOptions,
+    ) -> FdbResult<Self> {
+        // A missing program is reported here rather than by the child.
+        find_program(program, options.cwd.as_deref())?;
+        // Prepare everything that allocates before forking.
+        let env = options
+            .env
+            .as_ref()
+            .map(|env| {
+                env.iter()
+                    .map(|(key, val)| {
+                        let mut entry = key.to_bytes().to_vec();
+                        entry.push(b'=');
+                        entry.extend_from_slice(val.to_bytes());
+                        Ok(CString::new(entry)?)
+                    })
+                    .collect::<FdbResult<Vec<_>>>()
+            })
+            .transpose()?;
+        let cwd = options
+            .cwd
+            .as_ref()
+            .map(|dir| CString::new(dir.as_os_str().as_bytes()))
+            .transpose()?;
+        let stdout = options.stdout.as_ref().map(File::create).transpose()?;
+        let stderr = options.stderr.as_ref().map(File::create).transpose()?;
+        let (mut failure, mut report) = std::io::pipe()?;
+
         match unsafe { fork()? } {
             ForkResult::Parent { child, .. } => {
+                drop(report);
+                // The child stops itself before exec; run it up to the
+                // post-exec SIGTRAP so the new image is mapped.
                 waitpid(child, None)?;
-                Ok(ProcessHandle {
-                    pid: child,
-                    state: ProcessState::Initialized,
-                })
+                ptrace::cont(child, None)?;
+                if let WaitStatus::Exited(_, code) = waitpid(child, None)? {
+                    let mut errno = [0; 4];
+                    let errno = failure
+                        .read_exact(&mut errno)
+                        .ok()
+                        .map(|()| Errno::from_raw(i32::from_ne_bytes(errno)));
+                    return Err(FdbError::LaunchFailed(launch_failure(code, errno)));
+                }
+                let mut process = ProcessHandle::new(child, false)?;
+                process.launch = Some(LaunchSpec {
+                    program: program.to_owned(),
+                    args: args.iter().map(|arg| (*arg).to_owned()).collect(),
+                    options: options.clone(),
+                });
+                Ok(process)
             }
             ForkResult::Child => {
+                drop(failure);
                 ptrace::traceme().unwrap_or_else(|e| {
                     eprintln!("TRACEME: {e}");
                     std::process::exit(1);
                 });
+                // A handler the debugger installed must not run in the
+                // program, nor an ignored SIGINT stay ignored across exec.
+                // SAFETY: no handler is being installed.
+                let _ = unsafe { signal::signal(Signal::SIGINT, signal::SigHandler::SigDfl) };
                 let _ = nix::sys::signal::raise(nix::sys::signal::Signal::SIGSTOP);
-                execvp(&program, &args).unwrap_or_else(|e| {
-                    eprintln!("execvp: {e}");
-                    std::process::exit(1);
-                });
-                unreachable!()
+                if let Some(dir) = &cwd
+                    && let Err(e) = chdir(dir.as_c_str())
+                {
+                    child_failed(&mut report, EXIT_CHDIR_FAILED, e);
+                }
+                if let Some(file) = &stdout
+                    && let Err(e) = dup2_stdout(file)
+                {
+                    child_failed(&mut report, EXIT_REDIRECT_FAILED, e);
+                }
+                if let Some(file) = &stderr
+                    && let Err(e) = dup2_stderr(file)
+                {
+                    child_failed(&mut report, EXIT_REDIRECT_FAILED, e);
+                }
+                let Err(e) = match &env {
+                    Some(env) => execvpe(program, args, env),
+                    None => execvp(program, args),
+                };
+                child_failed(&mut report, EXIT_EXEC_FAILED, e);
             }
         }
     }
 
+    /// Kill the program and start it again with the same arguments and
+    /// launch options, stopped at its first instruction.
+    ///
+    /// User breakpoints carry over with their ids: those set on a function
+    /// are looked up again by name, the rest keep their offset into the
+    /// executable. Signal, syscall and fork settings, catchpoints and
+    /// convenience variables are kept; watchpoints are not.
+    pub fn relaunch(&mut self) -> FdbResult<()> {
+        let spec = self.launch.clone().ok_or(FdbError::NotLaunched)?;
+        if self.is_alive()
+            && let Err(e) = self.kill()
+        {
+            log::debug!("killing process {} before rerun: {e}", self.pid);
+        }
+        let args: Vec<&CStr> = spec.args.iter().map(CString::as_c_str).collect();
+        let mut fresh = Self::launch_with_options(&spec.program, &args, &spec.options)?;
+
+        fresh.signal_policies = self.signal_policies.clone();
+        fresh.syscall_catch = self.syscall_catch.clone();
+        fresh.catch_exit = self.catch_exit;
+        fresh.follow_fork = self.follow_fork;
+        fresh.backtrace_limit = self.backtrace_limit;
+        fresh.asm_syntax = self.asm_syntax;
+        fresh.catchpoints = self.catchpoints.clone();
+        fresh.convenience = self.convenience.clone();
+        fresh.next_breakpoint_id = self.next_breakpoint_id;
+        for bp in self.breakpoints.values().filter(|bp| !bp.is_internal()) {
+            let addr = bp
+                .function()
+                .and_then(|name| fresh.function_breakpoint_addr(name))
+                .unwrap_or_else(|| {
+                    bp.addr()
+                        .wrapping_sub(self.load_bias)
+                        .wrapping_add(fresh.load_bias)
+                });
+            fresh.restore_breakpoint(bp, addr);
+        }
+        *self = fresh;
+        Ok(())
+    }
+
     /// Attach to an existing PID using `ptrace`.
     pub fn attach(pid: i32) -> FdbResult<Self> {
         let _pid = Pid::from_raw(pid);
@@ -52,41 +477,217 @@ impl ProcessHandle {
         }
 
         ptrace::attach(_pid)?;
-        waitpid(_pid, None)?;
-        Ok(ProcessHandle {
-            pid: _pid,
-            state: ProcessState::Initialized,
-        })
+        waitpid(_pid, Some(WaitPidFlag::__WALL))?;
+        let mut process = ProcessHandle::new(_pid, true)?;
+        process.attach_threads()?;
+        Ok(process)
     }
 
+    /// Wait until some thread stops in a way worth reporting, then halt
+    /// the rest so the whole process is stopped.
+    ///
+    /// The reporting thread becomes the current thread.
     pub fn wait_on_signal(&mut self) -> FdbResult<ProcessState> {
+        loop {
+            let status = self.next_status()?;
+            if let Some(state) = self.handle_status(status)? {
+                return self.report_stop(state);
+            }
+        }
+    }
+
+    /// Like [`ProcessHandle::wait_on_signal`], but give up and return
+    /// `None` if nothing worth reporting happens within `dur`; the process
+    /// keeps running.
+    ///
+    /// The process is polled with `WNOHANG` every [`WAIT_POLL_INTERVAL`],
+    /// so the call can overrun `dur` by up to that much.
+    pub fn wait_on_signal_timeout(&mut self, dur: Duration) -> FdbResult<Option<ProcessState>> {
+        let deadline = Instant::now() + dur;
+        loop {
+            match self.poll_status(WaitPidFlag::WNOHANG)? {
+                Some(status) => {
+                    if let Some(state) = self.handle_status(status)? {
+                        return self.report_stop(state).map(Some);
+                    }
+                }
+                None if Instant::now() >= deadline => return Ok(None),
+                None => std::thread::sleep(WAIT_POLL_INTERVAL),
+            }
+        }
+    }
+
+    /// Finish reporting `state`: on a stop, halt the other threads and
+    /// record the details.
+    fn report_stop(&mut self, state: ProcessState) -> FdbResult<ProcessState> {
+        self.last_stop = None;
+        if let ProcessState::Stopped(reason) = state {
+            self.stopped_thread = self.tid;
+            self.stop_all_threads()?;
+            let catchpoint = self.hit_catchpoint(reason);
+            self.last_stop = Some(self.stop_event(reason, catchpoint)?);
+        }
+        Ok(state)
+    }
+
+    /// Details of the last stop, or `None` while running or after exit.
+    pub fn last_stop(&self) -> Option<&StopEvent> {
+        self.last_stop.as_ref()
+    }
+
+    /// Resolve the program counter of the stopped thread for `reason`.
+    fn stop_event(&self, reason: StopReason, catchpoint: Option<u32>) -> FdbResult<StopEvent> {
+        let pc = self.pc()?;
+        Ok(StopEvent {
+            tid: self.tid,
+            pc,
+            signal: reason.signal,
+            cause: reason.cause,
+            catchpoint,
+            function: self.symbol_at(pc).map(|sym| sym.name),
+            location: self.addr_to_line(pc),
+            siginfo: match reason.cause {
+                StopCause::Signal if reason.signal != Signal::SIGTRAP => self.siginfo().ok(),
+                _ => None,
+            },
+        })
+    }
+
+    /// Act on one wait status. Returns the state to report, or `None` if
+    /// the thread was dealt with and set running again.
+    fn handle_status(&mut self, status: WaitStatus) -> FdbResult<Option<ProcessState>> {
         use nix::sys::wait::WaitStatus::*;
-        match waitpid(self.pid, None)? {
+        let Some(tid) = status.pid() else {
+            return Ok(Some(self.state));
+        };
+        match status {
+            Exited(..) | Signaled(..) if tid != self.pid => {
+                self.remove_thread(tid);
+                return Ok(None);
+            }
+            Exited(..) | Signaled(..) => {}
+            _ => {
+                self.thread_mut(tid)?.state = ThreadState::Stopped;
+                self.tid = tid;
+            }
+        }
+
+        match status {
+            // The SIGSTOP that halted this thread for an earlier stop.
+            Stopped(_, Signal::SIGSTOP) if self.thread_mut(tid)?.stop_requested => {
+                self.thread_mut(tid)?.stop_requested = false;
+                self.restart(None)?;
+                Ok(None)
+            }
+            // Signals configured as `nostop` go straight back to the process
+            // unless caught.
+            Stopped(_, sig)
+                if sig != Signal::SIGTRAP
+                    && !self.signal_policy(sig).stop
+                    && !self.catches(CatchKind::Signal(sig)) =>
+            {
+                let policy = self.signal_policy(sig);
+                if policy.print {
+                    self.signal_notices.push(sig);
+                }
+                self.restart(policy.pass.then_some(sig))?;
+                Ok(None)
+            }
+            PtraceEvent(_, _, event)
+                if [
+                    libc::PTRACE_EVENT_FORK,
+                    libc::PTRACE_EVENT_VFORK,
+                    libc::PTRACE_EVENT_CLONE,
+                ]
+                .contains(&event) =>
+            {
+                let state = self.handle_fork_event(event)?;
+                if state.is_none() {
+                    self.restart(None)?;
+                }
+                Ok(state)
+            }
+            PtraceEvent(_, _, libc::PTRACE_EVENT_VFORK_DONE) => {
+                self.handle_vfork_done()?;
+                self.restart(None)?;
+                Ok(None)
+            }
+            PtraceEvent(_, _, libc::PTRACE_EVENT_EXEC) => {
+                self.handle_exec();
+                if !self.catches(CatchKind::Exec) {
+                    self.restart(None)?;
+                    return Ok(None);
+                }
+                self.state = ProcessState::Stopped(StopReason {
+                    signal: Signal::SIGTRAP,
+                    cause: StopCause::Exec,
+                });
+                Ok(Some(self.state))
+            }
+            PtraceEvent(_, _, libc::PTRACE_EVENT_EXIT) => {
+                // Threads leaving on their own are not worth a stop; the
+                // exit of the whole process is, when caught.
+                if tid != self.pid || !self.catch_exit {
+                    self.restart(None)?;
+                    return Ok(None);
+                }
+                let status = ptrace::getevent(tid)? as i32;
+                self.state = ProcessState::Stopped(StopReason {
+                    signal: Signal::SIGTRAP,
+                    cause: StopCause::Exiting(exit_status(status)?),
+                });
+                Ok(Some(self.state))
+            }
+            PtraceSyscall(_) => {
+                let event = self.syscall_event()?;
+                if !self.stops_at_syscall(event.number()) {
+                    self.restart(None)?;
+                    return Ok(None);
+                }
+                self.state = ProcessState::Stopped(StopReason {
+                    signal: Signal::SIGTRAP,
+                    cause: StopCause::Syscall(event),
+                });
+                Ok(Some(self.state))
+            }
             Stopped(_, sig) => {
-                let sig = sig; // nix::sys::signal::Signal
-                let reason = StopReason { signal: sig };
-                self.state = ProcessState::Stopped(reason);
-                Ok(self.state)
+                let cause = if sig == Signal::SIGTRAP {
+                    self.trap_cause()?
+                } else if sig == Signal::SIGSTOP
+                    && (std::mem::take(&mut self.interrupting) | take_interrupt(self.pid))
+                {
+                    StopCause::Interrupted
+                } else {
+                    StopCause::Signal
+                };
+                // A false condition lets the thread carry on past the trap.
+                let mut cause = cause;
+                if let StopCause::Breakpoint(id) = cause {
+                    if !self.breakpoint_should_stop(id) {
+                        self.step_over_breakpoint(tid)?;
+                        self.restart(None)?;
+                        return Ok(None);
+                    }
+                    if self.retire_temp_breakpoint(id)? {
+                        cause = StopCause::TemporaryBreakpoint(id);
+                    }
+                }
+                self.state = ProcessState::Stopped(StopReason { signal: sig, cause });
+                Ok(Some(self.state))
             }
             Exited(_, code) => {
                 self.state = ProcessState::Exited(code);
-                Ok(self.state)
+                Ok(Some(self.state))
             }
             Signaled(_, sig, _core) => {
                 self.state = ProcessState::Terminated(sig);
-                Ok(self.state)
-            }
-            other => {
-                // We shouldn’t see others much; surface as Stopped(SIGTRAP)-ish later.
-                // For now just keep it simple and treat as a generic stop if it happens.
-                if let Stopped(_, sig) = other {
-                    let reason = StopReason { signal: sig };
-                    self.state = ProcessState::Stopped(reason);
-                    Ok(self.state)
-                } else {
-                    // fallback: don't change state; return current
-                    Ok(self.state)
-                }
+                Ok(Some(self.state))
+            }
+            // Group stops and any other events were not asked for; keep the
+            // thread going.
+            PtraceEvent(..) | Continued(_) | StillAlive => {
+                self.restart(None)?;
+                Ok(None)
             }
         }
     }
@@ -95,14 +696,511 @@ impl ProcessHandle {
         self.state
     }
 
+    /// The code the program passed to `exit`, once it has exited normally.
+    pub fn exit_status(&self) -> Option<i32> {
+        match self.state {
+            ProcessState::Exited(code) => Some(code),
+            _ => None,
+        }
+    }
+
+    /// The signal that killed the program, if one did.
+    pub fn termination_signal(&self) -> Option<Signal> {
+        match self.state {
+            ProcessState::Terminated(sig) => Some(sig),
+            _ => None,
+        }
+    }
+
+    /// Stop with [`StopCause::Exiting`] just before the process exits, while
+    /// its state can still be inspected.
+    pub fn set_catch_exit(&mut self, catch: bool) {
+        self.catch_exit = catch;
+    }
+
+    pub fn catch_exit(&self) -> bool {
+        self.catch_exit
+    }
+
+    /// Fail with a typed error once the process has exited, been killed or
+    /// been detached, instead of letting ptrace report `ESRCH`.
+    pub(crate) fn ensure_alive(&self) -> FdbResult<()> {
+        match self.state {
+            ProcessState::Exited(code) => Err(FdbError::ProcessExited(code)),
+            ProcessState::Terminated(_) | ProcessState::Detached => Err(FdbError::NotRunning),
+            ProcessState::Initialized | ProcessState::Running | ProcessState::Stopped(_) => Ok(()),
+        }
+    }
+
     pub fn resume(&mut self) -> FdbResult<()> {
-        ptrace::cont(self.pid, None)?;
+        self.resume_as(None, self.default_resumption())
+    }
+
+    /// Continue the process, delivering `sig` to it as it resumes.
+    pub fn resume_with_signal(&mut self, sig: Signal) -> FdbResult<()> {
+        self.resume_as(Some(sig), self.default_resumption())
+    }
+
+    /// Continue from `addr` instead of the current pc.
+    ///
+    /// The signal the process stopped with is dropped rather than passed
+    /// on, so jumping past a faulting instruction does not fault again. A
+    /// breakpoint at `addr` is stepped over like one at the pc of a stop.
+    pub fn jump(&mut self, addr: u64) -> FdbResult<()> {
+        self.set_pc(addr)?;
+        if let ProcessState::Stopped(reason) = &mut self.state {
+            // SIGTRAP is never passed on.
+            reason.signal = Signal::SIGTRAP;
+        }
+        self.resume()
+    }
+
+    fn default_resumption(&self) -> Resumption {
+        if self.syscall_catch.is_some() {
+            Resumption::CaughtSyscall
+        } else {
+            Resumption::Continue
+        }
+    }
+
+    pub(crate) fn resume_as(&mut self, sig: Option<Signal>, how: Resumption) -> FdbResult<()> {
+        self.ensure_alive()?;
+        let mut sig = sig;
+        let stopped: Vec<Pid> = self
+            .threads
+            .iter()
+            .filter(|thread| thread.state == ThreadState::Stopped)
+            .map(|thread| thread.tid)
+            .collect();
+        if let ProcessState::Stopped(reason) = self.state {
+            for &tid in &stopped {
+                self.step_over_breakpoint(tid)?;
+            }
+            // Without an explicit signal, forward the one we stopped on if
+            // the policy says to pass it.
+            if sig.is_none() && self.signal_policy(reason.signal).pass {
+                sig = Some(reason.signal);
+            }
+        }
+        self.resumption = how;
+        // The signal goes to the thread that stopped, whichever is selected.
+        for tid in stopped {
+            let thread_sig = if tid == self.stopped_thread {
+                sig
+            } else {
+                None
+            };
+            self.restart_thread(tid, thread_sig)?;
+        }
+        self.state = ProcessState::Running;
+        self.last_stop = None;
+        Ok(())
+    }
+
+    /// Set the current thread running again the way it was last resumed.
+    fn restart(&mut self, sig: Option<Signal>) -> FdbResult<()> {
+        self.restart_thread(self.tid, sig)
+    }
+
+    pub(crate) fn restart_thread(&mut self, tid: Pid, sig: Option<Signal>) -> FdbResult<()> {
+        if !self.set_running(tid)? {
+            return Ok(());
+        }
+        match self.resumption {
+            Resumption::Continue => {
+                // A pending syscall exit stop will not be reported.
+                self.thread_mut(tid)?.in_syscall = false;
+                ptrace::cont(tid, sig)?;
+            }
+            Resumption::Syscall | Resumption::CaughtSyscall => ptrace::syscall(tid, sig)?,
+        }
+        Ok(())
+    }
+
+    /// Drop everything that described the image replaced by `execve`.
+    ///
+    /// User breakpoints are kept but disabled, since their addresses belong
+    /// to the old program. The kernel clears the debug registers on exec, so
+    /// watchpoints are gone as well.
+    fn handle_exec(&mut self) {
+        self.breakpoints.retain(|_, bp| !bp.is_internal());
+        for bp in self.breakpoints.values_mut() {
+            bp.forget_insertion();
+        }
+        self.watchpoints.clear();
+        self.vfork_lifted.clear();
+        // Exec leaves only one thread, running under the process id.
+        self.threads = vec![ThreadInfo::new(self.pid, ThreadState::Stopped)];
+        self.tid = self.pid;
+        self.stopped_thread = self.pid;
+        self.held_stops.clear();
+        self.line_table = OnceCell::new();
+        self.cfi = OnceCell::new();
+        self.debug_info = OnceCell::new();
+        self.load_bias = compute_load_bias(self.pid)
+            .inspect_err(|e| log::debug!("cannot compute load bias: {e}"))
+            .unwrap_or(0);
+        self.symbols = SymbolDatabase::new(self.pid, self.load_bias);
+    }
+
+    /// Path of the program the process is currently running.
+    pub fn executable(&self) -> FdbResult<PathBuf> {
+        Ok(std::fs::read_link(format!("/proc/{}/exe", self.pid))?)
+    }
+
+    /// Attribute a SIGTRAP to a breakpoint, a watchpoint, or neither.
+    fn trap_cause(&mut self) -> FdbResult<StopCause> {
+        if let Some(id) = self.rewind_breakpoint_trap()? {
+            return Ok(StopCause::Breakpoint(id));
+        }
+        if let Some((id, old, new)) = self.triggered_watchpoint()? {
+            return Ok(StopCause::Watchpoint { id, old, new });
+        }
+        Ok(StopCause::Signal)
+    }
+
+    /// Execute exactly one instruction and wait for the resulting stop.
+    ///
+    /// A breakpoint under RIP is lifted for the step so the original
+    /// instruction runs instead of `int3`.
+    pub fn single_step(&mut self) -> FdbResult<ProcessState> {
+        self.ensure_alive()?;
+        let pc = self.pc()?;
+        let lifted = self.enabled_breakpoint_at(pc);
+        if let Some(id) = lifted {
+            self.disable_breakpoint(id)?;
+        }
+
+        self.resumption = Resumption::Continue;
+        if self.set_running(self.tid)? {
+            ptrace::step(self.tid, None)?;
+        }
+        self.thread_mut(self.tid)?.in_syscall = false;
         self.state = ProcessState::Running;
+        self.last_stop = None;
+        let state = self.wait_on_signal()?;
+
+        if let (Some(id), ProcessState::Stopped(_)) = (lifted, state) {
+            self.enable_breakpoint(id)?;
+        }
+        Ok(state)
+    }
+
+    /// Execute `n` instructions, one single-step at a time.
+    ///
+    /// Stepping ends early when the process exits, stops for anything but
+    /// the end of a step, or lands on an enabled breakpoint;
+    /// [`steps_completed`] tells how many steps ran.
+    ///
+    /// [`steps_completed`]: ProcessHandle::steps_completed
+    pub fn single_step_n(&mut self, n: u32) -> FdbResult<ProcessState> {
+        self.repeat_step(n, Self::single_step)
+    }
+
+    /// Like [`single_step_n`] with each step taken by [`step_over`].
+    ///
+    /// [`single_step_n`]: ProcessHandle::single_step_n
+    /// [`step_over`]: ProcessHandle::step_over
+    pub fn step_over_n(&mut self, n: u32) -> FdbResult<ProcessState> {
+        self.repeat_step(n, Self::step_over)
+    }
+
+    /// Like [`single_step_n`] with each step taken by [`step_line`].
+    ///
+    /// [`single_step_n`]: ProcessHandle::single_step_n
+    /// [`step_line`]: ProcessHandle::step_line
+    pub fn step_line_n(&mut self, n: u32) -> FdbResult<ProcessState> {
+        self.repeat_step(n, Self::step_line)
+    }
+
+    /// Steps completed by the last [`single_step_n`], [`step_over_n`] or
+    /// [`step_line_n`]; less than asked for when something stopped the
+    /// process first.
+    ///
+    /// [`single_step_n`]: ProcessHandle::single_step_n
+    /// [`step_over_n`]: ProcessHandle::step_over_n
+    /// [`step_line_n`]: ProcessHandle::step_line_n
+    pub fn steps_completed(&self) -> u32 {
+        self.steps_completed
+    }
+
+    fn repeat_step(
+        &mut self,
+        n: u32,
+        step: fn(&mut Self) -> FdbResult<ProcessState>,
+    ) -> FdbResult<ProcessState> {
+        self.steps_completed = 0;
+        let mut state = self.state;
+        for _ in 0..n {
+            state = step(self)?;
+            let ProcessState::Stopped(reason) = state else {
+                break;
+            };
+            if reason.cause != StopCause::Signal || reason.signal != Signal::SIGTRAP {
+                break;
+            }
+            self.steps_completed += 1;
+            if self.enabled_breakpoint_at(self.pc()?).is_some() {
+                break;
+            }
+        }
+        Ok(state)
+    }
+
+    /// Execute one instruction, treating a `call` as a single step by running
+    /// until it returns.
+    ///
+    /// A user breakpoint reached inside the callee stops execution there.
+    pub fn step_over(&mut self) -> FdbResult<ProcessState> {
+        let regs = self.get_registers()?;
+        let Some(len) = self.call_length_at(regs.rip())? else {
+            return self.single_step();
+        };
+
+        self.run_to_return(regs.rip() + len, regs.rsp())
+    }
+
+    /// Run until the current function returns to its caller.
+    pub fn step_out(&mut self) -> FdbResult<ProcessState> {
+        let (return_addr, caller_sp) = self.current_return()?;
+        self.run_to_return(return_addr, caller_sp)
+    }
+
+    /// Run until `addr` is reached or the current function returns,
+    /// whichever happens first.
+    ///
+    /// Neither hidden breakpoint outlives the stop, and a user breakpoint
+    /// met on the way still stops execution there.
+    pub fn run_until(&mut self, addr: u64) -> FdbResult<ProcessState> {
+        let (return_addr, caller_sp) = self.current_return()?;
+        self.run_to(Some(addr), Some((return_addr, caller_sp)))
+    }
+
+    /// Run a freshly started program up to the entry point of the
+    /// executable, past the dynamic loader. A statically linked program
+    /// starts there already and is left as it is.
+    pub fn run_to_entry(&mut self) -> FdbResult<ProcessState> {
+        let entry = self.entry_point()?;
+        if self.pc()? == entry {
+            return Ok(self.state);
+        }
+        self.run_to(Some(entry), None)
+    }
+
+    /// Return address of the current function and the stack pointer its
+    /// caller resumes with.
+    ///
+    /// The return address is read from `[rbp+8]` once the frame is set up,
+    /// or from the top of the stack while still in the prologue or at `ret`.
+    fn current_return(&self) -> FdbResult<(u64, u64)> {
+        let regs = self.get_registers()?;
+        let slot = match self.decode(regs.rip(), 1)?.first() {
+            Some(insn) if matches!(insn.mnemonic.as_str(), "endbr64" | "ret") => regs.rsp(),
+            Some(insn) if insn.mnemonic == "push" && insn.operands == "rbp" => regs.rsp(),
+            Some(insn) if insn.mnemonic == "mov" && insn.operands == "rbp, rsp" => regs.rsp() + 8,
+            _ => regs.rbp() + 8,
+        };
+
+        let bytes = self.read_memory(slot, 8)?;
+        let return_addr = u64::from_le_bytes(bytes.try_into().expect("read 8 bytes"));
+        Ok((return_addr, slot + 8))
+    }
+
+    /// Step until execution reaches the start of a different source line.
+    ///
+    /// Calls into code without line information (PLT stubs, libc) run until
+    /// they return, and returning into such code runs on to the next stop.
+    pub fn step_line(&mut self) -> FdbResult<ProcessState> {
+        let pc = self.pc()?;
+        let Some(start) = self.line_row(pc).map(|row| (row.file.clone(), row.line)) else {
+            return self.step_out();
+        };
+
+        loop {
+            let regs = self.get_registers()?;
+            let call_len = self.call_length_at(regs.rip())?;
+            let mut state = self.single_step()?;
+            if !is_plain_trap(state) {
+                return Ok(state);
+            }
+
+            if self.line_row(self.pc()?).is_none() {
+                state = match call_len {
+                    Some(len) => self.run_to_return(regs.rip() + len, regs.rsp())?,
+                    None => {
+                        self.resume()?;
+                        self.wait_on_signal()?
+                    }
+                };
+                if !is_plain_trap(state) {
+                    return Ok(state);
+                }
+            }
+
+            let pc = self.pc()?;
+            let static_pc = pc.wrapping_sub(self.load_bias());
+            if let Some(row) = self.line_row(pc)
+                && row.is_stmt
+                && row.address == static_pc
+                && (&row.file, row.line) != (&start.0, start.1)
+            {
+                return Ok(state);
+            }
+        }
+    }
+
+    /// Continue until `return_addr` is reached with the stack pointer back at
+    /// `caller_sp`, then remove the hidden breakpoint used to get there.
+    fn run_to_return(&mut self, return_addr: u64, caller_sp: u64) -> FdbResult<ProcessState> {
+        self.run_to(None, Some((return_addr, caller_sp)))
+    }
+
+    /// Continue until `target` is reached, or until the return address in
+    /// `return_to` is reached with the stack pointer back at the caller's.
+    fn run_to(
+        &mut self,
+        target: Option<u64>,
+        return_to: Option<(u64, u64)>,
+    ) -> FdbResult<ProcessState> {
+        let target = match target {
+            Some(addr) => self.set_internal_breakpoint(addr)?,
+            None => None,
+        };
+        let (temp, caller_sp) = match return_to {
+            Some((return_addr, caller_sp)) => {
+                (self.set_internal_breakpoint(return_addr)?, caller_sp)
+            }
+            None => (None, 0),
+        };
+        let state = loop {
+            self.resume()?;
+            let state = self.wait_on_signal()?;
+            // A recursive call can reach the return address in a deeper
+            // frame; only stop once the stack is back at the caller's level.
+            if let ProcessState::Stopped(reason) = state
+                && reason.hit_breakpoint().is_some()
+                && reason.hit_breakpoint() == temp
+                && self.sp()? < caller_sp
+            {
+                continue;
+            }
+            break state;
+        };
+
+        // An exec on the way discards the hidden breakpoints by itself.
+        for id in [target, temp].into_iter().flatten() {
+            if !self.breakpoints.contains_key(&id) {
+                continue;
+            }
+            self.delete_breakpoint(id)?;
+            if let ProcessState::Stopped(mut reason) = state
+                && reason.hit_breakpoint() == Some(id)
+            {
+                reason.cause = StopCause::Signal;
+                self.state = ProcessState::Stopped(reason);
+                if let Some(event) = &mut self.last_stop {
+                    event.cause = StopCause::Signal;
+                }
+            }
+        }
+        Ok(self.state)
+    }
+
+    /// Difference between runtime and link-time addresses of the main
+    /// executable; zero for non-PIE binaries.
+    pub fn load_bias(&self) -> u64 {
+        self.load_bias
+    }
+
+    /// Runtime address of the executable's ELF entry point (`e_entry`
+    /// plus the load bias), where `_start` usually is.
+    pub fn entry_point(&self) -> FdbResult<u64> {
+        let exe = std::fs::read(format!("/proc/{}/exe", self.pid))?;
+        let entry = object::File::parse(&*exe)?.entry();
+        Ok(entry.wrapping_add(self.load_bias))
+    }
+
+    /// Remove every breakpoint and watchpoint, then let the process run
+    /// freely without the debugger.
+    pub fn detach(&mut self) -> FdbResult<()> {
+        self.ensure_alive()?;
+        let ids: Vec<u32> = self.breakpoints.keys().copied().collect();
+        for id in ids {
+            self.delete_breakpoint(id)?;
+        }
+        let ids: Vec<u32> = self.watchpoints.keys().copied().collect();
+        for id in ids {
+            self.remove_watchpoint(id)?;
+        }
+        for thread in &self.threads {
+            ptrace::detach(thread.tid, None)?;
+        }
+        self.state = ProcessState::Detached;
+        self.last_stop = None;
+        Ok(())
+    }
+
+    /// Send SIGKILL and reap the process.
+    pub fn kill(&mut self) -> FdbResult<()> {
+        self.ensure_alive()?;
+        signal::kill(self.pid, Signal::SIGKILL)?;
+        // Drain any stop that was already pending before the kill landed.
+        while let ProcessState::Stopped(_) | ProcessState::Running = self.wait_on_signal()? {}
         Ok(())
     }
 
+    /// Whether the process can still be driven by the debugger.
+    pub fn is_alive(&self) -> bool {
+        matches!(
+            self.state,
+            ProcessState::Initialized | ProcessState::Running | ProcessState::Stopped(_)
+        )
+    }
+
     /// Access the underlying PID for logging or future syscalls.
     pub fn pid(&self) -> Pid {
         self.pid
     }
 }
+
+/// Decode the wait status reported with `PTRACE_EVENT_EXIT`.
+fn exit_status(status: i32) -> FdbResult<ExitStatus> {
+    if libc::WIFEXITED(status) {
+        Ok(ExitStatus::Code(libc::WEXITSTATUS(status)))
+    } else {
+        Ok(ExitStatus::Signal(Signal::try_from(libc::WTERMSIG(
+            status,
+        ))?))
+    }
+}
+
+/// A SIGTRAP stop that was not caused by a user breakpoint, i.e. the normal
+/// end of a step.
+fn is_plain_trap(state: ProcessState) -> bool {
+    matches!(
+        state,
+        ProcessState::Stopped(reason)
+            if reason.signal == Signal::SIGTRAP && reason.cause == StopCause::Signal
+    )
+}
+
+impl Drop for ProcessHandle {
+    /// Kill processes we launched; leave attached ones running untraced.
+    fn drop(&mut self) {
+        if !self.is_alive() {
+            return;
+        }
+        let result = if self.attached {
+            if let ProcessState::Running = self.state {
+                // Detaching requires stopped tracees.
+                let _ = self.stop_all_threads();
+            }
+            self.detach()
+        } else {
+            self.kill()
+        };
+        if let Err(e) = result {
+            log::debug!("cleaning up process {}: {e}", self.pid);
+        }
+    }
+}
@@ -0,0 +1,282 @@
+//! Typed access to the inferior's general-purpose registers.
+
+use std::mem::offset_of;
+
+use nix::libc::user_regs_struct;
+use nix::sys::ptrace;
+
+use crate::errors::{FdbError, FdbResult};
+use crate::process::ProcessHandle;
+
+const USER_REGS_SLOTS: usize = size_of::<user_regs_struct>() / 8;
+
+/// Static description of one register in `user_regs_struct`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterInfo {
+    /// Lower-case name as used on the command line.
+    pub name: &'static str,
+    /// Number assigned by the x86-64 System V DWARF register mapping.
+    pub dwarf_id: Option<u16>,
+    /// Architectural size in bytes.
+    pub size: usize,
+    /// Byte offset of the field inside `user_regs_struct`.
+    pub offset: usize,
+}
+
+macro_rules! reg {
+    ($name:literal, $field:ident, $dwarf:expr, $size:literal) => {
+        RegisterInfo {
+            name: $name,
+            dwarf_id: $dwarf,
+            size: $size,
+            offset: offset_of!(user_regs_struct, $field),
+        }
+    };
+}
+
+/// Every register reachable through `PTRACE_GETREGS`, in GDB display order.
+pub const REGISTERS: &[RegisterInfo] = &[
+    reg!("rax", rax, Some(0), 8),
+    reg!("rbx", rbx, Some(3), 8),
+    reg!("rcx", rcx, Some(2), 8),
+    reg!("rdx", rdx, Some(1), 8),
+    reg!("rsi", rsi, Some(4), 8),
+    reg!("rdi", rdi, Some(5), 8),
+    reg!("rbp", rbp, Some(6), 8),
+    reg!("rsp", rsp, Some(7), 8),
+    reg!("r8", r8, Some(8), 8),
+    reg!("r9", r9, Some(9), 8),
+    reg!("r10", r10, Some(10), 8),
+    reg!("r11", r11, Some(11), 8),
+    reg!("r12", r12, Some(12), 8),
+    reg!("r13", r13, Some(13), 8),
+    reg!("r14", r14, Some(14), 8),
+    reg!("r15", r15, Some(15), 8),
+    reg!("rip", rip, Some(16), 8),
+    reg!("rflags", eflags, Some(49), 8),
+    reg!("cs", cs, Some(51), 2),
+    reg!("ss", ss, Some(52), 2),
+    reg!("ds", ds, Some(53), 2),
+    reg!("es", es, Some(50), 2),
+    reg!("fs", fs, Some(54), 2),
+    reg!("gs", gs, Some(55), 2),
+    reg!("fs_base", fs_base, Some(58), 8),
+    reg!("gs_base", gs_base, Some(59), 8),
+    reg!("orig_rax", orig_rax, None, 8),
+];
+
+/// Look up a register by name; `eflags` is accepted as an alias of `rflags`.
+pub fn register_by_name(name: &str) -> Option<&'static RegisterInfo> {
+    let name = if name == "eflags" { "rflags" } else { name };
+    REGISTERS.iter().find(|info| info.name == name)
+}
+
+/// Look up a register by its DWARF register number.
+pub fn register_by_dwarf(num: u16) -> Option<&'static RegisterInfo> {
+    REGISTERS.iter().find(|info| info.dwarf_id == Some(num))
+}
+
+/// Status and control bits of RFLAGS, by bit position.
+const RFLAGS_BITS: &[(u32, &str)] = &[
+    (0, "CF"),
+    (2, "PF"),
+    (4, "AF"),
+    (6, "ZF"),
+    (7, "SF"),
+    (8, "TF"),
+    (9, "IF"),
+    (10, "DF"),
+    (11, "OF"),
+];
+
+/// Names of the flags set in `rflags`, lowest bit first.
+pub fn decode_rflags(rflags: u64) -> Vec<&'static str> {
+    RFLAGS_BITS
+        .iter()
+        .filter(|(bit, _)| rflags & (1 << bit) != 0)
+        .map(|&(_, name)| name)
+        .collect()
+}
+
+/// Render `rflags` as GDB does, e.g. `[ PF ZF IF ]`.
+pub fn format_rflags(rflags: u64) -> String {
+    let mut out = String::from("[ ");
+    for name in decode_rflags(rflags) {
+        out.push_str(name);
+        out.push(' ');
+    }
+    out.push(']');
+    out
+}
+
+/// Snapshot of the x86-64 general-purpose register file.
+#[derive(Clone, Copy)]
+pub struct Registers {
+    regs: user_regs_struct,
+}
+
+macro_rules! accessors {
+    ($($name:ident),* $(,)?) => {
+        $(
+            pub fn $name(&self) -> u64 {
+                self.regs.$name
+            }
+        )*
+    };
+}
+
+impl Registers {
+    accessors!(
+        rax, rbx, rcx, rdx, rsi, rdi, rbp, rsp, r8, r9, r10, r11, r12, r13, r14, r15, rip, cs, ss,
+        ds, es, fs, gs, fs_base, gs_base, orig_rax,
+    );
+
+    /// RFLAGS; `user_regs_struct` names it `eflags`.
+    pub fn rflags(&self) -> u64 {
+        self.regs.eflags
+    }
+
+    /// Registers in the order GDB's `info registers` prints them.
+    pub fn general_purpose(&self) -> Vec<(&'static str, u64)> {
+        REGISTERS
+            .iter()
+            .filter(|info| info.name != "orig_rax")
+            .map(|info| (info.name, self.read(info)))
+            .collect()
+    }
+
+    /// Read the register described by `info`.
+    pub fn read(&self, info: &RegisterInfo) -> u64 {
+        self.slots()[info.offset / 8]
+    }
+
+    /// Overwrite the register described by `info` in this snapshot.
+    pub fn write(&mut self, info: &RegisterInfo, value: u64) {
+        self.slots_mut()[info.offset / 8] = value;
+    }
+
+    /// Read the register called `name`.
+    pub fn get(&self, name: &str) -> FdbResult<u64> {
+        let info =
+            register_by_name(name).ok_or_else(|| FdbError::UnknownRegister(name.to_string()))?;
+        Ok(self.read(info))
+    }
+
+    /// Overwrite the register called `name` in this snapshot.
+    pub fn set(&mut self, name: &str, value: u64) -> FdbResult<()> {
+        let info =
+            register_by_name(name).ok_or_else(|| FdbError::UnknownRegister(name.to_string()))?;
+        self.write(info, value);
+        Ok(())
+    }
+
+    // Every field of `user_regs_struct` is a u64, so it can be viewed as an
+    // array indexed by `offset / 8`.
+    fn slots(&self) -> &[u64; USER_REGS_SLOTS] {
+        // SAFETY: `user_regs_struct` is `repr(C)` and made solely of u64s.
+        unsafe { &*(&self.regs as *const user_regs_struct as *const [u64; USER_REGS_SLOTS]) }
+    }
+
+    fn slots_mut(&mut self) -> &mut [u64; USER_REGS_SLOTS] {
+        // SAFETY: see `slots`.
+        unsafe { &mut *(&mut self.regs as *mut user_regs_struct as *mut [u64; USER_REGS_SLOTS]) }
+    }
+
+    /// Raw kernel structure, for callers that need to write registers back.
+    pub fn raw(&self) -> &user_regs_struct {
+        &self.regs
+    }
+
+    pub(crate) fn raw_mut(&mut self) -> &mut user_regs_struct {
+        &mut self.regs
+    }
+}
+
+impl std::fmt::Debug for Registers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut map = f.debug_map();
+        for (name, value) in self.general_purpose() {
+            map.entry(&name, &format_args!("{value:#x}"));
+        }
+        map.finish()
+    }
+}
+
+/// Serialized as a map from register name to value, like `Debug`.
+impl serde::Serialize for Registers {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+        let regs = self.general_purpose();
+        let mut map = serializer.serialize_map(Some(regs.len()))?;
+        for (name, value) in regs {
+            map.serialize_entry(name, &value)?;
+        }
+        map.end()
+    }
+}
+
+impl ProcessHandle {
+    /// Read the general-purpose registers with `PTRACE_GETREGS`.
+    pub fn get_registers(&self) -> FdbResult<Registers> {
+        self.ensure_alive()?;
+        let regs = ptrace::getregs(self.tid)?;
+        Ok(Registers { regs })
+    }
+
+    /// Write a full register snapshot back with `PTRACE_SETREGS`.
+    pub fn set_registers(&mut self, regs: &Registers) -> FdbResult<()> {
+        self.ensure_alive()?;
+        ptrace::setregs(self.tid, regs.regs)?;
+        Ok(())
+    }
+
+    /// Update a single register by name, e.g. `"rax"` or `"rip"`.
+    pub fn set_register(&mut self, name: &str, value: u64) -> FdbResult<()> {
+        let mut regs = self.get_registers()?;
+        regs.set(name, value)?;
+        self.set_registers(&regs)
+    }
+
+    /// The program counter of the current thread.
+    pub fn pc(&self) -> FdbResult<u64> {
+        Ok(self.get_registers()?.rip())
+    }
+
+    /// Move the current thread's program counter to `addr`.
+    pub fn set_pc(&mut self, addr: u64) -> FdbResult<()> {
+        let mut regs = self.get_registers()?;
+        regs.raw_mut().rip = addr;
+        self.set_registers(&regs)
+    }
+
+    /// The stack pointer of the current thread.
+    pub fn sp(&self) -> FdbResult<u64> {
+        Ok(self.get_registers()?.rsp())
+    }
+
+    /// The frame pointer (`rbp`) of the current thread, meaningful only in
+    /// code that keeps one.
+    pub fn fp(&self) -> FdbResult<u64> {
+        Ok(self.get_registers()?.rbp())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_rflags_bits() {
+        assert!(decode_rflags(0).is_empty());
+        assert_eq!(decode_rflags(0x246), ["PF", "ZF", "IF"]);
+        assert_eq!(decode_rflags(0x8c5), ["CF", "PF", "ZF", "SF", "OF"]);
+        // Reserved bit 1 is always set and never named.
+        assert_eq!(decode_rflags(0x2), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn formats_rflags_like_gdb() {
+        assert_eq!(format_rflags(0x246), "[ PF ZF IF ]");
+        assert_eq!(format_rflags(0x2), "[ ]");
+    }
+}
@@ -0,0 +1,281 @@
+//! GDB-style per-signal handling policy.
+
+use std::fmt;
+use std::sync::atomic::{AtomicI32, Ordering};
+
+use nix::libc;
+use nix::sys::ptrace;
+use nix::sys::signal::{self, Signal};
+use nix::unistd::Pid;
+
+use crate::errors::{FdbError, FdbResult};
+use crate::process::ProcessHandle;
+
+/// Process an interrupt was requested for and whose stop has not been seen
+/// yet, or 0. Static so that a signal handler can request one.
+static INTERRUPT_PENDING: AtomicI32 = AtomicI32::new(0);
+
+/// Stop `pid` as Ctrl-C would, by sending it SIGSTOP; the stop is
+/// reported as [`StopCause::Interrupted`](crate::StopCause::Interrupted).
+///
+/// Only async-signal-safe calls are made, so a SIGINT handler may call
+/// this.
+pub fn request_interrupt(pid: Pid) {
+    INTERRUPT_PENDING.store(pid.as_raw(), Ordering::SeqCst);
+    let _ = signal::kill(pid, Signal::SIGSTOP);
+}
+
+/// Whether a SIGSTOP of `pid` is the interrupt requested for it, which
+/// is then no longer pending.
+pub(crate) fn take_interrupt(pid: Pid) -> bool {
+    INTERRUPT_PENDING
+        .compare_exchange(pid.as_raw(), 0, Ordering::SeqCst, Ordering::SeqCst)
+        .is_ok()
+}
+
+/// `si_code` values any signal can have, for signals sent by a process
+/// rather than raised by the kernel.
+const SI_CODES: &[(i32, &str)] = &[
+    (libc::SI_USER, "SI_USER"),
+    (libc::SI_KERNEL, "SI_KERNEL"),
+    (libc::SI_QUEUE, "SI_QUEUE"),
+    (libc::SI_TIMER, "SI_TIMER"),
+    (libc::SI_MESGQ, "SI_MESGQ"),
+    (libc::SI_ASYNCIO, "SI_ASYNCIO"),
+    (libc::SI_SIGIO, "SI_SIGIO"),
+    (libc::SI_TKILL, "SI_TKILL"),
+];
+
+// The libc crate has no SEGV_, ILL_ or FPE_ constants for Linux; these are
+// the kernel's values from <asm-generic/siginfo.h>.
+const SEGV_CODES: &[(i32, &str)] = &[
+    (1, "SEGV_MAPERR"),
+    (2, "SEGV_ACCERR"),
+    (3, "SEGV_BNDERR"),
+    (4, "SEGV_PKUERR"),
+];
+
+const ILL_CODES: &[(i32, &str)] = &[
+    (1, "ILL_ILLOPC"),
+    (2, "ILL_ILLOPN"),
+    (3, "ILL_ILLADR"),
+    (4, "ILL_ILLTRP"),
+    (5, "ILL_PRVOPC"),
+    (6, "ILL_PRVREG"),
+    (7, "ILL_COPROC"),
+    (8, "ILL_BADSTK"),
+];
+
+const FPE_CODES: &[(i32, &str)] = &[
+    (1, "FPE_INTDIV"),
+    (2, "FPE_INTOVF"),
+    (3, "FPE_FLTDIV"),
+    (4, "FPE_FLTOVF"),
+    (5, "FPE_FLTUND"),
+    (6, "FPE_FLTRES"),
+    (7, "FPE_FLTINV"),
+    (8, "FPE_FLTSUB"),
+];
+
+const BUS_CODES: &[(i32, &str)] = &[
+    (libc::BUS_ADRALN, "BUS_ADRALN"),
+    (libc::BUS_ADRERR, "BUS_ADRERR"),
+    (libc::BUS_OBJERR, "BUS_OBJERR"),
+    (libc::BUS_MCEERR_AR, "BUS_MCEERR_AR"),
+    (libc::BUS_MCEERR_AO, "BUS_MCEERR_AO"),
+];
+
+const TRAP_CODES: &[(i32, &str)] = &[
+    (libc::TRAP_BRKPT, "TRAP_BRKPT"),
+    (libc::TRAP_TRACE, "TRAP_TRACE"),
+    (libc::TRAP_BRANCH, "TRAP_BRANCH"),
+    (libc::TRAP_HWBKPT, "TRAP_HWBKPT"),
+    (libc::TRAP_UNK, "TRAP_UNK"),
+];
+
+const CLD_CODES: &[(i32, &str)] = &[
+    (libc::CLD_EXITED, "CLD_EXITED"),
+    (libc::CLD_KILLED, "CLD_KILLED"),
+    (libc::CLD_DUMPED, "CLD_DUMPED"),
+    (libc::CLD_TRAPPED, "CLD_TRAPPED"),
+    (libc::CLD_STOPPED, "CLD_STOPPED"),
+    (libc::CLD_CONTINUED, "CLD_CONTINUED"),
+];
+
+/// The fields of a `siginfo_t` worth showing, read with
+/// `PTRACE_GETSIGINFO`. Displays as e.g. `SEGV_MAPERR, address 0x0`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct SigInfo {
+    #[serde(serialize_with = "crate::serialize_signal")]
+    pub signal: Signal,
+    /// `si_code`: how the signal came about
+    pub code: i32,
+    /// Address that faulted, for SIGSEGV, SIGBUS, SIGILL and SIGFPE
+    pub addr: Option<u64>,
+    /// Process that sent the signal, or for SIGCHLD the child whose state
+    /// changed
+    pub pid: Option<i32>,
+    /// For SIGCHLD, the child's exit code or the signal that stopped or
+    /// killed it
+    pub status: Option<i32>,
+}
+
+impl SigInfo {
+    fn from_raw(info: &libc::siginfo_t) -> FdbResult<Self> {
+        let signal = Signal::try_from(info.si_signo)?;
+        let code = info.si_code;
+        let faults = matches!(
+            signal,
+            Signal::SIGSEGV | Signal::SIGBUS | Signal::SIGILL | Signal::SIGFPE
+        );
+        let sent = matches!(code, libc::SI_USER | libc::SI_QUEUE | libc::SI_TKILL);
+        let child = signal == Signal::SIGCHLD && code > 0;
+        // SAFETY: each union field is read only for the codes that set it.
+        unsafe {
+            Ok(SigInfo {
+                signal,
+                code,
+                addr: (faults && code > 0).then(|| info.si_addr() as u64),
+                pid: (sent || child).then(|| info.si_pid()),
+                status: child.then(|| info.si_status()),
+            })
+        }
+    }
+
+    /// Name of `code`, such as `SEGV_MAPERR`, if it is one we know.
+    pub fn code_name(&self) -> Option<&'static str> {
+        let table = match self.signal {
+            _ if self.code <= 0 || self.code == libc::SI_KERNEL => SI_CODES,
+            Signal::SIGSEGV => SEGV_CODES,
+            Signal::SIGILL => ILL_CODES,
+            Signal::SIGFPE => FPE_CODES,
+            Signal::SIGBUS => BUS_CODES,
+            Signal::SIGTRAP => TRAP_CODES,
+            Signal::SIGCHLD => CLD_CODES,
+            _ => return None,
+        };
+        table
+            .iter()
+            .find(|(code, _)| *code == self.code)
+            .map(|(_, name)| *name)
+    }
+}
+
+impl fmt::Display for SigInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.code_name() {
+            Some(name) => write!(f, "{name}")?,
+            None => write!(f, "code {}", self.code)?,
+        }
+        if let Some(addr) = self.addr {
+            write!(f, ", address {addr:#x}")?;
+        }
+        match (self.signal, self.pid, self.status) {
+            (Signal::SIGCHLD, Some(pid), Some(status)) => {
+                write!(f, ", child {pid}, status {status}")
+            }
+            (_, Some(pid), _) => write!(f, ", sent by {pid}"),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// How the debugger reacts when the inferior receives a signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignalPolicy {
+    /// Return control to the user.
+    pub stop: bool,
+    /// Report the signal even when not stopping.
+    pub print: bool,
+    /// Deliver the signal to the inferior when it resumes.
+    pub pass: bool,
+}
+
+impl SignalPolicy {
+    /// Default policy for `sig`, following GDB's defaults.
+    pub fn default_for(sig: Signal) -> Self {
+        match sig {
+            // Used by the debugger itself; never forwarded.
+            Signal::SIGTRAP | Signal::SIGINT | Signal::SIGSTOP => SignalPolicy {
+                stop: true,
+                print: true,
+                pass: false,
+            },
+            // Routine signals that would make stepping noisy.
+            Signal::SIGALRM
+            | Signal::SIGCHLD
+            | Signal::SIGURG
+            | Signal::SIGWINCH
+            | Signal::SIGPROF
+            | Signal::SIGIO => SignalPolicy {
+                stop: false,
+                print: false,
+                pass: true,
+            },
+            _ => SignalPolicy {
+                stop: true,
+                print: true,
+                pass: true,
+            },
+        }
+    }
+}
+
+impl ProcessHandle {
+    /// Configure how `sig` is handled. SIGTRAP drives breakpoints and
+    /// stepping, so it must always stop and is never passed.
+    pub fn set_signal_policy(
+        &mut self,
+        sig: Signal,
+        stop: bool,
+        print: bool,
+        pass: bool,
+    ) -> FdbResult<()> {
+        if sig == Signal::SIGTRAP && (!stop || pass) {
+            return Err(FdbError::ProtectedSignal(sig));
+        }
+        self.signal_policies
+            .insert(sig, SignalPolicy { stop, print, pass });
+        Ok(())
+    }
+
+    /// Current policy for `sig`.
+    pub fn signal_policy(&self, sig: Signal) -> SignalPolicy {
+        self.signal_policies
+            .get(&sig)
+            .copied()
+            .unwrap_or_else(|| SignalPolicy::default_for(sig))
+    }
+
+    /// Send `sig` to the process with `kill`. While the process is stopped
+    /// the signal stays pending and is handled under its policy once the
+    /// process resumes.
+    pub fn send_signal(&mut self, sig: Signal) -> FdbResult<()> {
+        self.ensure_alive()?;
+        signal::kill(self.pid(), sig)?;
+        Ok(())
+    }
+
+    /// Stop the running process as Ctrl-C would, by sending it SIGSTOP. The
+    /// next wait reports the stop as
+    /// [`StopCause::Interrupted`](crate::StopCause::Interrupted).
+    pub fn interrupt(&mut self) -> FdbResult<()> {
+        self.ensure_alive()?;
+        self.interrupting = true;
+        signal::kill(self.pid(), Signal::SIGSTOP)?;
+        Ok(())
+    }
+
+    /// What the kernel recorded about the signal the current thread is
+    /// stopped by.
+    pub fn siginfo(&self) -> FdbResult<SigInfo> {
+        self.ensure_alive()?;
+        SigInfo::from_raw(&ptrace::getsiginfo(self.tid)?)
+    }
+
+    /// Signals that were passed through without stopping but should be
+    /// reported, oldest first. Draining clears the list.
+    pub fn take_signal_notices(&mut self) -> Vec<Signal> {
+        std::mem::take(&mut self.signal_notices)
+    }
+}
@@ -0,0 +1,313 @@
+//! Function symbols from the ELF symbol tables of the inferior's executable
+//! and shared libraries.
+
+use std::cell::{OnceCell, RefCell};
+use std::collections::HashMap;
+use std::path::Path;
+
+use nix::unistd::Pid;
+use object::{
+    Object, ObjectSection, ObjectSegment, ObjectSymbol, ObjectSymbolTable, RelocationTarget,
+    SymbolKind,
+};
+
+use crate::errors::FdbResult;
+use crate::maps::shared_objects;
+use crate::process::ProcessHandle;
+
+/// A function symbol at its runtime (relocated) address.
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub address: u64,
+    /// Size in bytes; zero when the symbol table does not record one.
+    pub size: u64,
+}
+
+/// Function symbols of one object file, sorted by address.
+#[derive(Debug, Default)]
+pub struct SymbolTable {
+    symbols: Vec<Symbol>,
+    by_name: HashMap<String, usize>,
+}
+
+impl SymbolTable {
+    /// Read the function symbols of `path`, shifting each by `bias`.
+    ///
+    /// Falls back to `.dynsym` when the binary has been stripped of `.symtab`.
+    pub fn load(path: &Path, bias: u64) -> FdbResult<Self> {
+        let data = std::fs::read(path)?;
+        let object = object::File::parse(&*data)?;
+        Ok(Self::from_object(&object, bias))
+    }
+
+    /// Read the function symbols of `path`, mapped with its first segment
+    /// at `base`.
+    pub fn load_mapped(path: &Path, base: u64) -> FdbResult<Self> {
+        const PAGE_MASK: u64 = !0xfff;
+        let data = std::fs::read(path)?;
+        let object = object::File::parse(&*data)?;
+        let first = object
+            .segments()
+            .map(|seg| seg.address())
+            .min()
+            .unwrap_or(0);
+        Ok(Self::from_object(
+            &object,
+            base.wrapping_sub(first & PAGE_MASK),
+        ))
+    }
+
+    fn from_object(object: &object::File, bias: u64) -> Self {
+        let mut symbols: Vec<Symbol> = object
+            .symbols()
+            .chain(object.dynamic_symbols())
+            .filter(|sym| sym.kind() == SymbolKind::Text && sym.is_definition())
+            .filter_map(|sym| {
+                Some(Symbol {
+                    name: sym.name().ok()?.to_string(),
+                    address: sym.address().wrapping_add(bias),
+                    size: sym.size(),
+                })
+            })
+            .collect();
+        symbols.extend(plt_symbols(object, bias));
+        symbols.sort_by(|a, b| (a.address, &a.name).cmp(&(b.address, &b.name)));
+        symbols.dedup_by(|a, b| a.address == b.address && a.name == b.name);
+
+        let mut by_name = HashMap::new();
+        for (idx, sym) in symbols.iter().enumerate() {
+            by_name.entry(sym.name.clone()).or_insert(idx);
+        }
+        SymbolTable { symbols, by_name }
+    }
+
+    /// Symbol called `name`.
+    pub fn lookup(&self, name: &str) -> Option<&Symbol> {
+        self.by_name.get(name).map(|&idx| &self.symbols[idx])
+    }
+
+    /// Names of every symbol, in address order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.symbols.iter().map(|sym| sym.name.as_str())
+    }
+
+    /// Function containing `addr`. Symbols without a size only match their
+    /// first byte.
+    pub fn containing(&self, addr: u64) -> Option<&Symbol> {
+        let idx = self.symbols.partition_point(|sym| sym.address <= addr);
+        self.symbols[..idx]
+            .iter()
+            .rev()
+            .take_while(|sym| sym.address == self.symbols[idx - 1].address)
+            .find(|sym| addr < sym.address + sym.size.max(1))
+    }
+}
+
+/// Synthetic `name@plt` symbols for the PLT stubs, which the symbol tables
+/// leave unnamed.
+///
+/// Each stub jumps through a GOT slot with `jmp *disp(%rip)`, and the
+/// dynamic relocation that fills the slot names the function.
+fn plt_symbols(object: &object::File, bias: u64) -> Vec<Symbol> {
+    let (Some(relocations), Some(dynsym)) =
+        (object.dynamic_relocations(), object.dynamic_symbol_table())
+    else {
+        return Vec::new();
+    };
+    let slots: HashMap<u64, String> = relocations
+        .filter_map(|(slot, reloc)| {
+            let RelocationTarget::Symbol(idx) = reloc.target() else {
+                return None;
+            };
+            let name = dynsym.symbol_by_index(idx).ok()?.name().ok()?;
+            (!name.is_empty()).then(|| (slot, name.to_string()))
+        })
+        .collect();
+
+    let mut symbols = Vec::new();
+    for section in object.sections() {
+        let entry_size = match section.name() {
+            Ok(".plt" | ".plt.sec") => 16,
+            Ok(".plt.got") => 8,
+            _ => continue,
+        };
+        let Ok(code) = section.data() else {
+            continue;
+        };
+        for (idx, entry) in code.chunks(entry_size).enumerate() {
+            let entry_addr = section.address() + (idx * entry_size) as u64;
+            // The jump may follow `endbr64` or a `bnd` prefix.
+            let Some(pos) = entry.windows(2).position(|w| w == [0xff, 0x25]) else {
+                continue;
+            };
+            let Some(disp) = entry.get(pos + 2..pos + 6) else {
+                continue;
+            };
+            let disp = i32::from_le_bytes(disp.try_into().expect("four bytes"));
+            let slot = (entry_addr + pos as u64 + 6).wrapping_add_signed(disp.into());
+            if let Some(name) = slots.get(&slot) {
+                symbols.push(Symbol {
+                    name: format!("{name}@plt"),
+                    address: entry_addr.wrapping_add(bias),
+                    size: entry_size as u64,
+                });
+            }
+        }
+    }
+    symbols
+}
+
+/// A shared library whose symbols are read the first time it is searched.
+#[derive(Debug)]
+struct Library {
+    path: String,
+    base: u64,
+    end: u64,
+    table: OnceCell<SymbolTable>,
+}
+
+impl Library {
+    fn table(&self) -> &SymbolTable {
+        self.table.get_or_init(|| {
+            SymbolTable::load_mapped(Path::new(&self.path), self.base)
+                .inspect_err(|e| log::debug!("no symbols for {}: {e}", self.path))
+                .unwrap_or_default()
+        })
+    }
+}
+
+/// Symbols of the executable and of every shared library it has loaded.
+///
+/// Libraries are found from `/proc/<pid>/maps` when a query misses, so ones
+/// loaded since the last query are picked up. The executable's symbols win
+/// over a library's of the same name, then libraries in address order.
+#[derive(Debug)]
+pub struct SymbolDatabase {
+    pid: Pid,
+    load_bias: u64,
+    executable: OnceCell<SymbolTable>,
+    libraries: RefCell<Vec<Library>>,
+}
+
+impl SymbolDatabase {
+    pub(crate) fn new(pid: Pid, load_bias: u64) -> Self {
+        SymbolDatabase {
+            pid,
+            load_bias,
+            executable: OnceCell::new(),
+            libraries: RefCell::new(Vec::new()),
+        }
+    }
+
+    fn executable(&self) -> &SymbolTable {
+        self.executable.get_or_init(|| {
+            let exe = format!("/proc/{}/exe", self.pid);
+            SymbolTable::load(Path::new(&exe), self.load_bias)
+                .inspect_err(|e| log::debug!("no symbols for {exe}: {e}"))
+                .unwrap_or_default()
+        })
+    }
+
+    /// Symbol called `name`.
+    pub fn lookup(&self, name: &str) -> Option<Symbol> {
+        if let Some(sym) = self.executable().lookup(name) {
+            return Some(sym.clone());
+        }
+        let search = || {
+            self.libraries
+                .borrow()
+                .iter()
+                .find_map(|lib| lib.table().lookup(name).cloned())
+        };
+        search().or_else(|| {
+            self.refresh();
+            search()
+        })
+    }
+
+    /// Function containing `addr`.
+    pub fn containing(&self, addr: u64) -> Option<Symbol> {
+        if let Some(sym) = self.executable().containing(addr) {
+            return Some(sym.clone());
+        }
+        let search = || {
+            let libraries = self.libraries.borrow();
+            let lib = libraries
+                .iter()
+                .find(|lib| (lib.base..lib.end).contains(&addr))?;
+            lib.table().containing(addr).cloned()
+        };
+        search().or_else(|| {
+            self.refresh();
+            search()
+        })
+    }
+
+    /// Names of the functions of the executable and of every library
+    /// mapped now, sorted and without duplicates.
+    pub fn names(&self) -> Vec<String> {
+        self.refresh();
+        let libraries = self.libraries.borrow();
+        let mut names: Vec<String> = self
+            .executable()
+            .names()
+            .chain(libraries.iter().flat_map(|lib| lib.table().names()))
+            .map(str::to_string)
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+
+    /// Whether the symbols of library `path` have been read.
+    pub fn is_loaded(&self, path: &str) -> bool {
+        self.libraries
+            .borrow()
+            .iter()
+            .any(|lib| lib.path == path && lib.table.get().is_some())
+    }
+
+    /// Bring the library list up to date, keeping the symbols already read
+    /// for libraries still mapped where they were.
+    fn refresh(&self) {
+        let mapped = match shared_objects(self.pid) {
+            Ok(mapped) => mapped,
+            Err(e) => {
+                log::debug!("cannot list shared libraries: {e}");
+                return;
+            }
+        };
+        let mut libraries = self.libraries.borrow_mut();
+        let mut known = std::mem::take(&mut *libraries);
+        for lib in mapped {
+            let kept = known
+                .iter()
+                .position(|old| old.path == lib.path && old.base == lib.base)
+                .map(|idx| known.swap_remove(idx));
+            libraries.push(kept.unwrap_or(Library {
+                path: lib.path,
+                base: lib.base,
+                end: lib.end,
+                table: OnceCell::new(),
+            }));
+        }
+    }
+}
+
+impl ProcessHandle {
+    /// Runtime address of the function called `name`.
+    pub fn resolve_symbol(&self, name: &str) -> Option<u64> {
+        self.symbols.lookup(name).map(|sym| sym.address)
+    }
+
+    /// Names of the functions the program can refer to, for completion.
+    pub fn function_names(&self) -> Vec<String> {
+        self.symbols.names()
+    }
+
+    /// Function containing runtime address `addr`.
+    pub fn symbol_at(&self, addr: u64) -> Option<Symbol> {
+        self.symbols.containing(addr)
+    }
+}
@@ -0,0 +1,271 @@
+//! Readable system call arguments: file descriptors, flag names and the
+//! strings pointers lead to, in the style of strace.
+
+use nix::libc;
+
+use crate::process::ProcessHandle;
+use crate::syscalls::{display_name, format_call, syscall_name};
+
+/// Longest string or buffer shown in full; longer ones end in `...`.
+const STRING_LIMIT: usize = 32;
+
+/// Most `argv` entries shown before the rest are elided.
+const ARGV_LIMIT: usize = 32;
+
+/// How one argument is shown.
+#[derive(Debug, Clone, Copy)]
+enum Arg {
+    /// A file descriptor, as a signed number.
+    Fd,
+    /// A directory descriptor, which may be `AT_FDCWD`.
+    DirFd,
+    /// A pointer to a NUL-terminated path.
+    Path,
+    /// A pointer to bytes being written; their count is argument `len`.
+    Buffer {
+        len: usize,
+    },
+    /// A pointer shown as an address, or `NULL`.
+    Pointer,
+    /// A size or offset, in decimal.
+    Size,
+    OpenFlags,
+    /// The mode of `open`; present only when the flags create a file.
+    Mode,
+    Prot,
+    MapFlags,
+    /// A NULL-terminated array of string pointers.
+    Argv,
+}
+
+/// Arguments of the system calls that are decoded, by name.
+const DECODERS: &[(&str, &[Arg])] = {
+    use Arg::*;
+    &[
+        ("read", &[Fd, Pointer, Size]),
+        ("write", &[Fd, Buffer { len: 2 }, Size]),
+        ("open", &[Path, OpenFlags, Mode]),
+        ("close", &[Fd]),
+        ("mmap", &[Pointer, Size, Prot, MapFlags, Fd, Size]),
+        ("mprotect", &[Pointer, Size, Prot]),
+        ("munmap", &[Pointer, Size]),
+        ("execve", &[Path, Argv, Pointer]),
+        ("openat", &[DirFd, Path, OpenFlags, Mode]),
+    ]
+};
+
+const OPEN_FLAGS: &[(u64, &str)] = &[
+    // O_TMPFILE and O_SYNC include other flags' bits, so they go first.
+    (libc::O_TMPFILE as u64, "O_TMPFILE"),
+    (libc::O_SYNC as u64, "O_SYNC"),
+    (libc::O_CREAT as u64, "O_CREAT"),
+    (libc::O_EXCL as u64, "O_EXCL"),
+    (libc::O_NOCTTY as u64, "O_NOCTTY"),
+    (libc::O_TRUNC as u64, "O_TRUNC"),
+    (libc::O_APPEND as u64, "O_APPEND"),
+    (libc::O_NONBLOCK as u64, "O_NONBLOCK"),
+    (libc::O_DSYNC as u64, "O_DSYNC"),
+    (libc::O_ASYNC as u64, "O_ASYNC"),
+    (libc::O_DIRECT as u64, "O_DIRECT"),
+    (libc::O_DIRECTORY as u64, "O_DIRECTORY"),
+    (libc::O_NOFOLLOW as u64, "O_NOFOLLOW"),
+    (libc::O_NOATIME as u64, "O_NOATIME"),
+    (libc::O_CLOEXEC as u64, "O_CLOEXEC"),
+    (libc::O_PATH as u64, "O_PATH"),
+];
+
+const PROT_FLAGS: &[(u64, &str)] = &[
+    (libc::PROT_READ as u64, "PROT_READ"),
+    (libc::PROT_WRITE as u64, "PROT_WRITE"),
+    (libc::PROT_EXEC as u64, "PROT_EXEC"),
+    (libc::PROT_GROWSDOWN as u64, "PROT_GROWSDOWN"),
+    (libc::PROT_GROWSUP as u64, "PROT_GROWSUP"),
+];
+
+const MAP_FLAGS: &[(u64, &str)] = &[
+    // Both sharing bits together mean something else again.
+    (libc::MAP_SHARED_VALIDATE as u64, "MAP_SHARED_VALIDATE"),
+    (libc::MAP_SHARED as u64, "MAP_SHARED"),
+    (libc::MAP_PRIVATE as u64, "MAP_PRIVATE"),
+    (libc::MAP_FIXED as u64, "MAP_FIXED"),
+    (libc::MAP_ANONYMOUS as u64, "MAP_ANONYMOUS"),
+    (libc::MAP_32BIT as u64, "MAP_32BIT"),
+    (libc::MAP_GROWSDOWN as u64, "MAP_GROWSDOWN"),
+    (libc::MAP_DENYWRITE as u64, "MAP_DENYWRITE"),
+    (libc::MAP_EXECUTABLE as u64, "MAP_EXECUTABLE"),
+    (libc::MAP_LOCKED as u64, "MAP_LOCKED"),
+    (libc::MAP_NORESERVE as u64, "MAP_NORESERVE"),
+    (libc::MAP_POPULATE as u64, "MAP_POPULATE"),
+    (libc::MAP_NONBLOCK as u64, "MAP_NONBLOCK"),
+    (libc::MAP_STACK as u64, "MAP_STACK"),
+    (libc::MAP_HUGETLB as u64, "MAP_HUGETLB"),
+    (libc::MAP_SYNC as u64, "MAP_SYNC"),
+    (libc::MAP_FIXED_NOREPLACE as u64, "MAP_FIXED_NOREPLACE"),
+];
+
+impl ProcessHandle {
+    /// Render a call as `name(arg, ...)`, decoding the arguments of common
+    /// calls and showing those of the rest in hex.
+    ///
+    /// Pointers are followed into the process, so this must be called at
+    /// the call's entry, while they still point at its inputs.
+    pub fn decode_syscall(&self, number: u64, args: &[u64; 6]) -> String {
+        let Some(kinds) = syscall_name(number).and_then(|name| {
+            DECODERS
+                .iter()
+                .find(|(decoded, _)| *decoded == name)
+                .map(|(_, kinds)| *kinds)
+        }) else {
+            return format_call(number, args);
+        };
+        let mut shown = Vec::new();
+        for (i, kind) in kinds.iter().enumerate() {
+            let arg = args[i];
+            shown.push(match kind {
+                Arg::Fd => (arg as i32).to_string(),
+                Arg::DirFd if arg as i32 == libc::AT_FDCWD => "AT_FDCWD".to_string(),
+                Arg::DirFd => (arg as i32).to_string(),
+                Arg::Path => self.quoted_string(arg),
+                Arg::Buffer { len } => self.quoted_buffer(arg, args[*len] as usize),
+                Arg::Pointer => pointer(arg),
+                Arg::Size => arg.to_string(),
+                Arg::OpenFlags => open_flags(arg),
+                Arg::Mode => {
+                    let open = args[i - 1];
+                    let tmpfile = libc::O_TMPFILE as u64;
+                    if open & libc::O_CREAT as u64 == 0 && open & tmpfile != tmpfile {
+                        continue;
+                    }
+                    format!("0{arg:o}")
+                }
+                Arg::Prot => flags(arg, PROT_FLAGS).unwrap_or_else(|| "PROT_NONE".to_string()),
+                Arg::MapFlags => flags(arg, MAP_FLAGS).unwrap_or_else(|| "0".to_string()),
+                Arg::Argv => self.string_array(arg),
+            });
+        }
+        format!("{}({})", display_name(number), shown.join(", "))
+    }
+
+    /// The string at `addr` in quotes, or the bare address if it cannot
+    /// be read.
+    fn quoted_string(&self, addr: u64) -> String {
+        if addr == 0 {
+            return "NULL".to_string();
+        }
+        match self.read_c_string(addr, STRING_LIMIT + 1) {
+            Ok(s) if s.len() > STRING_LIMIT => {
+                let shown: String = s.chars().take(STRING_LIMIT).collect();
+                format!("\"{}\"...", shown.escape_debug())
+            }
+            Ok(s) => format!("\"{}\"", s.escape_debug()),
+            Err(_) => format!("{addr:#x}"),
+        }
+    }
+
+    /// The first bytes of the `len` at `addr` in quotes.
+    fn quoted_buffer(&self, addr: u64, len: usize) -> String {
+        match self.read_memory(addr, len.min(STRING_LIMIT)) {
+            Ok(bytes) if len > STRING_LIMIT => format!("\"{}\"...", bytes.escape_ascii()),
+            Ok(bytes) => format!("\"{}\"", bytes.escape_ascii()),
+            Err(_) => pointer(addr),
+        }
+    }
+
+    /// `["a", "b"]` for an `argv` array at `addr`.
+    fn string_array(&self, addr: u64) -> String {
+        let mut strings = Vec::new();
+        for i in 0..=ARGV_LIMIT {
+            let Ok(bytes) = self.read_memory(addr + 8 * i as u64, 8) else {
+                return pointer(addr);
+            };
+            let ptr = u64::from_le_bytes(bytes.try_into().unwrap());
+            if ptr == 0 {
+                break;
+            }
+            if i == ARGV_LIMIT {
+                strings.push("...".to_string());
+                break;
+            }
+            strings.push(self.quoted_string(ptr));
+        }
+        format!("[{}]", strings.join(", "))
+    }
+}
+
+fn pointer(addr: u64) -> String {
+    match addr {
+        0 => "NULL".to_string(),
+        addr => format!("{addr:#x}"),
+    }
+}
+
+/// Flags of `open`: the access mode, then any others.
+fn open_flags(value: u64) -> String {
+    let access = match value & libc::O_ACCMODE as u64 {
+        0 => "O_RDONLY".to_string(),
+        1 => "O_WRONLY".to_string(),
+        2 => "O_RDWR".to_string(),
+        mode => format!("{mode:#x}"),
+    };
+    match flags(value & !(libc::O_ACCMODE as u64), OPEN_FLAGS) {
+        Some(rest) => format!("{access}|{rest}"),
+        None => access,
+    }
+}
+
+/// The names of the flags set in `value`, joined with `|`, and any bits
+/// left over in hex; `None` if no bit is set.
+fn flags(mut value: u64, table: &[(u64, &str)]) -> Option<String> {
+    let mut names = Vec::new();
+    for &(flag, name) in table {
+        if flag != 0 && value & flag == flag {
+            names.push(name.to_string());
+            value &= !flag;
+        }
+    }
+    if value != 0 {
+        names.push(format!("{value:#x}"));
+    }
+    (!names.is_empty()).then(|| names.join("|"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn open_flags_name_the_access_mode_and_the_rest() {
+        assert_eq!(open_flags(0), "O_RDONLY");
+        assert_eq!(
+            open_flags((libc::O_RDONLY | libc::O_CLOEXEC) as u64),
+            "O_RDONLY|O_CLOEXEC"
+        );
+        assert_eq!(
+            open_flags((libc::O_WRONLY | libc::O_CREAT | libc::O_TRUNC) as u64),
+            "O_WRONLY|O_CREAT|O_TRUNC"
+        );
+        // O_SYNC is not also shown as O_DSYNC.
+        assert_eq!(
+            open_flags((libc::O_RDWR | libc::O_SYNC) as u64),
+            "O_RDWR|O_SYNC"
+        );
+        assert_eq!(open_flags(1 << 40), "O_RDONLY|0x10000000000");
+    }
+
+    #[test]
+    fn mmap_flags_and_prot_are_named() {
+        assert_eq!(
+            flags((libc::MAP_PRIVATE | libc::MAP_ANONYMOUS) as u64, MAP_FLAGS).unwrap(),
+            "MAP_PRIVATE|MAP_ANONYMOUS"
+        );
+        assert_eq!(
+            flags(libc::MAP_SHARED_VALIDATE as u64, MAP_FLAGS).unwrap(),
+            "MAP_SHARED_VALIDATE"
+        );
+        assert_eq!(
+            flags((libc::PROT_READ | libc::PROT_EXEC) as u64, PROT_FLAGS).unwrap(),
+            "PROT_READ|PROT_EXEC"
+        );
+        assert_eq!(flags(0, PROT_FLAGS), None);
+    }
+}
@@ -0,0 +1,264 @@
+//! Threads of the inferior and the all-stop wait loop.
+//!
+//! Every thread (LWP) is traced. When one of them reports a stop, the
+//! others are halted too so the whole process is stopped at the prompt.
+//! Stops those threads reported on the way are held back and delivered by
+//! later waits instead of being lost.
+
+use std::cell::RefCell;
+
+use nix::errno::Errno;
+use nix::libc;
+use nix::sys::ptrace;
+use nix::sys::signal::Signal;
+use nix::sys::wait::{WaitPidFlag, WaitStatus, waitpid};
+use nix::unistd::Pid;
+
+use crate::errors::{FdbError, FdbResult};
+use crate::process::{ProcessHandle, TRACE_OPTIONS};
+
+/// Whether a thread is executing or held by the debugger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThreadState {
+    Running,
+    Stopped,
+}
+
+/// One traced thread of the inferior.
+#[derive(Debug, Clone)]
+pub struct ThreadInfo {
+    /// Kernel thread id; equals the process id for the main thread.
+    pub tid: Pid,
+    pub state: ThreadState,
+    /// Set between a syscall's entry and exit stops.
+    pub(crate) in_syscall: bool,
+    /// A SIGSTOP sent to halt this thread has not been seen yet.
+    pub(crate) stop_requested: bool,
+}
+
+impl ThreadInfo {
+    pub(crate) fn new(tid: Pid, state: ThreadState) -> Self {
+        ThreadInfo {
+            tid,
+            state,
+            in_syscall: false,
+            stop_requested: false,
+        }
+    }
+}
+
+thread_local! {
+    /// Wait statuses collected by one inferior's wait that belong to a
+    /// different tracee of this tracer thread.
+    static PARKED: RefCell<Vec<WaitStatus>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Remove and return the first parked status for which `wanted` holds.
+fn take_parked(wanted: impl Fn(Pid) -> bool) -> Option<WaitStatus> {
+    PARKED.with_borrow_mut(|parked| {
+        let idx = parked
+            .iter()
+            .position(|status| status.pid().is_some_and(&wanted))?;
+        Some(parked.remove(idx))
+    })
+}
+
+/// Wait for the next status of `tid` alone.
+pub(crate) fn wait_thread(tid: Pid) -> FdbResult<WaitStatus> {
+    if let Some(status) = take_parked(|pid| pid == tid) {
+        return Ok(status);
+    }
+    Ok(waitpid(tid, Some(WaitPidFlag::__WALL))?)
+}
+
+/// Send `sig` to thread `tid` of process `pid`.
+fn tgkill(pid: Pid, tid: Pid, sig: Signal) -> FdbResult<()> {
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_tgkill,
+            pid.as_raw(),
+            tid.as_raw(),
+            sig as libc::c_int,
+        )
+    };
+    Errno::result(ret)?;
+    Ok(())
+}
+
+impl ProcessHandle {
+    /// Threads of the inferior in creation order.
+    pub fn threads(&self) -> &[ThreadInfo] {
+        &self.threads
+    }
+
+    /// Thread that register and memory operations act on. A stop selects
+    /// the thread that reported it.
+    pub fn current_thread(&self) -> Pid {
+        self.tid
+    }
+
+    /// Make thread `tid` the target of register reads, stepping and
+    /// backtraces until the next stop.
+    pub fn select_thread(&mut self, tid: i32) -> FdbResult<()> {
+        let tid = Pid::from_raw(tid);
+        // A thread that exited while the process ran is still in the list
+        // until its exit is reaped, so also check it is still there.
+        if !self.owns(tid) || ptrace::getregs(tid).is_err() {
+            return Err(FdbError::UnknownThread(tid.as_raw()));
+        }
+        self.tid = tid;
+        Ok(())
+    }
+
+    pub(crate) fn thread_mut(&mut self, tid: Pid) -> FdbResult<&mut ThreadInfo> {
+        self.threads
+            .iter_mut()
+            .find(|thread| thread.tid == tid)
+            .ok_or(FdbError::UnknownThread(tid.as_raw()))
+    }
+
+    fn owns(&self, tid: Pid) -> bool {
+        self.threads.iter().any(|thread| thread.tid == tid)
+    }
+
+    /// Trace the other threads of a process we just attached to.
+    pub(crate) fn attach_threads(&mut self) -> FdbResult<()> {
+        for entry in std::fs::read_dir(format!("/proc/{}/task", self.pid))? {
+            let Some(tid) = entry?.file_name().to_str().and_then(|s| s.parse().ok()) else {
+                continue;
+            };
+            let tid = Pid::from_raw(tid);
+            if self.owns(tid) {
+                continue;
+            }
+            // The thread may exit between listing and attaching.
+            if ptrace::attach(tid).is_err() {
+                continue;
+            }
+            wait_thread(tid)?;
+            ptrace::setoptions(tid, TRACE_OPTIONS)?;
+            self.threads
+                .push(ThreadInfo::new(tid, ThreadState::Stopped));
+        }
+        Ok(())
+    }
+
+    /// Start tracking a thread created by `clone`, after consuming the
+    /// SIGSTOP it starts with, and set it running like the rest.
+    pub(crate) fn add_thread(&mut self, tid: Pid) -> FdbResult<()> {
+        self.threads
+            .push(ThreadInfo::new(tid, ThreadState::Stopped));
+        // Debug registers are not inherited across clone.
+        self.copy_debug_registers(tid)?;
+        self.restart_thread(tid, None)
+    }
+
+    /// Next wait status of any of our threads.
+    ///
+    /// Held-back stops of resumed threads come first. Statuses for
+    /// tracees we do not know yet (another inferior, or a thread whose
+    /// clone event is still to come) are parked for whoever owns them.
+    pub(crate) fn next_status(&mut self) -> FdbResult<WaitStatus> {
+        loop {
+            // Without WNOHANG there is always a status.
+            if let Some(status) = self.poll_status(WaitPidFlag::empty())? {
+                return Ok(status);
+            }
+        }
+    }
+
+    /// Like [`ProcessHandle::next_status`], but with `WNOHANG` in `flags`
+    /// returns `None` instead of blocking when no thread has changed state.
+    pub(crate) fn poll_status(&mut self, flags: WaitPidFlag) -> FdbResult<Option<WaitStatus>> {
+        let resumed = |tid: Pid, threads: &[ThreadInfo]| {
+            threads
+                .iter()
+                .any(|thread| thread.tid == tid && thread.state == ThreadState::Running)
+        };
+        if let Some(idx) = self
+            .held_stops
+            .iter()
+            .position(|status| status.pid().is_some_and(|tid| resumed(tid, &self.threads)))
+        {
+            return Ok(Some(self.held_stops.remove(idx)));
+        }
+        if let Some(status) = take_parked(|tid| self.owns(tid)) {
+            return Ok(Some(status));
+        }
+        loop {
+            // __WNOTHREAD keeps us from reaping tracees of other tracer
+            // threads in the same program.
+            let flags = flags | WaitPidFlag::__WALL | WaitPidFlag::__WNOTHREAD;
+            let status = waitpid(None, Some(flags))?;
+            match status.pid() {
+                None => return Ok(None),
+                Some(tid) if self.owns(tid) => return Ok(Some(status)),
+                _ => PARKED.with_borrow_mut(|parked| parked.push(status)),
+            }
+        }
+    }
+
+    /// Whether `tid` is resumed by the kernel rather than by replaying a
+    /// held-back stop. Marks the thread running either way.
+    pub(crate) fn set_running(&mut self, tid: Pid) -> FdbResult<bool> {
+        self.thread_mut(tid)?.state = ThreadState::Running;
+        Ok(!self
+            .held_stops
+            .iter()
+            .any(|status| status.pid() == Some(tid)))
+    }
+
+    /// Forget a thread that has exited. If it was the current thread, the
+    /// main thread takes over.
+    pub(crate) fn remove_thread(&mut self, tid: Pid) {
+        self.threads.retain(|thread| thread.tid != tid);
+        if self.tid == tid {
+            self.tid = self.pid;
+        }
+    }
+
+    /// Halt every running thread.
+    ///
+    /// Each is sent SIGSTOP and waited for. A thread that reports something
+    /// else first keeps that stop held back for a later wait; its SIGSTOP
+    /// is discarded when it eventually arrives.
+    pub(crate) fn stop_all_threads(&mut self) -> FdbResult<()> {
+        let mut running = Vec::new();
+        for thread in &mut self.threads {
+            if thread.state != ThreadState::Running {
+                continue;
+            }
+            if self
+                .held_stops
+                .iter()
+                .any(|status| status.pid() == Some(thread.tid))
+            {
+                // Never actually resumed.
+                thread.state = ThreadState::Stopped;
+            } else if tgkill(self.pid, thread.tid, Signal::SIGSTOP).is_ok() {
+                thread.stop_requested = true;
+                running.push(thread.tid);
+            }
+        }
+        for tid in running {
+            match wait_thread(tid)? {
+                WaitStatus::Stopped(_, Signal::SIGSTOP) => {
+                    self.thread_mut(tid)?.stop_requested = false;
+                }
+                WaitStatus::Exited(..) | WaitStatus::Signaled(..) if tid != self.pid => {
+                    self.remove_thread(tid);
+                    continue;
+                }
+                // The whole process is gone; leave the exit for the next
+                // wait to report.
+                status @ (WaitStatus::Exited(..) | WaitStatus::Signaled(..)) => {
+                    self.held_stops.push(status);
+                    continue;
+                }
+                status => self.held_stops.push(status),
+            }
+            self.thread_mut(tid)?.state = ThreadState::Stopped;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,495 @@
+//! Call stack unwinding.
+//!
+//! Frames are unwound with the call frame information in `.eh_frame` or
+//! `.debug_frame` where the executable has it, and by following the saved
+//! frame-pointer chain everywhere else.
+
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+use gimli::{
+    BaseAddresses, CfaRule, DebugFrame, EhFrame, EndianSlice, LittleEndian, Register, RegisterRule,
+    UnwindContext, UnwindSection,
+};
+use object::{Object, ObjectSection};
+
+use crate::errors::{FdbError, FdbResult};
+use crate::process::ProcessHandle;
+use crate::registers::{Registers, register_by_dwarf};
+
+type Reader<'a> = EndianSlice<'a, LittleEndian>;
+
+/// Frames a backtrace walks before giving up, in case the chain is corrupt
+/// but still strictly increasing.
+pub const DEFAULT_BACKTRACE_LIMIT: usize = 512;
+
+/// DWARF column holding the return address on x86-64.
+const RETURN_ADDRESS: Register = Register(16);
+
+/// DWARF number of RBP.
+const RBP: Register = Register(6);
+
+/// One frame of the call stack; frame 0 is where the process stopped.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Frame {
+    /// Current instruction in frame 0, the return address in callers.
+    pub pc: u64,
+    /// Value of RBP for this frame.
+    pub frame_pointer: u64,
+    /// Function containing `pc`, when the symbol table knows it.
+    pub function: Option<String>,
+    /// Distance of `pc` from the start of `function`.
+    pub offset: u64,
+    /// Source file and line of `pc`, when line information covers it.
+    pub location: Option<(PathBuf, u32)>,
+    /// Parameters of `function` with their values, in declaration order.
+    pub arguments: Vec<(String, String)>,
+    /// Locals of `function` with their values; only filled in by
+    /// [`ProcessHandle::backtrace_full`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locals: Option<Vec<(String, String)>>,
+}
+
+/// Frames found by walking the call stack, innermost first.
+///
+/// A walk that ends before the outermost frame keeps the frames found so
+/// far along with the reason it stopped.
+#[derive(Debug)]
+pub struct Backtrace {
+    frames: Vec<Frame>,
+    error: Option<FdbError>,
+}
+
+impl Backtrace {
+    /// Why the walk stopped short of the outermost frame, if it did.
+    pub fn error(&self) -> Option<&FdbError> {
+        self.error.as_ref()
+    }
+
+    pub fn into_frames(self) -> Vec<Frame> {
+        self.frames
+    }
+}
+
+impl Deref for Backtrace {
+    type Target = [Frame];
+
+    fn deref(&self) -> &[Frame] {
+        &self.frames
+    }
+}
+
+/// Layout of the current frame on the stack, as `info frame` shows it.
+#[derive(Debug, Clone)]
+pub struct FrameInfo {
+    /// Current instruction.
+    pub pc: u64,
+    /// Function containing `pc`, when the symbol table knows it.
+    pub function: Option<String>,
+    /// Stack pointer; the frame occupies `[sp, cfa)`.
+    pub sp: u64,
+    /// Canonical frame address: the stack pointer just before the call
+    /// into this frame. `None` when the frame cannot be unwound.
+    pub cfa: Option<u64>,
+    /// Stack slot holding the return address, and the address in it.
+    pub saved_rip: Option<(u64, u64)>,
+    /// Registers this frame saved on the stack, with the slot of each.
+    pub saved_registers: Vec<(&'static str, u64)>,
+    /// Canonical frame address of the caller.
+    pub caller_cfa: Option<u64>,
+    /// Whether the layout comes from call frame information rather than
+    /// the frame-pointer chain.
+    pub from_cfi: bool,
+}
+
+/// Where a frame keeps what it saved, relative to its CFA.
+struct FrameLayout {
+    cfa: u64,
+    /// Stack slots by DWARF register, the return address included.
+    slots: Vec<(Register, u64)>,
+    from_cfi: bool,
+}
+
+/// Call frame information of the main executable, at static addresses.
+#[derive(Debug)]
+pub(crate) struct CfiTable {
+    eh_frame: Vec<u8>,
+    debug_frame: Vec<u8>,
+    bases: BaseAddresses,
+}
+
+/// Rules for recovering the caller's registers at one address.
+struct UnwindRow {
+    cfa: CfaRule<usize>,
+    registers: Vec<(Register, RegisterRule<usize>)>,
+}
+
+/// Result of unwinding a single frame.
+enum Unwound {
+    Caller(Box<Registers>),
+    /// The frame has no caller, e.g. `_start`.
+    Outermost,
+    /// No usable unwind information for the frame.
+    Unknown,
+}
+
+impl CfiTable {
+    /// Copy the CFI sections out of `path`.
+    pub(crate) fn load(path: &Path) -> FdbResult<Self> {
+        let data = std::fs::read(path)?;
+        let object = object::File::parse(&*data)?;
+        let section_data = |name: &str| {
+            object
+                .section_by_name(name)
+                .and_then(|section| section.uncompressed_data().ok())
+                .map_or_else(Vec::new, Cow::into_owned)
+        };
+        let section_addr = |name: &str| object.section_by_name(name).map(|s| s.address());
+
+        let mut bases = BaseAddresses::default();
+        if let Some(addr) = section_addr(".eh_frame_hdr") {
+            bases = bases.set_eh_frame_hdr(addr);
+        }
+        if let Some(addr) = section_addr(".eh_frame") {
+            bases = bases.set_eh_frame(addr);
+        }
+        if let Some(addr) = section_addr(".text") {
+            bases = bases.set_text(addr);
+        }
+        if let Some(addr) = section_addr(".got") {
+            bases = bases.set_got(addr);
+        }
+        Ok(CfiTable {
+            eh_frame: section_data(".eh_frame"),
+            debug_frame: section_data(".debug_frame"),
+            bases,
+        })
+    }
+
+    /// Unwind rules in effect at static address `addr`.
+    fn row(&self, addr: u64) -> Option<UnwindRow> {
+        let eh_frame = EhFrame::new(&self.eh_frame, LittleEndian);
+        let debug_frame = DebugFrame::new(&self.debug_frame, LittleEndian);
+        row_in(&eh_frame, &self.bases, addr).or_else(|| row_in(&debug_frame, &self.bases, addr))
+    }
+}
+
+impl UnwindRow {
+    /// The CFA under these rules, given the frame's registers.
+    fn cfa_value(&self, regs: &Registers) -> Option<u64> {
+        let CfaRule::RegisterAndOffset { register, offset } = self.cfa else {
+            return None;
+        };
+        Some(read_dwarf_register(regs, register)?.wrapping_add_signed(offset))
+    }
+}
+
+fn row_in<'a, S: UnwindSection<Reader<'a>>>(
+    section: &S,
+    bases: &BaseAddresses,
+    addr: u64,
+) -> Option<UnwindRow> {
+    let mut ctx = UnwindContext::new();
+    let row = section
+        .unwind_info_for_address(bases, &mut ctx, addr, S::cie_from_offset)
+        .ok()?;
+    Some(UnwindRow {
+        cfa: row.cfa().clone(),
+        registers: row.registers().cloned().collect(),
+    })
+}
+
+impl ProcessHandle {
+    /// Walk the call stack from the current registers.
+    ///
+    /// Each frame is unwound with CFI when the executable describes it, and
+    /// otherwise by reading the caller's RBP at `[rbp]` and the return
+    /// address at `[rbp + 8]`. The walk ends at the outermost frame, an
+    /// unreadable stack slot, or a caller whose stack pointer does not move
+    /// up the stack. It is cut short, with an error attached, after
+    /// [`backtrace_limit`] frames or when a frame repeats.
+    ///
+    /// [`backtrace_limit`]: ProcessHandle::backtrace_limit
+    pub fn backtrace(&self) -> FdbResult<Backtrace> {
+        self.walk_stack(false)
+    }
+
+    /// Walk the call stack as [`backtrace`] does, also reading the locals
+    /// of every frame.
+    ///
+    /// [`backtrace`]: ProcessHandle::backtrace
+    pub fn backtrace_full(&self) -> FdbResult<Backtrace> {
+        self.walk_stack(true)
+    }
+
+    /// Most frames a backtrace walks.
+    pub fn backtrace_limit(&self) -> usize {
+        self.backtrace_limit
+    }
+
+    /// Walk at most `limit` frames in later backtraces; 0 lifts the limit.
+    pub fn set_backtrace_limit(&mut self, limit: usize) {
+        self.backtrace_limit = if limit == 0 { usize::MAX } else { limit };
+    }
+
+    fn walk_stack(&self, with_locals: bool) -> FdbResult<Backtrace> {
+        let mut regs = self.get_registers()?;
+        let mut frames = vec![self.frame(&regs, false, with_locals)?];
+        let mut seen = HashSet::new();
+        let error = loop {
+            let is_caller = frames.len() > 1;
+            let Unwound::Caller(caller) = self.unwind(&regs, is_caller) else {
+                break None;
+            };
+            if caller.rip() == 0 {
+                break None;
+            }
+            // The caller's stack pointer is the CFA of the frame below.
+            let (pc, cfa) = (caller.rip(), caller.rsp());
+            if !seen.insert((pc, cfa)) {
+                break Some(FdbError::RepeatedFrame { pc, cfa });
+            }
+            if cfa <= regs.rsp() {
+                break None;
+            }
+            if frames.len() >= self.backtrace_limit {
+                break Some(FdbError::BacktraceLimit(self.backtrace_limit));
+            }
+            match self.frame(&caller, true, with_locals) {
+                Ok(frame) => frames.push(frame),
+                Err(e) => break Some(e),
+            }
+            regs = *caller;
+        };
+        Ok(Backtrace { frames, error })
+    }
+
+    /// Registers of frame `level` as unwinding recovers them, frame 0
+    /// being where the process stopped.
+    ///
+    /// Registers a caller did not save hold the values of the frame below
+    /// it, as in a backtrace.
+    pub fn frame_registers(&self, level: usize) -> FdbResult<Registers> {
+        let mut regs = self.get_registers()?;
+        for depth in 0..level {
+            regs = self
+                .caller_registers(&regs, depth > 0)
+                .ok_or(FdbError::NoSuchFrame(level))?;
+        }
+        Ok(regs)
+    }
+
+    /// Pop the current frame as if its function had returned, with `value`
+    /// in RAX when given.
+    ///
+    /// The caller's stack pointer and saved registers are recovered as for
+    /// a backtrace, and execution resumes at the return address.
+    pub fn force_return(&mut self, value: Option<u64>) -> FdbResult<()> {
+        let regs = self.get_registers()?;
+        let Unwound::Caller(mut caller) = self.unwind(&regs, false) else {
+            return Err(FdbError::NoCallerFrame);
+        };
+        if let Some(value) = value {
+            caller.raw_mut().rax = value;
+        }
+        self.set_registers(&caller)
+    }
+
+    /// Describe the current frame's place on the stack.
+    pub fn current_frame_info(&self) -> FdbResult<FrameInfo> {
+        let regs = self.get_registers()?;
+        let layout = self.frame_layout(&regs, false);
+        let caller_cfa = match self.unwind(&regs, false) {
+            Unwound::Caller(caller) => self.frame_layout(&caller, true).map(|l| l.cfa),
+            _ => None,
+        };
+        let mut info = FrameInfo {
+            pc: regs.rip(),
+            function: self.symbol_at(regs.rip()).map(|sym| sym.name),
+            sp: regs.rsp(),
+            cfa: layout.as_ref().map(|layout| layout.cfa),
+            saved_rip: None,
+            saved_registers: Vec::new(),
+            caller_cfa,
+            from_cfi: layout.as_ref().is_some_and(|layout| layout.from_cfi),
+        };
+        for (register, slot) in layout.map(|layout| layout.slots).unwrap_or_default() {
+            if register == RETURN_ADDRESS {
+                info.saved_rip = self.read_u64(slot).map(|value| (slot, value));
+            } else if let Some(reg) = register_by_dwarf(register.0) {
+                info.saved_registers.push((reg.name, slot));
+            }
+        }
+        Ok(info)
+    }
+
+    /// The frame's CFA and saved-register slots, from CFI when the frame
+    /// has it and from RBP otherwise.
+    fn frame_layout(&self, regs: &Registers, is_caller: bool) -> Option<FrameLayout> {
+        let static_pc = lookup_pc(regs.rip(), is_caller).wrapping_sub(self.load_bias());
+        if let Some(row) = self.cfi_table().and_then(|cfi| cfi.row(static_pc))
+            && let Some(cfa) = row.cfa_value(regs)
+        {
+            let slots = row
+                .registers
+                .iter()
+                .filter_map(|(register, rule)| match *rule {
+                    RegisterRule::Offset(offset) => {
+                        Some((*register, cfa.wrapping_add_signed(offset)))
+                    }
+                    _ => None,
+                })
+                .collect();
+            return Some(FrameLayout {
+                cfa,
+                slots,
+                from_cfi: true,
+            });
+        }
+        let rbp = regs.rbp();
+        (rbp != 0).then(|| FrameLayout {
+            cfa: rbp + 16,
+            slots: vec![(RBP, rbp), (RETURN_ADDRESS, rbp + 8)],
+            from_cfi: false,
+        })
+    }
+
+    fn frame(&self, regs: &Registers, is_caller: bool, with_locals: bool) -> FdbResult<Frame> {
+        let pc = regs.rip();
+        let symbol = self.symbol_at(lookup_pc(pc, is_caller));
+        let locals = if with_locals {
+            let locals = self.frame_locals(regs, is_caller)?;
+            Some(
+                locals
+                    .into_iter()
+                    .map(|(name, value)| (name, value.to_string()))
+                    .collect(),
+            )
+        } else {
+            None
+        };
+        Ok(Frame {
+            pc,
+            frame_pointer: regs.rbp(),
+            offset: symbol.as_ref().map_or(0, |sym| pc - sym.address),
+            function: symbol.map(|sym| sym.name),
+            location: self.addr_to_line(lookup_pc(pc, is_caller)),
+            arguments: self.frame_arguments(regs, is_caller),
+            locals,
+        })
+    }
+
+    /// Registers of the frame that called the one in `regs`, unless the
+    /// walk should stop there.
+    fn caller_registers(&self, regs: &Registers, is_caller: bool) -> Option<Registers> {
+        let Unwound::Caller(caller) = self.unwind(regs, is_caller) else {
+            return None;
+        };
+        (caller.rip() != 0 && caller.rsp() > regs.rsp()).then_some(*caller)
+    }
+
+    /// Recover the caller's registers, with CFI if there is any for the
+    /// frame and from the frame-pointer chain if not.
+    fn unwind(&self, regs: &Registers, is_caller: bool) -> Unwound {
+        match self.unwind_cfi(regs, is_caller) {
+            Unwound::Unknown => self.unwind_frame_pointer(regs),
+            unwound => unwound,
+        }
+    }
+
+    fn unwind_cfi(&self, regs: &Registers, is_caller: bool) -> Unwound {
+        let static_pc = lookup_pc(regs.rip(), is_caller).wrapping_sub(self.load_bias());
+        let Some(row) = self.cfi_table().and_then(|cfi| cfi.row(static_pc)) else {
+            return Unwound::Unknown;
+        };
+        let Some(cfa) = row.cfa_value(regs) else {
+            return Unwound::Unknown;
+        };
+
+        let mut caller = *regs;
+        let mut return_addr = None;
+        for (register, rule) in &row.registers {
+            let value = match *rule {
+                RegisterRule::Offset(offset) => {
+                    match self.read_u64(cfa.wrapping_add_signed(offset)) {
+                        Some(value) => value,
+                        None => return Unwound::Outermost,
+                    }
+                }
+                RegisterRule::ValOffset(offset) => cfa.wrapping_add_signed(offset),
+                RegisterRule::Register(other) => match read_dwarf_register(regs, other) {
+                    Some(value) => value,
+                    None => continue,
+                },
+                // Anything else leaves the register as it was.
+                _ => continue,
+            };
+            if *register == RETURN_ADDRESS {
+                return_addr = Some(value);
+            } else if let Some(info) = register_by_dwarf(register.0) {
+                caller.write(info, value);
+            }
+        }
+        // An undefined return address marks the outermost frame.
+        let Some(return_addr) = return_addr else {
+            return Unwound::Outermost;
+        };
+        caller.raw_mut().rip = return_addr;
+        caller.raw_mut().rsp = cfa;
+        Unwound::Caller(Box::new(caller))
+    }
+
+    /// Canonical frame address of the frame whose registers are `regs`:
+    /// the stack pointer just before the call into it.
+    pub(crate) fn frame_cfa(&self, regs: &Registers, is_caller: bool) -> Option<u64> {
+        let static_pc = lookup_pc(regs.rip(), is_caller).wrapping_sub(self.load_bias());
+        self.cfi_table()?.row(static_pc)?.cfa_value(regs)
+    }
+
+    fn unwind_frame_pointer(&self, regs: &Registers) -> Unwound {
+        let rbp = regs.rbp();
+        if rbp == 0 {
+            return Unwound::Outermost;
+        }
+        let (Some(caller_rbp), Some(return_addr)) = (self.read_u64(rbp), self.read_u64(rbp + 8))
+        else {
+            return Unwound::Outermost;
+        };
+        let mut caller = *regs;
+        let raw = caller.raw_mut();
+        raw.rbp = caller_rbp;
+        raw.rip = return_addr;
+        raw.rsp = rbp + 16;
+        Unwound::Caller(Box::new(caller))
+    }
+
+    fn read_u64(&self, addr: u64) -> Option<u64> {
+        let bytes = self.read_memory(addr, 8).ok()?;
+        Some(u64::from_le_bytes(bytes.try_into().ok()?))
+    }
+
+    fn cfi_table(&self) -> Option<&CfiTable> {
+        self.cfi
+            .get_or_init(|| {
+                let exe = format!("/proc/{}/exe", self.pid);
+                CfiTable::load(Path::new(&exe))
+                    .inspect_err(|e| log::debug!("no call frame info for {exe}: {e}"))
+                    .ok()
+            })
+            .as_ref()
+    }
+}
+
+/// Address used to find the function and unwind rules for a frame.
+///
+/// A return address may be the first byte after a call that ends its
+/// function, so callers are looked up by the call instruction instead.
+pub(crate) fn lookup_pc(pc: u64, is_caller: bool) -> u64 {
+    if is_caller { pc - 1 } else { pc }
+}
+
+fn read_dwarf_register(regs: &Registers, register: Register) -> Option<u64> {
+    register_by_dwarf(register.0).map(|info| regs.read(info))
+}
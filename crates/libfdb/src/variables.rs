@@ -0,0 +1,806 @@
+//! Variables and their types from DWARF `.debug_info`.
+//!
+//! Functions, their lexical blocks and the variables declared in each are
+//! read once per program image. A variable's value is found by evaluating
+//! its DWARF location expression against the current registers.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::ops::Range;
+use std::path::Path;
+
+use gimli::{
+    AttributeValue, EndianSlice, EvaluationResult, LittleEndian, Location, UnitOffset, Value,
+};
+use object::{Object, ObjectSection};
+
+use crate::errors::{FdbError, FdbResult};
+use crate::process::ProcessHandle;
+use crate::registers::{Registers, register_by_dwarf};
+use crate::unwind::lookup_pc;
+
+type Reader<'a> = EndianSlice<'a, LittleEndian>;
+type Dwarf<'a> = gimli::Dwarf<Reader<'a>>;
+type Unit<'a> = gimli::Unit<Reader<'a>>;
+
+/// Longest chain of typedefs, qualifiers, pointers and arrays followed
+/// when resolving a type.
+const MAX_TYPE_DEPTH: usize = 16;
+
+/// How the bits of a base type are read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BaseKind {
+    Signed,
+    Unsigned,
+    SignedChar,
+    UnsignedChar,
+    Float,
+    Boolean,
+}
+
+/// Type of a variable, with typedefs and qualifiers resolved away.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Type {
+    Base {
+        name: String,
+        size: u64,
+        kind: BaseKind,
+    },
+    Pointer(Box<Type>),
+    Array {
+        element: Box<Type>,
+        count: u64,
+    },
+    Void,
+    /// A structure, union, enumeration or anything else shown as raw bytes.
+    Opaque {
+        name: String,
+        size: u64,
+    },
+}
+
+impl Type {
+    /// Size of a value of this type in bytes.
+    pub fn size(&self) -> u64 {
+        match self {
+            Type::Base { size, .. } | Type::Opaque { size, .. } => *size,
+            Type::Pointer(_) => 8,
+            Type::Array { element, count } => element.size() * count,
+            Type::Void => 0,
+        }
+    }
+
+    fn is_char(&self) -> bool {
+        matches!(
+            self,
+            Type::Base {
+                kind: BaseKind::SignedChar | BaseKind::UnsignedChar,
+                ..
+            }
+        )
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Base { name, .. } | Type::Opaque { name, .. } => write!(f, "{name}"),
+            Type::Pointer(pointee) => write!(f, "{pointee} *"),
+            Type::Array { element, count } => write!(f, "{element} [{count}]"),
+            Type::Void => write!(f, "void"),
+        }
+    }
+}
+
+/// A value read from the inferior together with its type.
+#[derive(Debug, Clone)]
+pub struct TypedValue {
+    pub ty: Type,
+    /// The value's bytes in target (little-endian) order.
+    pub bytes: Vec<u8>,
+}
+
+impl TypedValue {
+    /// The address held by a `char *`, where a C string may start.
+    pub fn char_pointer(&self) -> Option<u64> {
+        match &self.ty {
+            Type::Pointer(pointee) if pointee.is_char() => {
+                Some(u64::from_le_bytes(self.bytes.get(..8)?.try_into().ok()?))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for TypedValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        format_value(&self.ty, &self.bytes, f)
+    }
+}
+
+fn format_value(ty: &Type, bytes: &[u8], f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match ty {
+        Type::Base { size, kind, .. } => {
+            let len = (*size as usize).min(8).min(bytes.len());
+            let mut raw = [0u8; 8];
+            raw[..len].copy_from_slice(&bytes[..len]);
+            let unsigned = u64::from_le_bytes(raw);
+            let shift = 64 - 8 * len.max(1) as u32;
+            let signed = ((unsigned << shift) as i64) >> shift;
+            match kind {
+                BaseKind::Signed => write!(f, "{signed}"),
+                BaseKind::Unsigned => write!(f, "{unsigned}"),
+                BaseKind::SignedChar => write!(f, "{signed} '{}'", (raw[0]).escape_ascii()),
+                BaseKind::UnsignedChar => write!(f, "{unsigned} '{}'", (raw[0]).escape_ascii()),
+                BaseKind::Boolean => write!(f, "{}", unsigned != 0),
+                BaseKind::Float if *size == 4 => {
+                    write!(f, "{}", f32::from_le_bytes(raw[..4].try_into().unwrap()))
+                }
+                BaseKind::Float if *size == 8 => write!(f, "{}", f64::from_le_bytes(raw)),
+                BaseKind::Float => write!(f, "<{size}-byte float {}>", hex(bytes)),
+            }
+        }
+        Type::Pointer(_) => {
+            let mut raw = [0u8; 8];
+            let len = bytes.len().min(8);
+            raw[..len].copy_from_slice(&bytes[..len]);
+            write!(f, "{:#x}", u64::from_le_bytes(raw))
+        }
+        // Character arrays read as strings up to the terminating NUL.
+        Type::Array { element, .. } if element.is_char() => {
+            let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+            write!(f, "\"{}\"", bytes[..end].escape_ascii())
+        }
+        Type::Array { element, .. } => {
+            let size = element.size() as usize;
+            if size == 0 {
+                return write!(f, "{{}}");
+            }
+            write!(f, "{{")?;
+            for (i, chunk) in bytes.chunks(size).enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                format_value(element, chunk, f)?;
+            }
+            write!(f, "}}")
+        }
+        Type::Void => write!(f, "void"),
+        Type::Opaque { name, .. } => write!(f, "<{name} {}>", hex(bytes)),
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Where a variable lives.
+#[derive(Debug, Clone)]
+enum VarLocation {
+    /// One expression, valid wherever the variable is in scope.
+    Expr(Vec<u8>),
+    /// Expressions each valid over a range of static addresses.
+    List(Vec<(Range<u64>, Vec<u8>)>),
+    /// A constant the compiler folded away, in target byte order.
+    Const(Vec<u8>),
+    /// The compiler recorded no location; the variable was optimized out.
+    None,
+}
+
+/// A named variable or parameter.
+#[derive(Debug, Clone)]
+pub(crate) struct Variable {
+    pub(crate) name: String,
+    /// Whether this is one of the function's parameters.
+    parameter: bool,
+    ty: Type,
+    location: VarLocation,
+    encoding: gimli::Encoding,
+}
+
+impl Variable {
+    /// Location expression in effect at static address `pc`, if any.
+    fn expr_at(&self, pc: u64) -> Option<&[u8]> {
+        match &self.location {
+            VarLocation::Expr(expr) => Some(expr),
+            VarLocation::List(list) => list
+                .iter()
+                .find(|(range, _)| range.contains(&pc))
+                .map(|(_, expr)| expr.as_slice()),
+            VarLocation::Const(_) | VarLocation::None => None,
+        }
+    }
+}
+
+/// A variable looked up by name, with the frame it is seen from.
+struct FoundVariable<'a> {
+    var: &'a Variable,
+    function: Option<&'a Function>,
+    regs: Registers,
+    /// Static address the frame is at.
+    pc: u64,
+    is_caller: bool,
+}
+
+/// A function body or a lexical block nested in one.
+#[derive(Debug, Default)]
+struct Scope {
+    /// Static address ranges of the scope's code.
+    ranges: Vec<Range<u64>>,
+    variables: Vec<Variable>,
+    children: Vec<Scope>,
+}
+
+impl Scope {
+    fn contains(&self, pc: u64) -> bool {
+        self.ranges.iter().any(|range| range.contains(&pc))
+    }
+}
+
+#[derive(Debug)]
+struct Function {
+    /// `DW_AT_frame_base`, which `DW_OP_fbreg` locations are relative to.
+    frame_base: Option<Vec<u8>>,
+    encoding: gimli::Encoding,
+    body: Scope,
+}
+
+impl Function {
+    /// Variables visible at static address `pc`, innermost scope first.
+    fn visible(&self, pc: u64) -> Vec<&Variable> {
+        let mut scopes = vec![&self.body];
+        while let Some(inner) = scopes
+            .last()
+            .and_then(|scope| scope.children.iter().find(|child| child.contains(pc)))
+        {
+            scopes.push(inner);
+        }
+        scopes
+            .iter()
+            .rev()
+            .flat_map(|scope| &scope.variables)
+            .collect()
+    }
+}
+
+/// Functions and global variables described by `.debug_info`.
+#[derive(Debug, Default)]
+pub(crate) struct DebugInfo {
+    functions: Vec<Function>,
+    globals: Vec<Variable>,
+}
+
+impl DebugInfo {
+    /// Read the functions and variables of every compilation unit in `path`.
+    pub(crate) fn load(path: &Path) -> FdbResult<Self> {
+        let data = std::fs::read(path)?;
+        let object = object::File::parse(&*data)?;
+        let sections = gimli::DwarfSections::load(|id| -> Result<_, gimli::Error> {
+            Ok(object
+                .section_by_name(id.name())
+                .and_then(|section| section.uncompressed_data().ok())
+                .unwrap_or(Cow::Borrowed(&[])))
+        })?;
+        let dwarf = sections.borrow(|section| EndianSlice::new(section, LittleEndian));
+
+        let mut info = DebugInfo::default();
+        let mut headers = dwarf.units();
+        while let Some(header) = headers.next()? {
+            let unit = dwarf.unit(header)?;
+            let mut tree = unit.entries_tree(None)?;
+            let mut children = tree.root()?.children();
+            while let Some(child) = children.next()? {
+                match child.entry().tag() {
+                    gimli::DW_TAG_variable => {
+                        if let Some(var) = read_variable(&dwarf, &unit, child.entry())? {
+                            info.globals.push(var);
+                        }
+                    }
+                    gimli::DW_TAG_subprogram => {
+                        let ranges = die_ranges(&dwarf, &unit, child.entry())?;
+                        // Declarations and abstract inline instances have
+                        // no code of their own.
+                        if ranges.is_empty() {
+                            continue;
+                        }
+                        let frame_base = match child.entry().attr_value(gimli::DW_AT_frame_base)? {
+                            Some(AttributeValue::Exprloc(expr)) => Some(expr.0.slice().to_vec()),
+                            _ => None,
+                        };
+                        let mut body = Scope {
+                            ranges,
+                            ..Scope::default()
+                        };
+                        read_scope(&dwarf, &unit, child, &mut body)?;
+                        info.functions.push(Function {
+                            frame_base,
+                            encoding: unit.encoding(),
+                            body,
+                        });
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(info)
+    }
+
+    fn function_at(&self, pc: u64) -> Option<&Function> {
+        self.functions
+            .iter()
+            .find(|function| function.body.contains(pc))
+    }
+}
+
+/// Collect the variables and nested blocks under `node` into `scope`.
+fn read_scope(
+    dwarf: &Dwarf<'_>,
+    unit: &Unit<'_>,
+    node: gimli::EntriesTreeNode<'_, '_, '_, Reader<'_>>,
+    scope: &mut Scope,
+) -> FdbResult<()> {
+    let mut children = node.children();
+    while let Some(child) = children.next()? {
+        match child.entry().tag() {
+            gimli::DW_TAG_formal_parameter | gimli::DW_TAG_variable => {
+                if let Some(var) = read_variable(dwarf, unit, child.entry())? {
+                    scope.variables.push(var);
+                }
+            }
+            gimli::DW_TAG_lexical_block => {
+                let mut block = Scope {
+                    ranges: die_ranges(dwarf, unit, child.entry())?,
+                    ..Scope::default()
+                };
+                read_scope(dwarf, unit, child, &mut block)?;
+                scope.children.push(block);
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn read_variable(
+    dwarf: &Dwarf<'_>,
+    unit: &Unit<'_>,
+    entry: &gimli::DebuggingInformationEntry<'_, '_, Reader<'_>>,
+) -> FdbResult<Option<Variable>> {
+    // A declaration is completed by another entry that has the location.
+    if entry.attr_value(gimli::DW_AT_declaration)?.is_some() {
+        return Ok(None);
+    }
+    let Some(name) = die_name(dwarf, unit, entry)? else {
+        return Ok(None);
+    };
+    let ty = match entry.attr_value(gimli::DW_AT_type)? {
+        Some(AttributeValue::UnitRef(offset)) => resolve_type(dwarf, unit, offset, 0)?,
+        _ => Type::Void,
+    };
+    let location = match entry.attr_value(gimli::DW_AT_location)? {
+        Some(AttributeValue::Exprloc(expr)) => VarLocation::Expr(expr.0.slice().to_vec()),
+        Some(value) => match dwarf.attr_locations(unit, value)? {
+            Some(mut entries) => {
+                let mut list = Vec::new();
+                while let Some(entry) = entries.next()? {
+                    list.push((
+                        entry.range.begin..entry.range.end,
+                        entry.data.0.slice().to_vec(),
+                    ));
+                }
+                VarLocation::List(list)
+            }
+            None => VarLocation::None,
+        },
+        None => match entry.attr_value(gimli::DW_AT_const_value)? {
+            Some(AttributeValue::Block(block)) => VarLocation::Const(block.slice().to_vec()),
+            Some(AttributeValue::Sdata(value)) => VarLocation::Const(value.to_le_bytes().to_vec()),
+            Some(value) => match value.udata_value() {
+                Some(value) => VarLocation::Const(value.to_le_bytes().to_vec()),
+                None => VarLocation::None,
+            },
+            None => VarLocation::None,
+        },
+    };
+    Ok(Some(Variable {
+        name,
+        parameter: entry.tag() == gimli::DW_TAG_formal_parameter,
+        ty,
+        location,
+        encoding: unit.encoding(),
+    }))
+}
+
+fn resolve_type(
+    dwarf: &Dwarf<'_>,
+    unit: &Unit<'_>,
+    offset: UnitOffset,
+    depth: usize,
+) -> FdbResult<Type> {
+    let entry = unit.entry(offset)?;
+    let name = die_name(dwarf, unit, &entry)?;
+    let size = entry
+        .attr_value(gimli::DW_AT_byte_size)?
+        .and_then(|value| value.udata_value())
+        .unwrap_or(0);
+    let opaque = |prefix: &str| Type::Opaque {
+        name: format!("{prefix}{}", name.as_deref().unwrap_or("?")),
+        size,
+    };
+    if depth >= MAX_TYPE_DEPTH {
+        return Ok(opaque(""));
+    }
+    let target = || -> FdbResult<Type> {
+        match entry.attr_value(gimli::DW_AT_type)? {
+            Some(AttributeValue::UnitRef(target)) => resolve_type(dwarf, unit, target, depth + 1),
+            _ => Ok(Type::Void),
+        }
+    };
+
+    Ok(match entry.tag() {
+        gimli::DW_TAG_base_type => {
+            let kind = match entry.attr_value(gimli::DW_AT_encoding)? {
+                Some(AttributeValue::Encoding(encoding)) => match encoding {
+                    gimli::DW_ATE_signed => BaseKind::Signed,
+                    gimli::DW_ATE_unsigned => BaseKind::Unsigned,
+                    gimli::DW_ATE_signed_char => BaseKind::SignedChar,
+                    gimli::DW_ATE_unsigned_char => BaseKind::UnsignedChar,
+                    gimli::DW_ATE_float => BaseKind::Float,
+                    gimli::DW_ATE_boolean => BaseKind::Boolean,
+                    _ => return Ok(opaque("")),
+                },
+                _ => return Ok(opaque("")),
+            };
+            Type::Base {
+                name: name.unwrap_or_default(),
+                size,
+                kind,
+            }
+        }
+        gimli::DW_TAG_pointer_type | gimli::DW_TAG_reference_type => {
+            Type::Pointer(Box::new(target()?))
+        }
+        gimli::DW_TAG_array_type => {
+            // One subrange per dimension, outermost first.
+            let mut counts = Vec::new();
+            let mut tree = unit.entries_tree(Some(offset))?;
+            let mut children = tree.root()?.children();
+            while let Some(child) = children.next()? {
+                let entry = child.entry();
+                if entry.tag() != gimli::DW_TAG_subrange_type {
+                    continue;
+                }
+                let count = match entry.attr_value(gimli::DW_AT_count)? {
+                    Some(count) => count.udata_value(),
+                    None => entry
+                        .attr_value(gimli::DW_AT_upper_bound)?
+                        .and_then(|bound| bound.udata_value())
+                        .map(|bound| bound + 1),
+                };
+                counts.push(count.unwrap_or(0));
+            }
+            let mut ty = target()?;
+            for count in counts.into_iter().rev() {
+                ty = Type::Array {
+                    element: Box::new(ty),
+                    count,
+                };
+            }
+            ty
+        }
+        gimli::DW_TAG_typedef
+        | gimli::DW_TAG_const_type
+        | gimli::DW_TAG_volatile_type
+        | gimli::DW_TAG_restrict_type
+        | gimli::DW_TAG_atomic_type => target()?,
+        gimli::DW_TAG_structure_type => opaque("struct "),
+        gimli::DW_TAG_union_type => opaque("union "),
+        gimli::DW_TAG_enumeration_type => opaque("enum "),
+        _ => opaque(""),
+    })
+}
+
+fn die_name(
+    dwarf: &Dwarf<'_>,
+    unit: &Unit<'_>,
+    entry: &gimli::DebuggingInformationEntry<'_, '_, Reader<'_>>,
+) -> FdbResult<Option<String>> {
+    let Some(value) = entry.attr_value(gimli::DW_AT_name)? else {
+        return Ok(None);
+    };
+    Ok(Some(
+        dwarf
+            .attr_string(unit, value)?
+            .to_string_lossy()
+            .into_owned(),
+    ))
+}
+
+fn die_ranges(
+    dwarf: &Dwarf<'_>,
+    unit: &Unit<'_>,
+    entry: &gimli::DebuggingInformationEntry<'_, '_, Reader<'_>>,
+) -> FdbResult<Vec<Range<u64>>> {
+    let mut ranges = Vec::new();
+    let mut iter = dwarf.die_ranges(unit, entry)?;
+    while let Some(range) = iter.next()? {
+        ranges.push(range.begin..range.end);
+    }
+    Ok(ranges)
+}
+
+impl ProcessHandle {
+    /// Value of the variable called `name` as seen from the current pc.
+    ///
+    /// Locals and parameters of the current function are searched from the
+    /// innermost block outwards, then global variables.
+    pub fn read_variable(&self, name: &str) -> FdbResult<TypedValue> {
+        self.read_variable_in_frame(name, 0)
+    }
+
+    /// Value of the variable called `name` as seen from frame `level` of
+    /// the call stack.
+    pub fn read_variable_in_frame(&self, name: &str, level: usize) -> FdbResult<TypedValue> {
+        let found = self.find_variable(name, level)?;
+        self.variable_value(
+            found.var,
+            found.function,
+            &found.regs,
+            found.pc,
+            found.is_caller,
+        )
+    }
+
+    /// Address and type of the variable called `name` as seen from the
+    /// current pc.
+    ///
+    /// Fails for a variable kept in a register or folded into a constant.
+    pub fn variable_address(&self, name: &str) -> FdbResult<(u64, Type)> {
+        let found = self.find_variable(name, 0)?;
+        let var = found.var;
+        let not_in_memory = || FdbError::VariableNotInMemory(name.to_string());
+        let expr = match var.location {
+            VarLocation::Const(_) => return Err(not_in_memory()),
+            _ => var
+                .expr_at(found.pc)
+                .ok_or_else(|| FdbError::VariableUnavailable(name.to_string()))?,
+        };
+        match self.locate(
+            expr,
+            var.encoding,
+            &found.regs,
+            found.function,
+            found.is_caller,
+        )? {
+            Location::Address { address } => Ok((address, var.ty.clone())),
+            _ => Err(not_in_memory()),
+        }
+    }
+
+    /// Look `name` up in frame `level`: locals and parameters from the
+    /// innermost block outwards, then global variables.
+    fn find_variable(&self, name: &str, level: usize) -> FdbResult<FoundVariable<'_>> {
+        let unknown = || FdbError::UnknownVariable(name.to_string());
+        let info = self.debug_info().ok_or_else(unknown)?;
+        let regs = self.frame_registers(level)?;
+        let is_caller = level > 0;
+        let pc = lookup_pc(regs.rip(), is_caller).wrapping_sub(self.load_bias());
+        let function = info.function_at(pc);
+        let var = function
+            .and_then(|function| function.visible(pc).into_iter().find(|v| v.name == name))
+            .or_else(|| info.globals.iter().find(|var| var.name == name))
+            .ok_or_else(unknown)?;
+        Ok(FoundVariable {
+            var,
+            function,
+            regs,
+            pc,
+            is_caller,
+        })
+    }
+
+    /// Local variables visible at the current pc with their values,
+    /// innermost block first. Parameters are left out, as are variables
+    /// with no location at this pc or one fdb cannot evaluate.
+    pub fn locals(&self) -> FdbResult<Vec<(String, TypedValue)>> {
+        self.locals_in_frame(0)
+    }
+
+    /// Local variables of frame `level` of the call stack, as [`locals`]
+    /// lists them for frame 0.
+    ///
+    /// [`locals`]: ProcessHandle::locals
+    pub fn locals_in_frame(&self, level: usize) -> FdbResult<Vec<(String, TypedValue)>> {
+        self.frame_locals(&self.frame_registers(level)?, level > 0)
+    }
+
+    /// Locals of the frame described by `regs`.
+    pub(crate) fn frame_locals(
+        &self,
+        regs: &Registers,
+        is_caller: bool,
+    ) -> FdbResult<Vec<(String, TypedValue)>> {
+        let Some(info) = self.debug_info() else {
+            return Ok(Vec::new());
+        };
+        let pc = lookup_pc(regs.rip(), is_caller).wrapping_sub(self.load_bias());
+        let Some(function) = info.function_at(pc) else {
+            return Ok(Vec::new());
+        };
+        let mut locals = Vec::new();
+        for var in function.visible(pc) {
+            if var.parameter {
+                continue;
+            }
+            match self.variable_value(var, Some(function), regs, pc, is_caller) {
+                Ok(value) => locals.push((var.name.clone(), value)),
+                Err(FdbError::VariableUnavailable(_)) => {}
+                Err(e @ FdbError::Unimplemented(_)) => {
+                    log::debug!("cannot read local {}: {e}", var.name);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(locals)
+    }
+
+    /// Parameters of the function the frame in `regs` is in, in declaration
+    /// order, with their values formatted for display. A parameter that
+    /// cannot be read shows why instead.
+    pub(crate) fn frame_arguments(
+        &self,
+        regs: &Registers,
+        is_caller: bool,
+    ) -> Vec<(String, String)> {
+        let Some(info) = self.debug_info() else {
+            return Vec::new();
+        };
+        let pc = lookup_pc(regs.rip(), is_caller).wrapping_sub(self.load_bias());
+        let Some(function) = info.function_at(pc) else {
+            return Vec::new();
+        };
+        function
+            .body
+            .variables
+            .iter()
+            .filter(|var| var.parameter)
+            .map(|var| {
+                let value = match self.variable_value(var, Some(function), regs, pc, is_caller) {
+                    Ok(value) => value.to_string(),
+                    Err(FdbError::VariableUnavailable(_)) => "<optimized out>".to_string(),
+                    Err(e) => format!("<error: {e}>"),
+                };
+                (var.name.clone(), value)
+            })
+            .collect()
+    }
+
+    /// Read `var` in the frame described by `regs`, stopped at static
+    /// address `pc` inside `function`. `is_caller` says the frame is not
+    /// the innermost one.
+    fn variable_value(
+        &self,
+        var: &Variable,
+        function: Option<&Function>,
+        regs: &Registers,
+        pc: u64,
+        is_caller: bool,
+    ) -> FdbResult<TypedValue> {
+        let unavailable = || FdbError::VariableUnavailable(var.name.clone());
+        if let VarLocation::Const(bytes) = &var.location {
+            let mut bytes = bytes.clone();
+            bytes.resize(var.ty.size() as usize, 0);
+            return Ok(TypedValue {
+                ty: var.ty.clone(),
+                bytes,
+            });
+        }
+        let expr = var.expr_at(pc).ok_or_else(unavailable)?;
+        let size = var.ty.size() as usize;
+        let bytes = match self.locate(expr, var.encoding, regs, function, is_caller)? {
+            Location::Address { address } => self.read_memory(address, size)?,
+            Location::Register { register } => {
+                let info = register_by_dwarf(register.0)
+                    .ok_or(FdbError::Unimplemented("values in non-integer registers"))?;
+                regs.read(info).to_le_bytes()[..size.min(8)].to_vec()
+            }
+            Location::Value { value } => value.to_u64(!0)?.to_le_bytes()[..size.min(8)].to_vec(),
+            Location::Bytes { value } => value.slice().to_vec(),
+            Location::Empty | Location::ImplicitPointer { .. } => return Err(unavailable()),
+        };
+        Ok(TypedValue {
+            ty: var.ty.clone(),
+            bytes,
+        })
+    }
+
+    /// Evaluate the location expression `expr` in the frame described by
+    /// `regs`. `function` supplies the frame base for `DW_OP_fbreg`.
+    fn locate<'a>(
+        &self,
+        expr: &'a [u8],
+        encoding: gimli::Encoding,
+        regs: &Registers,
+        function: Option<&Function>,
+        is_caller: bool,
+    ) -> FdbResult<Location<Reader<'a>>> {
+        let mut eval = gimli::Expression(EndianSlice::new(expr, LittleEndian)).evaluation(encoding);
+        let mut result = eval.evaluate()?;
+        loop {
+            result = match result {
+                EvaluationResult::Complete => break,
+                EvaluationResult::RequiresRegister { register, .. } => {
+                    let info = register_by_dwarf(register.0).ok_or(FdbError::Unimplemented(
+                        "non-integer registers in locations",
+                    ))?;
+                    eval.resume_with_register(Value::Generic(regs.read(info)))?
+                }
+                EvaluationResult::RequiresFrameBase => {
+                    let function =
+                        function.ok_or(FdbError::Unimplemented("frame base outside a function"))?;
+                    let frame_base = function
+                        .frame_base
+                        .as_deref()
+                        .ok_or(FdbError::Unimplemented("function without a frame base"))?;
+                    let base =
+                        match self.locate(frame_base, function.encoding, regs, None, is_caller)? {
+                            Location::Address { address } => address,
+                            Location::Register { register } => register_by_dwarf(register.0)
+                                .map(|info| regs.read(info))
+                                .ok_or(FdbError::Unimplemented("frame base in this register"))?,
+                            _ => return Err(FdbError::Unimplemented("this kind of frame base")),
+                        };
+                    eval.resume_with_frame_base(base)?
+                }
+                EvaluationResult::RequiresCallFrameCfa => {
+                    let cfa = self
+                        .frame_cfa(regs, is_caller)
+                        .ok_or(FdbError::Unimplemented(
+                            "frame without call frame information",
+                        ))?;
+                    eval.resume_with_call_frame_cfa(cfa)?
+                }
+                EvaluationResult::RequiresMemory { address, size, .. } => {
+                    let bytes = self.read_memory(address, size as usize)?;
+                    let mut raw = [0u8; 8];
+                    raw[..bytes.len()].copy_from_slice(&bytes);
+                    eval.resume_with_memory(Value::Generic(u64::from_le_bytes(raw)))?
+                }
+                EvaluationResult::RequiresRelocatedAddress(addr) => {
+                    eval.resume_with_relocated_address(addr.wrapping_add(self.load_bias()))?
+                }
+                // The caller's values at entry are not tracked, so treat
+                // anything computed from them as unavailable.
+                EvaluationResult::RequiresEntryValue(_)
+                | EvaluationResult::RequiresParameterRef(_) => return Ok(Location::Empty),
+                EvaluationResult::RequiresBaseType(_) => {
+                    return Err(FdbError::Unimplemented("typed DWARF expressions"));
+                }
+                EvaluationResult::RequiresTls(_) => {
+                    return Err(FdbError::Unimplemented("thread-local variables"));
+                }
+                _ => return Err(FdbError::Unimplemented("this DWARF location operation")),
+            };
+        }
+        let mut pieces = eval.result();
+        if pieces.len() != 1 {
+            return Err(FdbError::Unimplemented("variables split across locations"));
+        }
+        Ok(pieces.remove(0).location)
+    }
+
+    fn debug_info(&self) -> Option<&DebugInfo> {
+        self.debug_info
+            .get_or_init(|| {
+                let exe = format!("/proc/{}/exe", self.pid);
+                DebugInfo::load(Path::new(&exe))
+                    .inspect_err(|e| log::debug!("no debug info for {exe}: {e}"))
+                    .ok()
+            })
+            .as_ref()
+    }
+}
@@ -0,0 +1,332 @@
+//! Hardware watchpoints programmed through the x86-64 debug registers.
+//!
+//! DR0–DR3 hold watched addresses, DR7 enables them and selects the access
+//! type and length, and DR6 reports which one fired.
+
+use std::mem::offset_of;
+
+use nix::libc::{c_long, user};
+use nix::sys::ptrace;
+use nix::unistd::Pid;
+
+use crate::errors::{FdbError, FdbResult};
+use crate::process::ProcessHandle;
+use crate::variables::{Type, TypedValue};
+
+/// Number of address debug registers (DR0–DR3).
+const SLOTS: usize = 4;
+
+/// Access that triggers a watchpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchKind {
+    /// Reads. x86 has no read-only condition, so this also fires on writes.
+    Read,
+    Write,
+    ReadWrite,
+}
+
+impl WatchKind {
+    /// R/W field value for DR7.
+    fn rw_bits(self) -> u64 {
+        match self {
+            WatchKind::Write => 0b01,
+            WatchKind::Read | WatchKind::ReadWrite => 0b11,
+        }
+    }
+}
+
+/// Width of the watched range; the address must be aligned to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchSize {
+    One = 1,
+    Two = 2,
+    Four = 4,
+    Eight = 8,
+}
+
+impl WatchSize {
+    pub fn from_bytes(bytes: usize) -> Option<Self> {
+        match bytes {
+            1 => Some(WatchSize::One),
+            2 => Some(WatchSize::Two),
+            4 => Some(WatchSize::Four),
+            8 => Some(WatchSize::Eight),
+            _ => None,
+        }
+    }
+
+    pub fn bytes(self) -> usize {
+        self as usize
+    }
+
+    /// LEN field value for DR7.
+    fn len_bits(self) -> u64 {
+        match self {
+            WatchSize::One => 0b00,
+            WatchSize::Two => 0b01,
+            WatchSize::Eight => 0b10,
+            WatchSize::Four => 0b11,
+        }
+    }
+}
+
+/// A hardware watchpoint occupying one debug register.
+#[derive(Debug, Clone)]
+pub struct Watchpoint {
+    id: u32,
+    addr: u64,
+    size: WatchSize,
+    kind: WatchKind,
+    slot: usize,
+    /// Contents at the last stop, to report old/new values on a hit.
+    value: u64,
+    /// Set when watching a variable by name.
+    variable: Option<WatchedVariable>,
+}
+
+/// A variable watched by name.
+#[derive(Debug, Clone)]
+struct WatchedVariable {
+    name: String,
+    ty: Type,
+    /// Where the variable starts within the watched range, which may be
+    /// wider to meet the alignment debug registers need.
+    offset: usize,
+}
+
+impl Watchpoint {
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn addr(&self) -> u64 {
+        self.addr
+    }
+
+    pub fn size(&self) -> WatchSize {
+        self.size
+    }
+
+    pub fn kind(&self) -> WatchKind {
+        self.kind
+    }
+
+    /// Watched bytes as last observed by the debugger.
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    /// Name of the watched variable, for watchpoints set by name.
+    pub fn variable(&self) -> Option<&str> {
+        self.variable.as_ref().map(|var| var.name.as_str())
+    }
+
+    /// `raw` contents of the watched range as a value of the watched
+    /// variable's type, e.g. the `old` or `new` of a hit.
+    pub fn typed_value(&self, raw: u64) -> Option<TypedValue> {
+        let var = self.variable.as_ref()?;
+        let bytes = raw.to_le_bytes();
+        Some(TypedValue {
+            ty: var.ty.clone(),
+            bytes: bytes[var.offset..var.offset + var.ty.size() as usize].to_vec(),
+        })
+    }
+}
+
+impl ProcessHandle {
+    /// Watch `size` bytes at `addr` for the given access and return its id.
+    ///
+    /// Watchpoints share the id space with breakpoints.
+    pub fn set_watchpoint(
+        &mut self,
+        addr: u64,
+        size: WatchSize,
+        kind: WatchKind,
+    ) -> FdbResult<u32> {
+        self.arm_watchpoint(addr, size, kind, None)
+    }
+
+    /// Watch writes to the variable called `name`, as seen from the current
+    /// pc, and return the watchpoint id.
+    ///
+    /// The smallest aligned range a debug register can cover is watched,
+    /// so writes next to a small, oddly placed variable also stop. Larger
+    /// variables than 8 bytes are refused.
+    pub fn watch_variable(&mut self, name: &str) -> FdbResult<u32> {
+        let (addr, ty) = self.variable_address(name)?;
+        let len = ty.size();
+        let (base, size) = watch_range(addr, len).ok_or_else(|| FdbError::WatchTooLarge {
+            name: name.to_string(),
+            size: len,
+        })?;
+        let variable = WatchedVariable {
+            name: name.to_string(),
+            ty,
+            offset: (addr - base) as usize,
+        };
+        self.arm_watchpoint(base, size, WatchKind::Write, Some(variable))
+    }
+
+    fn arm_watchpoint(
+        &mut self,
+        addr: u64,
+        size: WatchSize,
+        kind: WatchKind,
+        variable: Option<WatchedVariable>,
+    ) -> FdbResult<u32> {
+        self.ensure_alive()?;
+        if !addr.is_multiple_of(size.bytes() as u64) {
+            return Err(FdbError::MisalignedWatchpoint {
+                addr,
+                size: size.bytes(),
+            });
+        }
+        let slot = (0..SLOTS)
+            .find(|slot| self.watchpoints.values().all(|wp| wp.slot != *slot))
+            .ok_or(FdbError::NoFreeDebugRegister)?;
+
+        let value = self.read_watched(addr, size)?;
+        self.write_debug_reg(slot, addr)?;
+        let mut dr7 = self.read_debug_reg(7)?;
+        dr7 &= !(0b11 << (slot * 2)) & !(0b1111 << (16 + slot * 4));
+        dr7 |= 1 << (slot * 2);
+        dr7 |= (kind.rw_bits() | size.len_bits() << 2) << (16 + slot * 4);
+        self.write_debug_reg(7, dr7)?;
+
+        let id = self.next_breakpoint_id;
+        self.next_breakpoint_id += 1;
+        self.watchpoints.insert(
+            id,
+            Watchpoint {
+                id,
+                addr,
+                size,
+                kind,
+                slot,
+                value,
+                variable,
+            },
+        );
+        Ok(id)
+    }
+
+    /// Disarm and forget watchpoint `id`.
+    pub fn remove_watchpoint(&mut self, id: u32) -> FdbResult<()> {
+        let wp = self
+            .watchpoints
+            .remove(&id)
+            .ok_or(FdbError::UnknownBreakpoint(id))?;
+        let dr7 = self.read_debug_reg(7)? & !(0b11 << (wp.slot * 2));
+        self.write_debug_reg(7, dr7)?;
+        self.write_debug_reg(wp.slot, 0)
+    }
+
+    /// All watchpoints ordered by id.
+    pub fn watchpoints(&self) -> impl Iterator<Item = &Watchpoint> {
+        self.watchpoints.values()
+    }
+
+    /// After a SIGTRAP, consult DR6 for a triggered watchpoint and clear it.
+    ///
+    /// Returns the watchpoint id with the previous and current contents.
+    pub(crate) fn triggered_watchpoint(&mut self) -> FdbResult<Option<(u32, u64, u64)>> {
+        if self.watchpoints.is_empty() {
+            return Ok(None);
+        }
+        let dr6 = self.read_debug_reg(6)?;
+        // The CPU never clears DR6, so reset it for the next stop.
+        self.write_debug_reg(6, 0)?;
+        let Some((id, addr, size)) = self
+            .watchpoints
+            .values()
+            .find(|wp| dr6 & (1 << wp.slot) != 0)
+            .map(|wp| (wp.id, wp.addr, wp.size))
+        else {
+            return Ok(None);
+        };
+
+        let new = self.read_watched(addr, size)?;
+        let wp = self
+            .watchpoints
+            .get_mut(&id)
+            .expect("watchpoint just found");
+        let old = std::mem::replace(&mut wp.value, new);
+        Ok(Some((id, old, new)))
+    }
+
+    /// Current DR0–DR3, DR6 and DR7, named as in the Intel manuals.
+    pub fn debug_registers(&self) -> FdbResult<Vec<(&'static str, u64)>> {
+        [
+            (0, "dr0"),
+            (1, "dr1"),
+            (2, "dr2"),
+            (3, "dr3"),
+            (6, "dr6"),
+            (7, "dr7"),
+        ]
+        .into_iter()
+        .map(|(index, name)| Ok((name, self.read_debug_reg(index)?)))
+        .collect()
+    }
+
+    fn read_watched(&self, addr: u64, size: WatchSize) -> FdbResult<u64> {
+        let bytes = self.read_memory(addr, size.bytes())?;
+        let mut raw = [0u8; 8];
+        raw[..bytes.len()].copy_from_slice(&bytes);
+        Ok(u64::from_le_bytes(raw))
+    }
+
+    /// Read debug register `index` of the current thread.
+    fn read_debug_reg(&self, index: usize) -> FdbResult<u64> {
+        let value = ptrace::read_user(self.tid, debug_reg_offset(index))?;
+        Ok(value as u64)
+    }
+
+    /// Write debug register `index` of every thread, so a watchpoint
+    /// triggers whichever thread makes the access. DR6 is per-thread status
+    /// and only touched on the current one.
+    fn write_debug_reg(&self, index: usize, value: u64) -> FdbResult<()> {
+        if index == 6 {
+            ptrace::write_user(self.tid, debug_reg_offset(index), value as c_long)?;
+            return Ok(());
+        }
+        for thread in &self.threads {
+            ptrace::write_user(thread.tid, debug_reg_offset(index), value as c_long)?;
+        }
+        Ok(())
+    }
+
+    /// Give a new thread the watchpoints the others have.
+    pub(crate) fn copy_debug_registers(&self, tid: Pid) -> FdbResult<()> {
+        if self.watchpoints.is_empty() {
+            return Ok(());
+        }
+        for index in [0, 1, 2, 3, 7] {
+            let value = self.read_debug_reg(index)?;
+            ptrace::write_user(tid, debug_reg_offset(index), value as c_long)?;
+        }
+        Ok(())
+    }
+}
+
+/// The smallest aligned range a debug register can watch that covers `len`
+/// bytes at `addr`.
+fn watch_range(addr: u64, len: u64) -> Option<(u64, WatchSize)> {
+    [
+        WatchSize::One,
+        WatchSize::Two,
+        WatchSize::Four,
+        WatchSize::Eight,
+    ]
+    .into_iter()
+    .find_map(|size| {
+        let bytes = size.bytes() as u64;
+        let base = addr & !(bytes - 1);
+        (addr + len <= base + bytes).then_some((base, size))
+    })
+}
+
+/// `PTRACE_PEEKUSER`/`POKEUSER` offset of debug register `index`.
+fn debug_reg_offset(index: usize) -> ptrace::AddressType {
+    (offset_of!(user, u_debugreg) + index * 8) as ptrace::AddressType
+}
@@ -1,6 +1,1899 @@
-//! Integration test harness placeholder.
+//! Integration tests driving real inferiors built from `tests/targets`.
+
+use std::collections::BTreeSet;
+use std::ffi::{CStr, CString};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use libfdb::breakpoint::BreakpointSpec;
+use libfdb::catchpoint::CatchKind;
+use libfdb::disasm::AsmSyntax;
+use libfdb::expr::Expr;
+use libfdb::fork::FollowForkMode;
+use libfdb::fpregs;
+use libfdb::process::{LaunchOptions, ProcessHandle, command_line, find_pid_by_name};
+use libfdb::registers::{REGISTERS, register_by_dwarf, register_by_name};
+use libfdb::signals::request_interrupt;
+use libfdb::syscalls::{SyscallCatch, SyscallEvent, syscall_name, syscall_number};
+use libfdb::threads::ThreadState;
+use libfdb::unwind::Frame;
+use libfdb::watchpoint::{WatchKind, WatchSize};
+use libfdb::{ExitStatus, FdbError, ProcessState, StopCause, StopReason};
+use nix::sys::signal::Signal;
+use object::{Object, ObjectSymbol};
+
+/// Compile `tests/targets/<name>.c` into the test scratch directory.
+///
+/// Tests run in parallel and may share a target, so each one is built only
+/// once; rebuilding could replace a binary another test is running.
+fn build_target(name: &str, flags: &[&str]) -> PathBuf {
+    static BUILT: Mutex<BTreeSet<PathBuf>> = Mutex::new(BTreeSet::new());
+    let src = Path::new(env!("CARGO_MANIFEST_DIR")).join(format!("../../tests/targets/{name}.c"));
+    let out = Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("{name}{}", flags.concat()));
+    let mut built = BUILT.lock().unwrap();
+    if built.contains(&out) {
+        return out;
+    }
+    let status = Command::new("cc")
+        .args(["-g", "-O0"])
+        .args(flags)
+        .arg("-o")
+        .arg(&out)
+        .arg(&src)
+        .status()
+        .expect("failed to run cc");
+    assert!(status.success(), "compiling {name}.c failed");
+    built.insert(out.clone());
+    out
+}
+
+/// Look up the static address of a symbol in the compiled target.
+fn symbol_addr(path: &Path, name: &str) -> u64 {
+    let data = std::fs::read(path).unwrap();
+    let file = object::File::parse(&*data).unwrap();
+    file.symbols()
+        .find(|s| s.name() == Ok(name))
+        .map(|s| s.address())
+        .unwrap_or_else(|| panic!("symbol {name} not found"))
+}
+
+fn launch(path: &Path) -> ProcessHandle {
+    let prog = CString::new(path.to_str().unwrap()).unwrap();
+    ProcessHandle::launch(&prog, &[prog.as_c_str()]).unwrap()
+}
 
 #[test]
 fn lib_version_available() {
     assert!(!libfdb::version().is_empty());
 }
+
+#[test]
+fn breakpoint_in_loop_hits_repeatedly() {
+    let target = build_target("loop", &["-no-pie"]);
+    let mut process = launch(&target);
+    let id = process
+        .set_breakpoint(symbol_addr(&target, "tick"))
+        .unwrap();
+
+    for _ in 0..3 {
+        process.resume().unwrap();
+        match process.wait_on_signal().unwrap() {
+            ProcessState::Stopped(reason) => {
+                assert_eq!(reason.signal, Signal::SIGTRAP);
+                assert_eq!(reason.hit_breakpoint(), Some(id));
+            }
+            other => panic!("expected breakpoint stop, got {other:?}"),
+        }
+    }
+
+    process.resume().unwrap();
+    assert!(matches!(
+        process.wait_on_signal().unwrap(),
+        ProcessState::Exited(3)
+    ));
+}
+
+#[test]
+fn register_lookups_round_trip() {
+    for info in REGISTERS {
+        assert_eq!(register_by_name(info.name), Some(info));
+        if let Some(dwarf) = info.dwarf_id {
+            assert_eq!(register_by_dwarf(dwarf), Some(info));
+        }
+    }
+    assert_eq!(register_by_name("eflags").unwrap().name, "rflags");
+    assert_eq!(register_by_dwarf(7).unwrap().name, "rsp");
+    assert!(register_by_name("xmm0").is_none());
+}
+
+#[test]
+fn search_memory_finds_data_across_chunks() {
+    let target = build_target("vars", &["-no-pie"]);
+    let mut process = launch(&target);
+    let name = symbol_addr(&target, "global_name");
+    // The environment on the stack may hold more copies.
+    let found = process.search_memory(0, u64::MAX, b"fdb\0").unwrap();
+    assert_eq!(found.first(), Some(&name));
+    assert!(process.search_memory(0, name, b"fdb\0").unwrap().is_empty());
+
+    // Plant a pattern straddling the first 64 KiB chunk of the stack.
+    let stack = process
+        .memory_map()
+        .unwrap()
+        .into_iter()
+        .find(|region| region.pathname.as_deref() == Some("[stack]"))
+        .unwrap();
+    let needle = b"\xfd\xb0needle\x0b\xdf";
+    let addr = stack.start + 0x10000 - 3;
+    process.write_memory(addr, needle).unwrap();
+    assert_eq!(
+        process
+            .search_memory(stack.start, stack.end, needle)
+            .unwrap(),
+        [addr]
+    );
+    assert!(matches!(
+        process.search_memory(0, u64::MAX, b""),
+        Err(FdbError::EmptySearchPattern)
+    ));
+}
+
+#[test]
+fn dump_memory_writes_readable_bytes_to_a_file() {
+    let target = build_target("vars", &["-no-pie"]);
+    let process = launch(&target);
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR"));
+    let path = dir.join("global_name.bin");
+    let name = symbol_addr(&target, "global_name");
+    process.dump_memory(name, 4, &path).unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"fdb\0");
+
+    // The last page of the stack runs into unmapped memory.
+    let stack = process
+        .memory_map()
+        .unwrap()
+        .into_iter()
+        .find(|region| region.pathname.as_deref() == Some("[stack]"))
+        .unwrap();
+    let path = dir.join("stack_tail.bin");
+    let start = stack.end - 0x100;
+    match process.dump_memory(start, 0x180, &path) {
+        Err(FdbError::PartialDump {
+            requested: 0x180,
+            written: 0x100,
+            ..
+        }) => {}
+        other => panic!("expected a partial dump, got {other:?}"),
+    }
+    assert_eq!(
+        std::fs::read(&path).unwrap(),
+        process.read_memory(start, 0x100).unwrap()
+    );
+}
+
+#[test]
+fn read_c_string_stops_at_nul_limit_and_unmapped_memory() {
+    let target = build_target("vars", &["-no-pie"]);
+    let mut process = launch(&target);
+    let name = symbol_addr(&target, "global_name");
+    assert_eq!(process.read_c_string(name, 200).unwrap(), "fdb");
+    assert_eq!(process.read_c_string(name, 2).unwrap(), "fd");
+
+    let msg = symbol_addr(&target, "global_msg");
+    let ptr = u64::from_le_bytes(process.read_memory(msg, 8).unwrap().try_into().unwrap());
+    assert_eq!(process.read_c_string(ptr, 200).unwrap(), "hello");
+
+    // A string without a NUL before the end of the stack ends there.
+    let stack = process
+        .memory_map()
+        .unwrap()
+        .into_iter()
+        .find(|region| region.pathname.as_deref() == Some("[stack]"))
+        .unwrap();
+    process.write_memory(stack.end - 4, b"tail").unwrap();
+    assert_eq!(process.read_c_string(stack.end - 4, 200).unwrap(), "tail");
+    assert!(matches!(
+        process.read_c_string(stack.end, 200),
+        Err(FdbError::PartialRead { .. })
+    ));
+}
+
+#[test]
+fn read_memory_of_a_huge_range_stops_at_unmapped_memory() {
+    let target = build_target("vars", &["-no-pie"]);
+    let process = launch(&target);
+    let stack = process
+        .memory_map()
+        .unwrap()
+        .into_iter()
+        .find(|region| region.pathname.as_deref() == Some("[stack]"))
+        .unwrap();
+    match process.read_memory(stack.end - 0x10, usize::MAX) {
+        Err(FdbError::PartialRead {
+            requested: usize::MAX,
+            data,
+            ..
+        }) => assert_eq!(data.len(), 0x10),
+        other => panic!("expected a partial read, got {other:?}"),
+    }
+}
+
+#[test]
+fn restore_memory_writes_file_into_writable_mappings() {
+    let target = build_target("vars", &["-no-pie"]);
+    let mut process = launch(&target);
+    let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("restore_name.bin");
+    std::fs::write(&path, b"gdb").unwrap();
+
+    let name = symbol_addr(&target, "global_name");
+    process.restore_memory(name, &path).unwrap();
+    assert_eq!(process.read_memory(name, 4).unwrap(), b"gdb\0");
+
+    let main = symbol_addr(&target, "main");
+    let code = process.read_memory(main, 3).unwrap();
+    assert!(matches!(
+        process.restore_memory(main, &path),
+        Err(FdbError::NotWritable(addr)) if addr == main
+    ));
+    assert_eq!(process.read_memory(main, 3).unwrap(), code);
+    assert!(matches!(
+        process.restore_memory(0x10, &path),
+        Err(FdbError::NotWritable(0x10))
+    ));
+
+    std::fs::write(&path, [0u8; 32]).unwrap();
+    assert!(matches!(
+        process.restore_memory(0xffff_ffff_ffff_fff0, &path),
+        Err(FdbError::AddressOverflow {
+            addr: 0xffff_ffff_ffff_fff0,
+            len: 32
+        })
+    ));
+}
+
+#[test]
+fn fp_registers_read_back_what_was_written() {
+    let target = build_target("loop", &["-no-pie"]);
+    let mut process = launch(&target);
+    let fp = process.get_fpregs().unwrap();
+    assert_eq!(fp.mxcsr(), 0x1f80);
+    assert_eq!(fp.fctrl(), 0x37f);
+    let rip = process.get_registers().unwrap().rip();
+
+    let value = 0x0123_4567_89ab_cdef_fedc_ba98_7654_3210;
+    process.set_fpreg("xmm7", value).unwrap();
+    let mut st = [0; 16];
+    st[..10].copy_from_slice(&fpregs::f64_to_x87(-1.25));
+    process.set_fpreg("st3", u128::from_le_bytes(st)).unwrap();
+    process.set_fpreg("mxcsr", 0x1f81).unwrap();
+
+    let fp = process.get_fpregs().unwrap();
+    assert_eq!(fp.xmm(7), Some(value));
+    assert_eq!(fp.get("xmm7").unwrap(), value);
+    assert_eq!(fpregs::x87_to_f64(fp.st(3).unwrap()), -1.25);
+    assert_eq!(fp.mxcsr(), 0x1f81);
+    // General-purpose registers are untouched.
+    assert_eq!(process.get_registers().unwrap().rip(), rip);
+
+    assert!(matches!(
+        process.set_fpreg("fctrl", 0x1_0000),
+        Err(FdbError::RegisterValueTooWide { bits: 16, .. })
+    ));
+    assert!(matches!(
+        process.set_fpreg("xmm16", 0),
+        Err(FdbError::UnknownRegister(_))
+    ));
+}
+
+#[test]
+fn pc_sp_and_fp_match_the_register_snapshot() {
+    let target = build_target("loop", &["-no-pie"]);
+    let mut process = launch(&target);
+    let regs = process.get_registers().unwrap();
+    assert_eq!(process.pc().unwrap(), regs.rip());
+    assert_eq!(process.sp().unwrap(), regs.rsp());
+    assert_eq!(process.fp().unwrap(), regs.rbp());
+
+    let main = symbol_addr(&target, "main");
+    process.set_pc(main).unwrap();
+    assert_eq!(process.pc().unwrap(), main);
+    // Only the program counter moves.
+    assert_eq!(process.sp().unwrap(), regs.rsp());
+}
+
+#[test]
+fn write_memory_across_word_boundary() {
+    let target = build_target("loop", &["-no-pie"]);
+    let mut process = launch(&target);
+    let rsp = process.get_registers().unwrap().rsp();
+
+    let before = process.read_memory(rsp, 16).unwrap();
+    process
+        .write_memory(rsp + 6, &[0xde, 0xad, 0xbe, 0xef])
+        .unwrap();
+    let after = process.read_memory(rsp, 16).unwrap();
+
+    assert_eq!(&after[6..10], &[0xde, 0xad, 0xbe, 0xef]);
+    assert_eq!(&after[..6], &before[..6]);
+    assert_eq!(&after[10..], &before[10..]);
+}
+
+#[test]
+fn watchpoint_reports_old_and_new_values() {
+    let target = build_target("loop", &["-no-pie"]);
+    let mut process = launch(&target);
+    let counter = symbol_addr(&target, "counter");
+    let id = process
+        .set_watchpoint(counter, WatchSize::Four, WatchKind::Write)
+        .unwrap();
+
+    for expected in 1..=3 {
+        process.resume().unwrap();
+        match process.wait_on_signal().unwrap() {
+            ProcessState::Stopped(reason) => assert_eq!(
+                reason.cause,
+                StopCause::Watchpoint {
+                    id,
+                    old: expected - 1,
+                    new: expected
+                }
+            ),
+            other => panic!("expected watchpoint stop, got {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn watching_a_variable_reports_typed_values() {
+    let target = build_target("vars", &[]);
+    let mut process = launch(&target);
+    let global = process.watch_variable("global_int").unwrap();
+    let flag = process.watch_variable("global_flag").unwrap();
+    let wp = process.watchpoints().find(|wp| wp.id() == flag).unwrap();
+    // One byte is watched as it is.
+    assert_eq!(wp.size(), WatchSize::One);
+    assert_eq!(wp.variable(), Some("global_flag"));
+    assert!(matches!(
+        process.watch_variable("sum"),
+        Err(FdbError::UnknownVariable(_))
+    ));
+
+    process.set_breakpoint_at_function("compute").unwrap();
+    process.resume().unwrap();
+    process.wait_on_signal().unwrap();
+    let sum = process.watch_variable("sum").unwrap();
+
+    let mut hits = Vec::new();
+    for _ in 0..2 {
+        process.resume().unwrap();
+        let ProcessState::Stopped(reason) = process.wait_on_signal().unwrap() else {
+            panic!("expected a watchpoint stop");
+        };
+        let StopCause::Watchpoint { id, old, new } = reason.cause else {
+            panic!("expected a watchpoint stop, got {reason:?}");
+        };
+        let wp = process.watchpoints().find(|wp| wp.id() == id).unwrap();
+        let (old, new) = (wp.typed_value(old).unwrap(), wp.typed_value(new).unwrap());
+        hits.push((id, old.to_string(), new.to_string()));
+    }
+    // The old value of a local is whatever the stack held.
+    assert_eq!((hits[0].0, hits[0].2.as_str()), (sum, "7"));
+    assert_eq!(hits[1], (global, "-42".into(), "14".into()));
+}
+
+#[test]
+fn symbols_resolve_in_pie_binary() {
+    let target = build_target("loop", &["-pie", "-fPIE"]);
+    let mut process = launch(&target);
+    let tick = process.resolve_symbol("tick").expect("tick not found");
+    assert_ne!(
+        tick,
+        symbol_addr(&target, "tick"),
+        "PIE should be relocated"
+    );
+
+    let id = process.set_breakpoint(tick).unwrap();
+    process.resume().unwrap();
+    match process.wait_on_signal().unwrap() {
+        ProcessState::Stopped(reason) => assert_eq!(reason.hit_breakpoint(), Some(id)),
+        other => panic!("expected breakpoint stop, got {other:?}"),
+    }
+
+    let pc = process.get_registers().unwrap().rip();
+    let sym = process.symbol_at(pc).expect("no symbol at pc");
+    assert_eq!(sym.name, "tick");
+    assert_eq!(sym.address, tick);
+    assert!(
+        process
+            .symbol_at(tick + sym.size)
+            .is_none_or(|s| s.name != "tick")
+    );
+}
+
+#[test]
+fn exported_breakpoints_reload_symbolically() {
+    let target = build_target("loop", &["-pie", "-fPIE"]);
+    let mut process = launch(&target);
+    process.set_breakpoint_at_function("main").unwrap();
+    let line = process.set_breakpoint_at_line("loop.c", 7).unwrap();
+    process
+        .set_breakpoint_condition(line, "counter == 2")
+        .unwrap();
+    process.set_ignore_count(line, 1).unwrap();
+    process.disable_breakpoint(line).unwrap();
+    let tick = process.resolve_symbol("tick").unwrap();
+    process.set_breakpoint(tick + 1).unwrap();
+    process.set_temp_breakpoint(tick + 2).unwrap();
+
+    let specs = process.export_breakpoints();
+    let lines: Vec<String> = specs.iter().map(ToString::to_string).collect();
+    assert_eq!(lines[0], "break main");
+    assert!(lines[1].ends_with("loop.c:7 disabled ignore 1 if counter == 2"));
+    assert_eq!(lines[2], "break *tick+0x1");
+    assert_eq!(lines[3], "tbreak *tick+0x2");
+    for (line, spec) in lines.iter().zip(&specs) {
+        assert_eq!(&line.parse::<BreakpointSpec>().unwrap(), spec);
+    }
+    assert!(matches!(
+        "watch x".parse::<BreakpointSpec>(),
+        Err(FdbError::InvalidBreakpointSpec(_))
+    ));
+
+    let mut relaunched = launch(&target);
+    let ids: Vec<u32> = relaunched
+        .import_breakpoints(&specs)
+        .into_iter()
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(ids, [1, 2, 3, 4]);
+    assert_eq!(relaunched.export_breakpoints(), specs);
+    let tick = relaunched.resolve_symbol("tick").unwrap();
+    let addrs: Vec<u64> = relaunched.breakpoints().map(|bp| bp.addr()).collect();
+    assert_eq!(addrs[2..], [tick + 1, tick + 2]);
+}
+
+#[test]
+fn run_to_entry_stops_at_start_past_the_loader() {
+    let target = build_target("loop", &["-pie", "-fPIE"]);
+    let mut process = launch(&target);
+    let entry = process.entry_point().unwrap();
+    assert_eq!(entry, symbol_addr(&target, "_start") + process.load_bias());
+    assert_ne!(process.get_registers().unwrap().rip(), entry);
+    let state = process.run_to_entry().unwrap();
+    assert!(matches!(
+        state,
+        ProcessState::Stopped(StopReason {
+            cause: StopCause::Signal,
+            ..
+        })
+    ));
+    assert_eq!(process.get_registers().unwrap().rip(), entry);
+    assert_eq!(process.breakpoints().count(), 0);
+
+    let target = build_target("loop", &["-static"]);
+    let mut process = launch(&target);
+    let entry = process.entry_point().unwrap();
+    assert_eq!(entry, symbol_addr(&target, "_start"));
+    assert_eq!(process.get_registers().unwrap().rip(), entry);
+    assert!(matches!(
+        process.run_to_entry().unwrap(),
+        ProcessState::Initialized
+    ));
+}
+
+#[test]
+fn shared_libraries_list_each_file_once() {
+    let target = build_target("loop", &["-no-pie"]);
+    let mut process = launch(&target);
+    process
+        .set_breakpoint(symbol_addr(&target, "main"))
+        .unwrap();
+    process.resume().unwrap();
+    process.wait_on_signal().unwrap();
+
+    let libs = process.shared_libraries().unwrap();
+    let exe = process.executable().unwrap();
+    assert!(libs.iter().all(|lib| Path::new(&lib.path) != exe));
+    let libc: Vec<_> = libs
+        .iter()
+        .filter(|lib| lib.path.contains("libc.so"))
+        .collect();
+    assert_eq!(libc.len(), 1);
+    let lowest = process
+        .memory_map()
+        .unwrap()
+        .into_iter()
+        .filter(|region| region.pathname.as_deref() == Some(libc[0].path.as_str()))
+        .map(|region| region.start)
+        .min()
+        .unwrap();
+    assert_eq!(libc[0].base, lowest);
+    assert!(!libc[0].symbols_loaded);
+}
+
+#[test]
+fn symbols_resolve_in_shared_libraries_once_loaded() {
+    let target = build_target("loop", &["-pie", "-fPIE"]);
+    let mut process = launch(&target);
+    assert_eq!(process.resolve_symbol("printf"), None);
+    let main = process.resolve_symbol("main").unwrap();
+    process.set_breakpoint(main).unwrap();
+    process.resume().unwrap();
+    process.wait_on_signal().unwrap();
+
+    let printf = process.resolve_symbol("printf").unwrap();
+    let libs = process.shared_libraries().unwrap();
+    let libc = libs
+        .iter()
+        .find(|lib| lib.path.contains("libc.so"))
+        .unwrap();
+    assert!((libc.base..libc.end).contains(&printf));
+    assert!(libc.symbols_loaded);
+    assert_eq!(process.symbol_at(printf).unwrap().address, printf);
+    assert_eq!(process.symbol_at(main).unwrap().name, "main");
+
+    let names = process.function_names();
+    assert!(names.is_sorted());
+    assert!(names.iter().any(|name| name == "main"));
+    assert!(names.iter().any(|name| name == "printf"));
+}
+
+#[test]
+fn jump_steps_over_its_target_and_drops_the_stop_signal() {
+    let target = build_target("loop", &["-no-pie"]);
+    let mut process = launch(&target);
+    let counter = symbol_addr(&target, "counter");
+    let read_counter = |process: &ProcessHandle| {
+        let bytes = process.read_memory(counter, 4).unwrap();
+        u32::from_le_bytes(bytes.try_into().unwrap())
+    };
+    let id = process.set_breakpoint_at_function("tick").unwrap();
+    process.resume().unwrap();
+    process.wait_on_signal().unwrap();
+    assert_eq!(read_counter(&process), 0);
+
+    // Jumping onto the breakpoint runs tick rather than trapping again.
+    let pc = process.get_registers().unwrap().rip();
+    process.jump(pc).unwrap();
+    match process.wait_on_signal().unwrap() {
+        ProcessState::Stopped(reason) => assert_eq!(reason.hit_breakpoint(), Some(id)),
+        other => panic!("expected the second call to tick, got {other:?}"),
+    }
+    assert_eq!(read_counter(&process), 1);
+
+    process.delete_breakpoint(id).unwrap();
+    process.send_signal(Signal::SIGUSR1).unwrap();
+    process.resume().unwrap();
+    match process.wait_on_signal().unwrap() {
+        ProcessState::Stopped(reason) => assert_eq!(reason.signal, Signal::SIGUSR1),
+        other => panic!("expected SIGUSR1, got {other:?}"),
+    }
+    let pc = process.get_registers().unwrap().rip();
+    process.jump(pc).unwrap();
+    assert!(matches!(
+        process.wait_on_signal().unwrap(),
+        ProcessState::Exited(3)
+    ));
+}
+
+#[test]
+fn function_breakpoint_skips_prologue() {
+    let target = build_target("loop", &["-no-pie"]);
+    let mut process = launch(&target);
+    assert_eq!(process.load_bias(), 0);
+    assert!(matches!(
+        process.set_breakpoint_at_function("no_such_function"),
+        Err(FdbError::UnknownSymbol(_))
+    ));
+
+    let id = process.set_breakpoint_at_function("tick").unwrap();
+    process.resume().unwrap();
+    match process.wait_on_signal().unwrap() {
+        ProcessState::Stopped(reason) => assert_eq!(reason.hit_breakpoint(), Some(id)),
+        other => panic!("expected breakpoint stop, got {other:?}"),
+    }
+    let pc = process.get_registers().unwrap().rip();
+    assert!(pc > symbol_addr(&target, "tick"));
+    assert_eq!(process.symbol_at(pc).unwrap().name, "tick");
+}
+
+#[test]
+fn memory_map_describes_text_and_stack() {
+    let target = build_target("loop", &["-pie", "-fPIE"]);
+    let process = launch(&target);
+    let map = process.memory_map().unwrap();
+
+    let tick = process.resolve_symbol("tick").unwrap();
+    let text = map
+        .iter()
+        .find(|r| r.contains(tick))
+        .expect("tick unmapped");
+    assert!(text.perms.read && text.perms.execute && !text.perms.write);
+    assert_eq!(text.pathname.as_deref(), target.to_str());
+
+    let rsp = process.get_registers().unwrap().rsp();
+    let stack = map.iter().find(|r| r.contains(rsp)).expect("rsp unmapped");
+    assert_eq!(stack.pathname.as_deref(), Some("[stack]"));
+    assert!(stack.perms.write && !stack.perms.shared);
+}
+
+#[test]
+fn line_table_maps_both_ways() {
+    let target = build_target("loop", &["-pie", "-fPIE"]);
+    let process = launch(&target);
+
+    // Line 7 is the call to `tick()` inside the loop.
+    let call = process
+        .line_to_addr("loop.c", 7)
+        .expect("no code for line 7");
+    let (file, line) = process.addr_to_line(call).unwrap();
+    assert!(file.ends_with("loop.c"));
+    assert_eq!(line, 7);
+    let main = process.resolve_symbol("main").unwrap();
+    assert_eq!(process.symbol_at(call).unwrap().address, main);
+
+    // Line 4 is blank, so it resolves to `main` on the next line.
+    assert_eq!(process.line_to_addr("targets/loop.c", 4), Some(main));
+    assert_eq!(process.line_to_addr("other.c", 7), None);
+}
+
+#[test]
+fn line_breakpoint_hits_each_iteration() {
+    let target = build_target("loop", &["-no-pie"]);
+    let mut process = launch(&target);
+    assert!(matches!(
+        process.set_breakpoint_at_line("missing.c", 1),
+        Err(FdbError::UnknownSourceFile(_))
+    ));
+    assert!(matches!(
+        process.set_breakpoint_at_line("loop.c", 99),
+        Err(FdbError::NoCodeForLine { line: 99, .. })
+    ));
+
+    let id = process.set_breakpoint_at_line("loop.c", 7).unwrap();
+    for _ in 0..3 {
+        process.resume().unwrap();
+        match process.wait_on_signal().unwrap() {
+            ProcessState::Stopped(reason) => assert_eq!(reason.hit_breakpoint(), Some(id)),
+            other => panic!("expected breakpoint stop, got {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn last_stop_resolves_pc_function_and_line() {
+    let target = build_target("loop", &["-no-pie"]);
+    let mut process = launch(&target);
+    assert!(process.last_stop().is_none());
+    let tick = symbol_addr(&target, "tick");
+    let id = process.set_breakpoint_at_function("tick").unwrap();
+
+    process.resume().unwrap();
+    assert!(process.last_stop().is_none());
+    process.wait_on_signal().unwrap();
+    let event = process.last_stop().unwrap();
+    assert_eq!(event.hit_breakpoint(), Some(id));
+    assert_eq!(event.tid.as_raw(), process.pid().as_raw());
+    assert_eq!(event.pc, process.get_registers().unwrap().rip());
+    assert!(event.pc > tick);
+    assert_eq!(event.function.as_deref(), Some("tick"));
+    let (file, line) = event.location.clone().unwrap();
+    assert!(file.ends_with("loop.c"));
+    assert_eq!(line, 3);
+
+    // The hidden breakpoint used by `finish` is not reported as a hit.
+    process.step_out().unwrap();
+    let event = process.last_stop().unwrap();
+    assert_eq!(event.cause, StopCause::Signal);
+    assert_eq!(event.function.as_deref(), Some("main"));
+
+    process.delete_breakpoint(id).unwrap();
+    process.resume().unwrap();
+    process.wait_on_signal().unwrap();
+    assert!(process.last_stop().is_none());
+}
+
+#[test]
+fn backtrace_follows_frame_pointers() {
+    let target = build_target("loop", &["-no-pie"]);
+    let mut process = launch(&target);
+    process.set_breakpoint_at_function("tick").unwrap();
+    process.resume().unwrap();
+    process.wait_on_signal().unwrap();
+
+    let frames = process.backtrace().unwrap();
+    let names: Vec<_> = frames.iter().map(|f| f.function.as_deref()).collect();
+    assert_eq!(names[..2], [Some("tick"), Some("main")]);
+    assert_eq!(process.addr_to_line(frames[1].pc).unwrap().1, 6);
+}
+
+#[test]
+fn run_until_stops_at_location_or_frame_return() {
+    let target = build_target("nested", &[]);
+    let mut process = launch(&target);
+    let id = process.set_breakpoint_at_function("leaf").unwrap();
+    process.resume().unwrap();
+    process.wait_on_signal().unwrap();
+    process.delete_breakpoint(id).unwrap();
+
+    // `leaf` returns to `middle` before line 12 is ever reached.
+    let return_line = process.line_to_addr("nested.c", 12).unwrap();
+    let state = process.run_until(return_line).unwrap();
+    assert!(matches!(
+        state,
+        ProcessState::Stopped(StopReason {
+            cause: StopCause::Signal,
+            ..
+        })
+    ));
+    let event = process.last_stop().unwrap();
+    assert_eq!(event.function.as_deref(), Some("middle"));
+    assert_eq!(event.location.as_ref().unwrap().1, 10);
+    for addr in [event.pc, return_line] {
+        assert_ne!(process.read_memory(addr, 1).unwrap(), [0xcc]);
+    }
+
+    let state = process.run_until(return_line).unwrap();
+    assert!(matches!(
+        state,
+        ProcessState::Stopped(StopReason {
+            cause: StopCause::Signal,
+            ..
+        })
+    ));
+    assert_eq!(process.get_registers().unwrap().rip(), return_line);
+    assert_eq!(process.breakpoints().count(), 0);
+}
+
+#[test]
+fn backtrace_uses_cfi_without_frame_pointers() {
+    let target = build_target("nested", &["-O2", "-fomit-frame-pointer"]);
+    let mut process = launch(&target);
+    process.set_breakpoint_at_function("leaf").unwrap();
+    process.resume().unwrap();
+    process.wait_on_signal().unwrap();
+
+    let frames = process.backtrace().unwrap();
+    let names: Vec<_> = frames.iter().map(|f| f.function.as_deref()).collect();
+    assert_eq!(names[..3], [Some("leaf"), Some("middle"), Some("main")]);
+}
+
+#[test]
+fn frame_info_locates_saved_registers() {
+    let target = build_target("nested", &[]);
+    let mut process = launch(&target);
+    process.set_breakpoint_at_function("leaf").unwrap();
+    process.resume().unwrap();
+    process.wait_on_signal().unwrap();
+
+    let regs = process.get_registers().unwrap();
+    let info = process.current_frame_info().unwrap();
+    assert_eq!(info.function.as_deref(), Some("leaf"));
+    assert!(info.from_cfi);
+    assert_eq!(info.sp, regs.rsp());
+    assert_eq!(info.cfa, Some(regs.rbp() + 16));
+    let caller = process.backtrace().unwrap()[1].pc;
+    assert_eq!(info.saved_rip, Some((regs.rbp() + 8, caller)));
+    assert_eq!(info.saved_registers, [("rbp", regs.rbp())]);
+    assert!(info.caller_cfa.unwrap() > info.cfa.unwrap());
+}
+
+#[test]
+fn outer_frames_have_their_own_registers_and_variables() {
+    let target = build_target("nested", &[]);
+    let mut process = launch(&target);
+    process.set_breakpoint_at_function("leaf").unwrap();
+    process.resume().unwrap();
+    process.wait_on_signal().unwrap();
+    // The second call, from `int b = leaf(a);`.
+    process.resume().unwrap();
+    process.wait_on_signal().unwrap();
+
+    let frames = process.backtrace().unwrap();
+    let caller = process.frame_registers(1).unwrap();
+    assert_eq!(caller.rip(), frames[1].pc);
+    assert!(caller.rsp() > process.get_registers().unwrap().rsp());
+    assert_eq!(process.read_variable("x").unwrap().to_string(), "3");
+    assert_eq!(
+        process.read_variable_in_frame("x", 1).unwrap().to_string(),
+        "1"
+    );
+    assert_eq!(
+        process.read_variable_in_frame("a", 1).unwrap().to_string(),
+        "3"
+    );
+    assert!(matches!(
+        process.read_variable_in_frame("a", 0),
+        Err(FdbError::UnknownVariable(_))
+    ));
+    let locals = process.locals_in_frame(1).unwrap();
+    assert!(locals.iter().any(|(name, _)| name == "a"));
+    assert!(matches!(
+        process.frame_registers(frames.len()),
+        Err(FdbError::NoSuchFrame(_))
+    ));
+}
+
+#[test]
+fn backtrace_shows_arguments_lines_and_locals() {
+    let target = build_target("nested", &[]);
+    let mut process = launch(&target);
+    process.set_breakpoint_at_function("leaf").unwrap();
+    for _ in 0..2 {
+        process.resume().unwrap();
+        process.wait_on_signal().unwrap();
+    }
+
+    let frames = process.backtrace().unwrap();
+    let args = |frame: &Frame| frame.arguments.clone();
+    assert_eq!(args(&frames[0]), [("x".to_string(), "3".to_string())]);
+    assert_eq!(args(&frames[1]), [("x".to_string(), "1".to_string())]);
+    assert!(args(&frames[2]).is_empty());
+    let line = |frame: &Frame| frame.location.as_ref().map(|(_, line)| *line);
+    assert_eq!(line(&frames[0]), Some(5));
+    // The return address of `int b = leaf(a);`.
+    assert_eq!(line(&frames[1]), Some(11));
+    assert!(frames.iter().all(|frame| frame.locals.is_none()));
+
+    let full = process.backtrace_full().unwrap();
+    let locals = full[1].locals.as_ref().unwrap();
+    assert!(locals.contains(&("a".to_string(), "3".to_string())));
+    assert_eq!(full[0].locals.as_deref(), Some(&[][..]));
+}
+
+#[test]
+fn backtrace_stops_at_its_limit_and_at_repeated_frames() {
+    let target = build_target("nested", &[]);
+    let mut process = launch(&target);
+    process.set_breakpoint_at_function("leaf").unwrap();
+    process.resume().unwrap();
+    process.wait_on_signal().unwrap();
+
+    let whole = process.backtrace().unwrap();
+    assert!(whole.error().is_none());
+    process.set_backtrace_limit(2);
+    let cut = process.backtrace().unwrap();
+    assert_eq!(cut.len(), 2);
+    assert_eq!(cut[1].pc, whole[1].pc);
+    assert!(matches!(cut.error(), Some(FdbError::BacktraceLimit(2))));
+    process.set_backtrace_limit(0);
+
+    // Point leaf's saved RBP at itself, so middle's frame seems to call
+    // itself forever.
+    let rbp = process.get_registers().unwrap().rbp();
+    process.write_memory(rbp, &rbp.to_le_bytes()).unwrap();
+    let looped = process.backtrace().unwrap();
+    assert_eq!(looped.len(), 2);
+    assert_eq!(looped[1].function.as_deref(), Some("middle"));
+    assert!(matches!(
+        looped.error(),
+        Some(FdbError::RepeatedFrame { pc, .. }) if *pc == whole[1].pc
+    ));
+}
+
+#[test]
+fn stepping_n_instructions_stops_early_at_breakpoints_and_exit() {
+    let target = build_target("nested", &[]);
+    let mut process = launch(&target);
+    process.set_breakpoint_at_function("middle").unwrap();
+    process.resume().unwrap();
+    process.wait_on_signal().unwrap();
+
+    let state = process.single_step_n(3).unwrap();
+    assert!(matches!(state, ProcessState::Stopped(_)));
+    assert_eq!(process.steps_completed(), 3);
+
+    let leaf = process.set_breakpoint_at_function("leaf").unwrap();
+    let leaf_addr = process
+        .breakpoints()
+        .find(|bp| bp.id() == leaf)
+        .unwrap()
+        .addr();
+    process.single_step_n(1000).unwrap();
+    assert_eq!(process.get_registers().unwrap().rip(), leaf_addr);
+    assert!((1..1000).contains(&process.steps_completed()));
+
+    process.delete_breakpoint(leaf).unwrap();
+    let state = process.step_over_n(u32::MAX).unwrap();
+    assert!(matches!(state, ProcessState::Exited(0)));
+    assert!(process.steps_completed() < u32::MAX);
+}
+
+#[test]
+fn stepping_n_lines_counts_the_lines_stepped() {
+    let target = build_target("loop", &["-no-pie"]);
+    let mut process = launch(&target);
+    process.set_breakpoint_at_function("main").unwrap();
+    process.resume().unwrap();
+    process.wait_on_signal().unwrap();
+
+    // The loop header, then the call into tick.
+    process.step_line_n(2).unwrap();
+    assert_eq!(process.steps_completed(), 2);
+    let pc = process.pc().unwrap();
+    assert_eq!(process.symbol_at(pc).unwrap().name, "tick");
+
+    let state = process.step_line_n(1000).unwrap();
+    assert!(matches!(state, ProcessState::Exited(3)));
+    assert!(process.steps_completed() < 1000);
+}
+
+#[test]
+fn force_return_restores_the_caller_frame() {
+    let target = build_target("nested", &["-O2", "-fomit-frame-pointer"]);
+    let mut process = launch(&target);
+    let id = process.set_breakpoint_at_function("leaf").unwrap();
+    process.resume().unwrap();
+    process.wait_on_signal().unwrap();
+
+    let regs = process.get_registers().unwrap();
+    let caller = process.backtrace().unwrap()[1].clone();
+    process.force_return(Some(100)).unwrap();
+    let regs_after = process.get_registers().unwrap();
+    assert_eq!(regs_after.rip(), caller.pc);
+    // The call's return address is popped off the stack.
+    assert_eq!(regs_after.rsp(), regs.rsp() + 8);
+    assert_eq!(regs_after.rax(), 100);
+
+    // middle goes on to call leaf with the value it was given.
+    process.resume().unwrap();
+    match process.wait_on_signal().unwrap() {
+        ProcessState::Stopped(reason) => assert_eq!(reason.hit_breakpoint(), Some(id)),
+        other => panic!("expected the second call to leaf, got {other:?}"),
+    }
+    assert_eq!(process.get_registers().unwrap().rdi(), 100);
+    process.delete_breakpoint(id).unwrap();
+    process.resume().unwrap();
+    assert!(matches!(
+        process.wait_on_signal().unwrap(),
+        ProcessState::Exited(0)
+    ));
+}
+
+#[test]
+fn syscall_stops_report_entry_and_exit() {
+    let target = build_target("loop", &["-no-pie"]);
+    let mut process = launch(&target);
+
+    let entry = match process.resume_to_syscall().unwrap() {
+        ProcessState::Stopped(reason) => reason.cause,
+        other => panic!("expected syscall stop, got {other:?}"),
+    };
+    let StopCause::Syscall(SyscallEvent::Entry { number, .. }) = entry else {
+        panic!("expected syscall entry, got {entry:?}");
+    };
+    match process.resume_to_syscall().unwrap() {
+        ProcessState::Stopped(reason) => assert!(matches!(
+            reason.cause,
+            StopCause::Syscall(SyscallEvent::Exit { number: n, .. }) if n == number
+        )),
+        other => panic!("expected syscall stop, got {other:?}"),
+    }
+
+    let exit_group = syscall_number("exit_group").unwrap();
+    assert_eq!(syscall_name(exit_group), Some("exit_group"));
+    process.set_syscall_catch(Some(SyscallCatch::Only(vec![exit_group])));
+    process.resume().unwrap();
+    match process.wait_on_signal().unwrap() {
+        ProcessState::Stopped(reason) => assert!(matches!(
+            reason.cause,
+            StopCause::Syscall(SyscallEvent::Entry { number, args }) if number == exit_group && args[0] == 3
+        )),
+        other => panic!("expected exit_group entry, got {other:?}"),
+    }
+}
+
+#[test]
+fn trace_syscalls_logs_filtered_calls() {
+    let target = build_target("loop", &["-no-pie"]);
+    let mut process = launch(&target);
+    let exit_group = syscall_number("exit_group").unwrap();
+    process.set_syscall_catch(Some(SyscallCatch::Only(vec![exit_group])));
+
+    let mut log = Vec::new();
+    let state = process.trace_syscalls(&mut log).unwrap();
+    assert!(matches!(state, ProcessState::Exited(3)));
+    let log = String::from_utf8(log).unwrap();
+    let lines: Vec<&str> = log.lines().collect();
+    assert_eq!(lines.len(), 2, "unexpected log: {log}");
+    assert!(lines[0].starts_with("exit_group(0x3, ") && lines[0].ends_with(") = ?"));
+    assert_eq!(lines[1], "+++ exited with 3 +++");
+}
+
+#[test]
+fn trace_syscalls_decodes_common_arguments() {
+    let target = build_target("nested", &[]);
+    let mut process = launch(&target);
+    let mut log = Vec::new();
+    process.trace_syscalls(&mut log).unwrap();
+    let log = String::from_utf8(log).unwrap();
+    // The dynamic loader opens its cache and maps libraries in.
+    assert!(
+        log.contains(r#"openat(AT_FDCWD, "/etc/ld.so.cache", O_RDONLY|O_CLOEXEC) = "#),
+        "unexpected log: {log}"
+    );
+    assert!(log.contains("PROT_READ"), "unexpected log: {log}");
+    assert!(log.contains("MAP_PRIVATE|"), "unexpected log: {log}");
+    assert!(log.contains("close(3) = 0"), "unexpected log: {log}");
+}
+
+#[test]
+fn fork_child_runs_free_when_following_parent() {
+    let target = build_target("fork", &["-no-pie"]);
+    let mut process = launch(&target);
+    // The child inherits this int3 and would die on it if it were left in.
+    process.set_breakpoint_at_function("in_child").unwrap();
+    process.resume().unwrap();
+    assert!(matches!(
+        process.wait_on_signal().unwrap(),
+        ProcessState::Exited(8)
+    ));
+}
+
+#[test]
+fn fork_child_is_debuggable_when_following_both() {
+    let target = build_target("fork", &["-no-pie"]);
+    let mut parent = launch(&target);
+    parent.set_follow_fork(FollowForkMode::Both);
+    let id = parent.set_breakpoint_at_function("in_child").unwrap();
+
+    parent.resume().unwrap();
+    let ProcessState::Stopped(reason) = parent.wait_on_signal().unwrap() else {
+        panic!("expected a fork stop");
+    };
+    let StopCause::Fork { child: child_pid } = reason.cause else {
+        panic!("expected a fork stop, got {reason:?}");
+    };
+    let mut child = parent.take_fork_child().unwrap();
+    assert_eq!(child.pid(), child_pid);
+
+    child.resume().unwrap();
+    match child.wait_on_signal().unwrap() {
+        ProcessState::Stopped(reason) => assert_eq!(reason.hit_breakpoint(), Some(id)),
+        other => panic!("expected breakpoint in child, got {other:?}"),
+    }
+    child.resume().unwrap();
+    assert!(matches!(
+        child.wait_on_signal().unwrap(),
+        ProcessState::Exited(7)
+    ));
+
+    parent.resume().unwrap();
+    assert!(matches!(
+        parent.wait_on_signal().unwrap(),
+        ProcessState::Exited(8)
+    ));
+}
+
+#[test]
+fn catchpoints_stop_on_fork_and_ignored_signals() {
+    let target = build_target("fork", &["-no-pie"]);
+    let mut process = launch(&target);
+    let fork = process.add_catchpoint(CatchKind::Fork);
+    let sigchld = process.add_catchpoint(CatchKind::Signal(Signal::SIGCHLD));
+    assert_eq!(process.catchpoints().count(), 2);
+
+    process.resume().unwrap();
+    let ProcessState::Stopped(reason) = process.wait_on_signal().unwrap() else {
+        panic!("expected a fork stop");
+    };
+    assert!(matches!(reason.cause, StopCause::Fork { .. }));
+    assert_eq!(process.last_stop().unwrap().catchpoint, Some(fork));
+    // Following the parent, the child is let go all the same.
+    assert!(process.take_fork_child().is_none());
+
+    process.resume().unwrap();
+    match process.wait_on_signal().unwrap() {
+        ProcessState::Stopped(reason) => assert_eq!(reason.signal, Signal::SIGCHLD),
+        other => panic!("expected SIGCHLD, got {other:?}"),
+    }
+    assert_eq!(process.last_stop().unwrap().catchpoint, Some(sigchld));
+    assert!(process.catchpoints().all(|cp| cp.hit_count() == 1));
+
+    process.remove_catchpoint(sigchld).unwrap();
+    process.resume().unwrap();
+    assert!(matches!(
+        process.wait_on_signal().unwrap(),
+        ProcessState::Exited(8)
+    ));
+}
+
+#[test]
+fn exec_runs_through_unless_caught() {
+    let target = build_target("nested", &[]);
+    let sh = CString::new("/bin/sh").unwrap();
+    let script = CString::new(format!("exec {}", target.display())).unwrap();
+    let dash_c = CString::new("-c").unwrap();
+    let mut process = ProcessHandle::launch(&sh, &[&sh, &dash_c, &script]).unwrap();
+    process.resume().unwrap();
+    assert!(matches!(
+        process.wait_on_signal().unwrap(),
+        ProcessState::Exited(_)
+    ));
+}
+
+#[test]
+fn exec_reloads_symbols_for_new_program() {
+    let target = build_target("nested", &[]);
+    let sh = CString::new("/bin/sh").unwrap();
+    let script = CString::new(format!("exec {}", target.display())).unwrap();
+    let dash_c = CString::new("-c").unwrap();
+    let mut process = ProcessHandle::launch(&sh, &[&sh, &dash_c, &script]).unwrap();
+    assert!(process.resolve_symbol("leaf").is_none());
+    let catch = process.add_catchpoint(CatchKind::Exec);
+
+    process.resume().unwrap();
+    match process.wait_on_signal().unwrap() {
+        ProcessState::Stopped(reason) => assert_eq!(reason.cause, StopCause::Exec),
+        other => panic!("expected an exec stop, got {other:?}"),
+    }
+    assert_eq!(process.last_stop().unwrap().catchpoint, Some(catch));
+    assert_eq!(process.executable().unwrap(), target);
+    assert_eq!(
+        process.resolve_symbol("leaf"),
+        Some(symbol_addr(&target, "leaf") + process.load_bias())
+    );
+
+    let id = process.set_breakpoint_at_function("leaf").unwrap();
+    process.resume().unwrap();
+    match process.wait_on_signal().unwrap() {
+        ProcessState::Stopped(reason) => assert_eq!(reason.hit_breakpoint(), Some(id)),
+        other => panic!("expected breakpoint in the new program, got {other:?}"),
+    }
+}
+
+#[test]
+fn breakpoints_stop_worker_threads() {
+    let target = build_target("threads", &["-pthread"]);
+    let mut process = launch(&target);
+    let id = process.set_breakpoint_at_function("work").unwrap();
+
+    let mut hit_by = BTreeSet::new();
+    for _ in 0..2 {
+        process.resume().unwrap();
+        match process.wait_on_signal().unwrap() {
+            ProcessState::Stopped(reason) => assert_eq!(reason.hit_breakpoint(), Some(id)),
+            other => panic!("expected breakpoint in a worker, got {other:?}"),
+        }
+        let tid = process.current_thread();
+        assert_ne!(tid, process.pid());
+        assert!(process.threads().len() >= 2);
+        assert!(
+            process
+                .threads()
+                .iter()
+                .all(|thread| thread.state == ThreadState::Stopped)
+        );
+        hit_by.insert(tid);
+    }
+    assert_eq!(hit_by.len(), 2);
+
+    process.resume().unwrap();
+    assert!(matches!(
+        process.wait_on_signal().unwrap(),
+        ProcessState::Exited(4)
+    ));
+}
+
+#[test]
+fn select_thread_switches_register_view() {
+    let target = build_target("threads", &["-pthread"]);
+    let mut process = launch(&target);
+    let id = process.set_breakpoint_at_function("work").unwrap();
+    process.resume().unwrap();
+    match process.wait_on_signal().unwrap() {
+        ProcessState::Stopped(reason) => assert_eq!(reason.hit_breakpoint(), Some(id)),
+        other => panic!("expected breakpoint in a worker, got {other:?}"),
+    }
+    let worker = process.current_thread();
+    let in_work = |process: &ProcessHandle| {
+        process.backtrace().unwrap()[0].function.as_deref() == Some("work")
+    };
+    assert!(in_work(&process));
+
+    process.select_thread(process.pid().as_raw()).unwrap();
+    assert_eq!(process.current_thread(), process.pid());
+    assert!(!in_work(&process));
+    assert!(matches!(
+        process.select_thread(-1),
+        Err(FdbError::UnknownThread(-1))
+    ));
+
+    // Resuming from another thread still steps the worker off its
+    // breakpoint, so each worker hits it once.
+    process.resume().unwrap();
+    match process.wait_on_signal().unwrap() {
+        ProcessState::Stopped(reason) => assert_eq!(reason.hit_breakpoint(), Some(id)),
+        other => panic!("expected breakpoint in the other worker, got {other:?}"),
+    }
+    assert_ne!(process.current_thread(), worker);
+    process.resume().unwrap();
+    assert!(matches!(
+        process.wait_on_signal().unwrap(),
+        ProcessState::Exited(4)
+    ));
+}
+
+#[test]
+fn relaunch_restores_breakpoints_in_new_process() {
+    let target = build_target("nested", &[]);
+    let mut process = launch(&target);
+    let by_name = process.set_breakpoint_at_function("leaf").unwrap();
+    let middle = process.load_bias() + symbol_addr(&target, "middle");
+    let by_addr = process.set_breakpoint(middle).unwrap();
+    let first_pid = process.pid();
+
+    process.relaunch().unwrap();
+    assert_ne!(process.pid(), first_pid);
+    let middle = process.load_bias() + symbol_addr(&target, "middle");
+    assert_eq!(
+        process.breakpoints().map(|bp| bp.id()).collect::<Vec<_>>(),
+        [by_name, by_addr]
+    );
+
+    process.resume().unwrap();
+    match process.wait_on_signal().unwrap() {
+        ProcessState::Stopped(reason) => assert_eq!(reason.hit_breakpoint(), Some(by_addr)),
+        other => panic!("expected breakpoint on middle, got {other:?}"),
+    }
+    assert_eq!(process.get_registers().unwrap().rip(), middle);
+    process.resume().unwrap();
+    match process.wait_on_signal().unwrap() {
+        ProcessState::Stopped(reason) => assert_eq!(reason.hit_breakpoint(), Some(by_name)),
+        other => panic!("expected breakpoint on leaf, got {other:?}"),
+    }
+}
+
+#[test]
+fn relaunch_requires_a_launched_process() {
+    let mut child = Command::new("sleep").arg("10").spawn().unwrap();
+    let mut process = ProcessHandle::attach(child.id() as i32).unwrap();
+    assert!(matches!(process.relaunch(), Err(FdbError::NotLaunched)));
+    drop(process);
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
+
+#[test]
+fn launch_checks_the_program_before_forking() {
+    let not_found = |program: &str, options: &LaunchOptions| {
+        let program = CString::new(program).unwrap();
+        match ProcessHandle::launch_with_options(&program, &[program.as_c_str()], options) {
+            Err(FdbError::ProgramNotFound(name)) => assert_eq!(name, program.to_str().unwrap()),
+            other => panic!("expected ProgramNotFound, got {other:?}"),
+        }
+    };
+    let target = build_target("loop", &["-no-pie"]);
+    let dir = target.parent().unwrap();
+    let defaults = LaunchOptions::default();
+    not_found("/nonexistent/program", &defaults);
+    not_found("fdb-no-such-program", &defaults);
+    not_found(dir.to_str().unwrap(), &defaults);
+    not_found(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../../tests/targets/loop.c"),
+        &defaults,
+    );
+
+    // Names without a slash are looked up in PATH, and relative paths are
+    // taken from the working directory the program gets.
+    let name = CString::new("true").unwrap();
+    ProcessHandle::launch(&name, &[name.as_c_str()]).unwrap();
+    let relative = CString::new(format!(
+        "./{}",
+        target.file_name().unwrap().to_str().unwrap()
+    ))
+    .unwrap();
+    let options = LaunchOptions {
+        cwd: Some(dir.to_path_buf()),
+        ..LaunchOptions::default()
+    };
+    ProcessHandle::launch_with_options(&relative, &[relative.as_c_str()], &options).unwrap();
+    not_found(relative.to_str().unwrap(), &defaults);
+}
+
+#[test]
+fn launch_reports_why_the_child_failed() {
+    let launch_error =
+        |args: &[&CStr], options: &LaunchOptions| match ProcessHandle::launch_with_options(
+            args[0], args, options,
+        ) {
+            Err(FdbError::LaunchFailed(message)) => message,
+            other => panic!("expected LaunchFailed, got {other:?}"),
+        };
+    let target = build_target("loop", &["-no-pie"]);
+    let program = CString::new(target.to_str().unwrap()).unwrap();
+
+    let options = LaunchOptions {
+        cwd: Some("/nonexistent".into()),
+        ..LaunchOptions::default()
+    };
+    assert_eq!(
+        launch_error(&[&program], &options),
+        "could not change to the working directory: No such file or directory"
+    );
+
+    // A single argument longer than the kernel allows fails the exec itself.
+    let long = CString::new("x".repeat(256 * 1024)).unwrap();
+    assert_eq!(
+        launch_error(&[&program, &long], &LaunchOptions::default()),
+        "could not execute program: Argument list too long"
+    );
+}
+
+#[test]
+fn processes_are_found_by_name() {
+    let target = build_target("spin", &["-no-pie"]);
+    let process = launch(&target);
+    let pid = process.pid().as_raw();
+    let name = target.file_name().unwrap().to_str().unwrap();
+    assert!(find_pid_by_name(name).unwrap().contains(&pid));
+    assert!(find_pid_by_name("spin-no").unwrap().contains(&pid));
+    assert_eq!(command_line(pid).unwrap(), target.to_str().unwrap());
+    assert!(find_pid_by_name("fdb-no-such-process").unwrap().is_empty());
+}
+
+#[test]
+fn requested_interrupt_stops_a_blocking_wait() {
+    let target = build_target("spin", &["-no-pie"]);
+    let mut process = launch(&target);
+    process.resume().unwrap();
+    // As a SIGINT handler would, while the debugger is blocked waiting.
+    let pid = process.pid();
+    let interrupter = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(100));
+        request_interrupt(pid);
+    });
+    let state = process.wait_on_signal().unwrap();
+    interrupter.join().unwrap();
+    assert!(matches!(
+        state,
+        ProcessState::Stopped(StopReason {
+            signal: Signal::SIGSTOP,
+            cause: StopCause::Interrupted,
+        })
+    ));
+    assert_eq!(
+        process.last_stop().unwrap().function.as_deref(),
+        Some("main")
+    );
+
+    // The SIGSTOP is not passed on, so the loop carries on spinning.
+    process.resume().unwrap();
+    assert!(
+        process
+            .wait_on_signal_timeout(Duration::from_millis(100))
+            .unwrap()
+            .is_none()
+    );
+    process.interrupt().unwrap();
+    assert!(matches!(
+        process.wait_on_signal().unwrap(),
+        ProcessState::Stopped(StopReason {
+            cause: StopCause::Interrupted,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn wait_with_timeout_gives_up_on_a_spinning_program() {
+    let target = build_target("spin", &["-no-pie"]);
+    let mut process = launch(&target);
+    process.resume().unwrap();
+    let started = std::time::Instant::now();
+    let waited = process
+        .wait_on_signal_timeout(Duration::from_millis(50))
+        .unwrap();
+    assert!(waited.is_none());
+    assert!(started.elapsed() >= Duration::from_millis(50));
+
+    // Interrupting stops it where it was spinning.
+    process.send_signal(Signal::SIGSTOP).unwrap();
+    let state = process
+        .wait_on_signal_timeout(Duration::from_secs(5))
+        .unwrap();
+    assert!(matches!(
+        state,
+        Some(ProcessState::Stopped(StopReason {
+            signal: Signal::SIGSTOP,
+            ..
+        }))
+    ));
+    assert_eq!(
+        process.last_stop().unwrap().function.as_deref(),
+        Some("main")
+    );
+
+    let target = build_target("loop", &["-no-pie"]);
+    let mut process = launch(&target);
+    process.resume().unwrap();
+    assert!(matches!(
+        process.wait_on_signal_timeout(Duration::from_secs(5)),
+        Ok(Some(ProcessState::Exited(3)))
+    ));
+}
+
+#[test]
+fn interrupting_a_running_program_is_told_apart_from_a_sent_sigstop() {
+    let target = build_target("spin", &["-no-pie"]);
+    let mut process = launch(&target);
+    process.resume().unwrap();
+    // Let it get past the loader into the spin loop.
+    std::thread::sleep(Duration::from_millis(50));
+    process.interrupt().unwrap();
+    let state = process
+        .wait_on_signal_timeout(Duration::from_secs(5))
+        .unwrap();
+    assert!(matches!(
+        state,
+        Some(ProcessState::Stopped(StopReason {
+            signal: Signal::SIGSTOP,
+            cause: StopCause::Interrupted,
+        }))
+    ));
+    assert_eq!(
+        process.last_stop().unwrap().function.as_deref(),
+        Some("main")
+    );
+
+    // The same signal sent any other way is an ordinary signal stop.
+    process.resume().unwrap();
+    process.send_signal(Signal::SIGSTOP).unwrap();
+    let state = process
+        .wait_on_signal_timeout(Duration::from_secs(5))
+        .unwrap();
+    assert!(matches!(
+        state,
+        Some(ProcessState::Stopped(StopReason {
+            signal: Signal::SIGSTOP,
+            cause: StopCause::Signal,
+        }))
+    ));
+}
+
+#[test]
+fn exited_process_reports_typed_errors() {
+    let target = build_target("loop", &["-no-pie"]);
+    let mut process = launch(&target);
+    process.resume().unwrap();
+    assert!(matches!(
+        process.wait_on_signal().unwrap(),
+        ProcessState::Exited(3)
+    ));
+    assert_eq!(process.exit_status(), Some(3));
+    assert_eq!(process.termination_signal(), None);
+
+    assert!(matches!(process.resume(), Err(FdbError::ProcessExited(3))));
+    assert!(matches!(
+        process.single_step(),
+        Err(FdbError::ProcessExited(3))
+    ));
+    assert!(matches!(
+        process.get_registers(),
+        Err(FdbError::ProcessExited(3))
+    ));
+    assert!(matches!(
+        process.read_memory(symbol_addr(&target, "tick"), 4),
+        Err(FdbError::ProcessExited(3))
+    ));
+    assert!(matches!(
+        process.set_breakpoint(symbol_addr(&target, "tick")),
+        Err(FdbError::ProcessExited(3))
+    ));
+}
+
+#[test]
+fn caught_exit_stops_before_the_process_is_gone() {
+    let target = build_target("threads", &["-pthread"]);
+    let mut process = launch(&target);
+    process.set_catch_exit(true);
+    process.resume().unwrap();
+    // Worker threads finishing do not stop; only the process exit does.
+    let state = process.wait_on_signal().unwrap();
+    assert!(matches!(
+        state,
+        ProcessState::Stopped(StopReason {
+            cause: StopCause::Exiting(ExitStatus::Code(4)),
+            ..
+        })
+    ));
+    assert_eq!(process.last_stop().unwrap().tid, process.pid());
+    assert!(process.get_registers().is_ok());
+    process.resume().unwrap();
+    assert!(matches!(
+        process.wait_on_signal().unwrap(),
+        ProcessState::Exited(4)
+    ));
+
+    let target = build_target("loop", &["-no-pie"]);
+    let mut process = launch(&target);
+    process.set_catch_exit(true);
+    nix::sys::signal::kill(process.pid(), Signal::SIGTERM).unwrap();
+    process.resume().unwrap();
+    assert!(matches!(
+        process.wait_on_signal().unwrap(),
+        ProcessState::Stopped(StopReason {
+            signal: Signal::SIGTERM,
+            cause: StopCause::Signal,
+        })
+    ));
+    process.resume().unwrap();
+    assert!(matches!(
+        process.wait_on_signal().unwrap(),
+        ProcessState::Stopped(StopReason {
+            cause: StopCause::Exiting(ExitStatus::Signal(Signal::SIGTERM)),
+            ..
+        })
+    ));
+    process.resume().unwrap();
+    process.wait_on_signal().unwrap();
+    assert_eq!(process.termination_signal(), Some(Signal::SIGTERM));
+}
+
+#[test]
+fn sent_signal_is_handled_on_resume() {
+    let target = build_target("loop", &["-no-pie"]);
+    let mut process = launch(&target);
+    process.send_signal(Signal::SIGUSR1).unwrap();
+    process.resume().unwrap();
+    assert!(matches!(
+        process.wait_on_signal().unwrap(),
+        ProcessState::Stopped(StopReason {
+            signal: Signal::SIGUSR1,
+            cause: StopCause::Signal,
+        })
+    ));
+    let info = process.siginfo().unwrap();
+    assert_eq!(info.code_name(), Some("SI_USER"));
+    assert_eq!(info.pid, Some(std::process::id() as i32));
+    assert_eq!(process.last_stop().unwrap().siginfo, Some(info));
+
+    // Passed on under the default policy, SIGUSR1 kills the program.
+    process.resume().unwrap();
+    process.wait_on_signal().unwrap();
+    assert_eq!(process.termination_signal(), Some(Signal::SIGUSR1));
+    assert!(matches!(
+        process.send_signal(Signal::SIGUSR1),
+        Err(FdbError::NotRunning)
+    ));
+}
+
+#[test]
+fn segfault_stop_reports_the_faulting_address() {
+    let target = build_target("segv", &["-no-pie"]);
+    let mut process = launch(&target);
+    process.resume().unwrap();
+    process.wait_on_signal().unwrap();
+
+    let info = process.last_stop().unwrap().siginfo.unwrap();
+    assert_eq!(info.signal, Signal::SIGSEGV);
+    assert_eq!(info.addr, Some(0x10));
+    assert_eq!(info.to_string(), "SEGV_MAPERR, address 0x10");
+}
+
+#[test]
+fn killed_process_is_not_being_run() {
+    let target = build_target("loop", &["-no-pie"]);
+    let mut process = launch(&target);
+    assert_eq!(process.termination_signal(), None);
+    process.kill().unwrap();
+    assert_eq!(process.termination_signal(), Some(Signal::SIGKILL));
+    assert_eq!(process.exit_status(), None);
+
+    assert!(matches!(process.resume(), Err(FdbError::NotRunning)));
+    assert!(matches!(process.kill(), Err(FdbError::NotRunning)));
+}
+
+#[test]
+fn expressions_follow_c_precedence() {
+    let target = build_target("loop", &["-no-pie"]);
+    let process = launch(&target);
+    let eval = |src: &str| process.evaluate_str(src).unwrap();
+    assert_eq!(eval("1 + 2 * 3"), 7);
+    assert_eq!(eval("(1 + 2) * 3"), 9);
+    assert_eq!(eval("-8 >> 1 == -4 && !0"), 1);
+    assert_eq!(eval("0x10 | 1 ^ 3 & 6"), 0x13);
+    assert_eq!(eval("$pc - $rip"), 0);
+    assert_eq!(eval("main"), symbol_addr(&target, "main") as i64);
+    let sp = process.get_registers().unwrap().rsp();
+    let top = process.read_memory(sp, 8).unwrap();
+    assert_eq!(eval("*$sp"), i64::from_le_bytes(top.try_into().unwrap()));
+
+    for bad in ["1 +", "(1", "1 2", "$", "#"] {
+        assert!(
+            matches!(Expr::parse(bad), Err(FdbError::InvalidExpression(_))),
+            "{bad:?} should not parse"
+        );
+    }
+    assert!(matches!(
+        process.evaluate_str("1 / 0"),
+        Err(FdbError::InvalidExpression(_))
+    ));
+}
+
+#[test]
+fn convenience_variables_feed_later_expressions() {
+    let target = build_target("loop", &["-no-pie"]);
+    let mut process = launch(&target);
+    assert!(matches!(
+        process.evaluate_str("$base"),
+        Err(FdbError::UnsetConvenienceVariable(name)) if name == "base"
+    ));
+    let base = process.evaluate_str("$sp + 8").unwrap();
+    process.set_convenience_var("base", base).unwrap();
+    process.set_convenience_var("$two", 2).unwrap();
+    assert_eq!(process.evaluate_str("$base - $sp").unwrap(), 8);
+    assert_eq!(process.evaluate_str("$base + $two * 2").unwrap(), base + 4);
+    for taken in ["rax", "pc", "$sp", "1x", "a b"] {
+        assert!(
+            process.set_convenience_var(taken, 0).is_err(),
+            "{taken:?} should not be assignable"
+        );
+    }
+
+    process.relaunch().unwrap();
+    assert_eq!(process.convenience_var("base"), Some(base));
+    let names: Vec<_> = process.convenience_vars().map(|(name, _)| name).collect();
+    assert_eq!(names, ["base", "two"]);
+}
+
+#[test]
+fn conditional_breakpoint_skips_false_hits() {
+    let target = build_target("nested", &[]);
+    let mut process = launch(&target);
+    let id = process.set_breakpoint_at_function("leaf").unwrap();
+    process.set_breakpoint_condition(id, "$rdi == 3").unwrap();
+    assert_eq!(
+        process.breakpoints().next().unwrap().condition(),
+        Some("$rdi == 3")
+    );
+
+    // leaf is called with 1, then 3.
+    process.resume().unwrap();
+    match process.wait_on_signal().unwrap() {
+        ProcessState::Stopped(reason) => assert_eq!(reason.hit_breakpoint(), Some(id)),
+        other => panic!("expected conditional breakpoint hit, got {other:?}"),
+    }
+    assert_eq!(process.get_registers().unwrap().rdi(), 3);
+
+    // A condition that cannot be evaluated stops and reports why.
+    process.relaunch().unwrap();
+    process.set_breakpoint_condition(id, "*0 == 1").unwrap();
+    process.resume().unwrap();
+    match process.wait_on_signal().unwrap() {
+        ProcessState::Stopped(reason) => assert_eq!(reason.hit_breakpoint(), Some(id)),
+        other => panic!("expected a stop on the failing condition, got {other:?}"),
+    }
+    let errors = process.take_condition_errors();
+    assert!(matches!(errors[..], [(bp, FdbError::PartialRead { .. })] if bp == id));
+
+    process.set_breakpoint_condition(id, "").unwrap();
+    assert_eq!(process.breakpoints().next().unwrap().condition(), None);
+}
+
+#[test]
+fn ignore_count_only_spends_hits_passing_the_condition() {
+    let target = build_target("loop", &["-no-pie"]);
+    let mut process = launch(&target);
+    let counter = symbol_addr(&target, "counter");
+    let id = process
+        .set_breakpoint(symbol_addr(&target, "tick"))
+        .unwrap();
+    // counter is 0, 1, 2 on the three calls; the first fails the condition
+    // and the second is ignored.
+    process
+        .set_breakpoint_condition(id, &format!("(*{counter:#x} & 0xffffffff) >= 1"))
+        .unwrap();
+    process.set_ignore_count(id, 1).unwrap();
+
+    process.resume().unwrap();
+    match process.wait_on_signal().unwrap() {
+        ProcessState::Stopped(reason) => assert_eq!(reason.hit_breakpoint(), Some(id)),
+        other => panic!("expected breakpoint stop, got {other:?}"),
+    }
+    let value = process.read_memory(counter, 4).unwrap();
+    assert_eq!(u32::from_le_bytes(value.try_into().unwrap()), 2);
+    assert_eq!(process.breakpoints().next().unwrap().ignore_count(), 0);
+
+    process.resume().unwrap();
+    assert!(matches!(
+        process.wait_on_signal().unwrap(),
+        ProcessState::Exited(3)
+    ));
+}
+
+#[test]
+fn temporary_breakpoint_deletes_itself_on_first_hit() {
+    let target = build_target("loop", &["-no-pie"]);
+    let mut process = launch(&target);
+    let tick = symbol_addr(&target, "tick");
+    let id = process.set_temp_breakpoint(tick).unwrap();
+    assert!(process.breakpoints().next().unwrap().is_temporary());
+
+    process.resume().unwrap();
+    match process.wait_on_signal().unwrap() {
+        ProcessState::Stopped(reason) => {
+            assert_eq!(reason.cause, StopCause::TemporaryBreakpoint(id));
+            assert_eq!(reason.hit_breakpoint(), Some(id));
+        }
+        other => panic!("expected temporary breakpoint stop, got {other:?}"),
+    }
+    assert_eq!(process.get_registers().unwrap().rip(), tick);
+    assert_eq!(process.breakpoints().count(), 0);
+
+    // The other two calls run through, and the first one's instruction
+    // executes normally.
+    process.resume().unwrap();
+    assert!(matches!(
+        process.wait_on_signal().unwrap(),
+        ProcessState::Exited(3)
+    ));
+}
+
+#[test]
+fn hit_counts_include_ignored_hits_and_reset_on_relaunch() {
+    let target = build_target("loop", &["-no-pie"]);
+    let mut process = launch(&target);
+    let id = process
+        .set_breakpoint(symbol_addr(&target, "tick"))
+        .unwrap();
+    assert_eq!(process.breakpoint_hit_count(id), Some(0));
+    assert_eq!(process.breakpoint_hit_count(id + 1), None);
+    process.set_ignore_count(id, 1).unwrap();
+
+    process.resume().unwrap();
+    assert!(matches!(
+        process.wait_on_signal().unwrap(),
+        ProcessState::Stopped(_)
+    ));
+    assert_eq!(process.breakpoint_hit_count(id), Some(2));
+
+    process.relaunch().unwrap();
+    assert_eq!(process.breakpoint_hit_count(id), Some(0));
+}
+
+#[test]
+fn disassembly_hides_breakpoints_and_names_call_targets() {
+    let target = build_target("loop", &["-no-pie"]);
+    let mut process = launch(&target);
+    let tick = symbol_addr(&target, "tick");
+    process.set_breakpoint(tick).unwrap();
+
+    let insns = process.disassemble(tick, 2).unwrap();
+    assert_eq!(insns.len(), 2);
+    assert_eq!(insns[0].address, tick);
+    assert_eq!(insns[0].bytes, [0x55]);
+    assert_eq!(insns[0].mnemonic, "push");
+    assert_eq!(insns[1].address, tick + 1);
+
+    let main = process
+        .disassemble(symbol_addr(&target, "main"), 12)
+        .unwrap();
+    let call = main.iter().find(|insn| insn.mnemonic == "call").unwrap();
+    assert_eq!(call.target, Some(tick));
+    assert_eq!(call.target_symbol.as_deref(), Some("tick"));
+    let jump = main.iter().find(|insn| insn.mnemonic == "jle").unwrap();
+    assert!(jump.target_symbol.as_deref().unwrap().starts_with("main+"));
+
+    process.set_asm_syntax(AsmSyntax::Att);
+    let insns = process.disassemble(tick, 1).unwrap();
+    assert_eq!(
+        (insns[0].mnemonic.as_str(), insns[0].operands.as_str()),
+        ("pushq", "%rbp")
+    );
+}
+
+#[test]
+fn plt_stubs_are_named_after_their_functions() {
+    let target = build_target("fork", &["-no-pie"]);
+    let process = launch(&target);
+    let stub = process.resolve_symbol("fork@plt").unwrap();
+    assert_eq!(process.symbolize(stub).as_deref(), Some("fork@plt"));
+    assert_eq!(process.symbolize(stub + 6).as_deref(), Some("fork@plt+6"));
+    assert_eq!(process.symbolize(0x10), None);
+
+    let main = process.disassemble_function("main").unwrap();
+    let call = main
+        .iter()
+        .find(|insn| insn.target == Some(stub))
+        .expect("main calls fork through the PLT");
+    assert_eq!(call.target_symbol.as_deref(), Some("fork@plt"));
+}
+
+#[test]
+fn disassemble_function_covers_the_whole_body() {
+    let target = build_target("loop", &["-no-pie"]);
+    let process = launch(&target);
+    let main = process.symbol_at(symbol_addr(&target, "main")).unwrap();
+    let (start, size) = (main.address, main.size);
+
+    let insns = process.disassemble_function("main").unwrap();
+    assert_eq!(insns.first().unwrap().address, start);
+    assert_eq!(insns.last().unwrap().mnemonic, "ret");
+    let len: usize = insns.iter().map(|insn| insn.bytes.len()).sum();
+    assert_eq!(len as u64, size);
+
+    assert!(matches!(
+        process.disassemble_function("no_such_function"),
+        Err(FdbError::UnknownSymbol(_))
+    ));
+}
+
+#[test]
+fn read_variable_formats_locals_and_globals() {
+    let target = build_target("vars", &[]);
+    let mut process = launch(&target);
+    // Inside the inner block of compute(3, 4).
+    let id = process.set_breakpoint_at_line("vars.c", 16).unwrap();
+    process.resume().unwrap();
+    match process.wait_on_signal().unwrap() {
+        ProcessState::Stopped(reason) => assert_eq!(reason.hit_breakpoint(), Some(id)),
+        other => panic!("expected breakpoint stop, got {other:?}"),
+    }
+
+    let show = |name: &str| process.read_variable(name).unwrap().to_string();
+    assert_eq!(show("a"), "3");
+    assert_eq!(show("b"), "4");
+    assert_eq!(show("sum"), "7");
+    assert_eq!(show("letter"), "120 'x'");
+    assert_eq!(show("ratio"), "3.5");
+    assert_eq!(show("inner"), "14");
+    assert_eq!(show("global_int"), "-42");
+    assert_eq!(show("global_ulong"), "123456789");
+    assert_eq!(show("global_double"), "2.5");
+    assert_eq!(show("global_float"), "1.5");
+    assert_eq!(show("global_name"), "\"fdb\"");
+    assert_eq!(show("global_flag"), "true");
+    assert_eq!(show("global_shorts"), "{1, -2, 3}");
+
+    let msg = process.read_variable("global_msg").unwrap();
+    assert_eq!(msg.ty.to_string(), "char *");
+    let addr = u64::from_le_bytes(msg.bytes.try_into().unwrap());
+    assert_eq!(process.read_memory(addr, 6).unwrap(), b"hello\0");
+
+    assert!(matches!(
+        process.read_variable("missing"),
+        Err(FdbError::UnknownVariable(_))
+    ));
+}
+
+#[test]
+fn locals_follow_lexical_blocks() {
+    let target = build_target("vars", &[]);
+    let mut process = launch(&target);
+    let names = |process: &ProcessHandle| -> Vec<String> {
+        process
+            .locals()
+            .unwrap()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect()
+    };
+    process.set_breakpoint_at_line("vars.c", 16).unwrap();
+    process.set_breakpoint_at_line("vars.c", 18).unwrap();
+
+    process.resume().unwrap();
+    process.wait_on_signal().unwrap();
+    assert_eq!(names(&process), ["inner", "sum", "letter", "ratio"]);
+    let (_, inner) = &process.locals().unwrap()[0];
+    assert_eq!(inner.to_string(), "14");
+
+    // Past the block, only the function-level locals remain.
+    process.resume().unwrap();
+    process.wait_on_signal().unwrap();
+    assert_eq!(names(&process), ["sum", "letter", "ratio"]);
+}
+
+#[test]
+fn locals_skip_values_optimized_out() {
+    let target = build_target("vars", &["-O2"]);
+    let mut process = launch(&target);
+    process.set_breakpoint_at_function("compute").unwrap();
+    process.resume().unwrap();
+    process.wait_on_signal().unwrap();
+    // Whatever survives optimization is listed without failing on the rest.
+    let locals = process.locals().unwrap();
+    assert!(locals.iter().all(|(name, _)| name != "a" && name != "b"));
+}
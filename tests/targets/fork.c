@@ -0,0 +1,14 @@
+#include <sys/wait.h>
+#include <unistd.h>
+
+__attribute__((noinline)) int in_child(void) { return 7; }
+
+int main(void) {
+    pid_t pid = fork();
+    if (pid == 0) {
+        return in_child();
+    }
+    int status;
+    waitpid(pid, &status, 0);
+    return WEXITSTATUS(status) + 1;
+}
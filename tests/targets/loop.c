@@ -0,0 +1,10 @@
+int counter;
+
+void tick(void) { counter++; }
+
+int main(void) {
+    for (int i = 0; i < 3; i++) {
+        tick();
+    }
+    return counter;
+}
@@ -0,0 +1,18 @@
+/* A short call chain that keeps its frames when optimized. */
+volatile int sink;
+
+__attribute__((noinline)) int leaf(int x) {
+    sink = x;
+    return x * 3;
+}
+
+__attribute__((noinline)) int middle(int x) {
+    int a = leaf(x);
+    int b = leaf(a);
+    return a + b;
+}
+
+int main(void) {
+    sink = middle(1);
+    return 0;
+}
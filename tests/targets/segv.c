@@ -0,0 +1,5 @@
+int *volatile target = (int *)0x10;
+
+int main(void) {
+    return *target;
+}
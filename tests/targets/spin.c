@@ -0,0 +1,7 @@
+volatile unsigned long spins;
+
+int main(void) {
+    for (;;) {
+        spins++;
+    }
+}
@@ -0,0 +1,22 @@
+/* Two worker threads that each pass through `work` once. */
+#include <pthread.h>
+
+volatile int sink;
+
+__attribute__((noinline)) void work(long n) {
+    sink = n;
+}
+
+static void *run(void *arg) {
+    work((long)arg);
+    return 0;
+}
+
+int main(void) {
+    pthread_t threads[2];
+    for (long i = 0; i < 2; i++)
+        pthread_create(&threads[i], 0, run, (void *)i);
+    for (int i = 0; i < 2; i++)
+        pthread_join(threads[i], 0);
+    return 4;
+}
@@ -0,0 +1,23 @@
+int global_int = -42;
+unsigned long global_ulong = 123456789;
+double global_double = 2.5;
+float global_float = 1.5f;
+char global_name[] = "fdb";
+const char *global_msg = "hello";
+_Bool global_flag = 1;
+short global_shorts[3] = {1, -2, 3};
+
+__attribute__((noinline)) int compute(int a, long b) {
+    int sum = a + (int)b;
+    char letter = 'x';
+    double ratio = sum / 2.0;
+    {
+        int inner = sum * 2;
+        global_int = inner;
+    }
+    return sum + letter + (int)ratio;
+}
+
+int main(void) {
+    return compute(3, 4) & 0x7f;
+}